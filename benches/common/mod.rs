@@ -0,0 +1,28 @@
+//! Timing shared by the benchmarks, which are plain binaries (`harness = false`), so that every one
+//! measures the same way.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+/// Returns the fastest of several runs.
+pub fn measure<R>(mut f: impl FnMut() -> R) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..10 {
+        let start = Instant::now();
+        black_box(f());
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+/// Prints a row comparing two ways of doing the same thing, under a header naming them.
+#[allow(dead_code, reason = "not every benchmark compares exactly two things")]
+pub fn report(name: &str, a: Duration, b: Duration) {
+    println!(
+        "{name:<32} {:>12} {:>12}",
+        format!("{a:.2?}"),
+        format!("{b:.2?}")
+    );
+}
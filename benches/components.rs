@@ -0,0 +1,111 @@
+//! Compares updating boxed-component `GameObject`s with a `ComponentManager` of the same
+//! components.
+//!
+//! Each `GameObject` (itself boxed, as a game would hold a list of pointers to its entities) finds
+//! its components through pointers, and updates all three before moving to the next object. The
+//! manager walks one contiguous array per kind of component instead:
+//!
+//! ```bash
+//! cargo bench --bench components
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+
+use common::{measure, report};
+use game_patterns_rs::component::{
+    ComponentManager, GameObject, GraphicsComponent, InputComponent, PhysicsComponent,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+const OBJECTS: usize = 100_000;
+
+#[derive(Clone, Copy, Default)]
+struct Body {
+    position: [f32; 2],
+    velocity: [f32; 2],
+}
+
+/// Steers in a circle.
+struct Wander {
+    turn: f32,
+}
+
+impl InputComponent<Body, f32> for Wander {
+    fn update(&mut self, body: &mut Body, dt: &f32) {
+        let [x, y] = body.velocity;
+        let (sin, cos) = (self.turn * dt).sin_cos();
+        body.velocity = [x * cos - y * sin, x * sin + y * cos];
+    }
+}
+
+struct Move {
+    drag: f32,
+}
+
+impl PhysicsComponent<Body, f32> for Move {
+    fn update(&mut self, body: &mut Body, dt: &f32) {
+        for (position, velocity) in body.position.iter_mut().zip(&mut body.velocity) {
+            *velocity *= 1.0 - self.drag * dt;
+            *position += *velocity * dt;
+        }
+    }
+}
+
+struct Sprite {
+    frame: u32,
+}
+
+impl GraphicsComponent<Body, f32> for Sprite {
+    fn update(&mut self, _: &mut Body, _: &f32) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+}
+
+fn main() {
+    let mut objects: Vec<_> = (0..OBJECTS)
+        .map(|i| {
+            Box::new(GameObject::new(
+                body(i),
+                wander(i),
+                Move { drag: 0.1 },
+                Sprite { frame: 0 },
+            ))
+        })
+        .collect();
+    // Entities are created and destroyed over a game's life, so they aren't updated in the order
+    // they were allocated in.
+    objects.shuffle(&mut StdRng::seed_from_u64(7));
+
+    let mut manager = ComponentManager::new();
+    for i in 0..OBJECTS {
+        manager.spawn(body(i), wander(i), Move { drag: 0.1 }, Sprite { frame: 0 });
+    }
+
+    println!("{OBJECTS} objects, with best of 10 runs:");
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "GameObject", "manager");
+    report(
+        "update",
+        measure(|| {
+            for object in &mut objects {
+                object.update(black_box(&(1.0 / 60.0)));
+            }
+        }),
+        measure(|| manager.update(black_box(&(1.0 / 60.0)))),
+    );
+}
+
+fn body(i: usize) -> Body {
+    Body {
+        velocity: [1.0, i as f32 / OBJECTS as f32],
+        ..Body::default()
+    }
+}
+
+fn wander(i: usize) -> Wander {
+    Wander {
+        turn: (i % 7) as f32 * 0.1,
+    }
+}
@@ -0,0 +1,101 @@
+//! Compares the two ECS storages: sparse sets (`ecs::World`) and archetypes (`ecs::archetype`).
+//!
+//! Sparse sets make adding and removing components cheap; archetypes make iterating cheap. Which
+//! matters more depends on the game, which is why it's worth measuring:
+//!
+//! ```bash
+//! cargo bench --bench ecs
+//! ```
+
+mod common;
+
+use common::{measure, report};
+use game_patterns_rs::ecs::{archetype::ArchetypeWorld, Entity, World};
+
+const ENTITIES: usize = 10_000;
+
+struct Position(f32, f32);
+struct Velocity(f32, f32);
+struct Health(#[allow(dead_code)] u8);
+struct Frozen;
+
+fn main() {
+    let (mut sparse, sparse_entities) = sparse_world();
+    let (mut archetype, archetype_entities) = archetype_world();
+
+    println!("{ENTITIES} entities, with best of 10 runs:");
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "sparse set", "archetype");
+
+    report(
+        "iterate (position += velocity)",
+        measure(|| {
+            let mut positions = sparse.storage_mut::<Position>();
+            let velocities = sparse.storage::<Velocity>();
+            for (_, position, velocity) in positions.join_mut(&velocities) {
+                position.0 += velocity.0;
+                position.1 += velocity.1;
+            }
+        }),
+        measure(|| {
+            archetype.for_each_mut(|_, position: &mut Position, velocity: &Velocity| {
+                position.0 += velocity.0;
+                position.1 += velocity.1;
+            });
+        }),
+    );
+
+    report(
+        "insert and remove a component",
+        measure(|| {
+            for &entity in &sparse_entities {
+                sparse.insert(entity, Frozen);
+            }
+            for &entity in &sparse_entities {
+                sparse.remove::<Frozen>(entity);
+            }
+        }),
+        measure(|| {
+            for &entity in &archetype_entities {
+                archetype.insert(entity, Frozen);
+            }
+            for &entity in &archetype_entities {
+                archetype.remove::<Frozen>(entity);
+            }
+        }),
+    );
+}
+
+/// Every entity moves; some also have health, so that they aren't all stored alike.
+fn sparse_world() -> (World, Vec<Entity>) {
+    let mut world = World::new();
+    let entities = (0..ENTITIES)
+        .map(|i| {
+            let entity = world.spawn();
+            world.insert(entity, Position(0.0, 0.0));
+            world.insert(entity, Velocity(1.0, i as f32));
+            if i % 3 == 0 {
+                world.insert(entity, Health(100));
+            }
+            entity
+        })
+        .collect();
+    (world, entities)
+}
+
+/// The same entities as [`sparse_world`].
+fn archetype_world() -> (ArchetypeWorld, Vec<Entity>) {
+    let mut world = ArchetypeWorld::new();
+    let entities = (0..ENTITIES)
+        .map(|i| {
+            let entity = world.spawn();
+            world.insert(entity, Position(0.0, 0.0));
+            world.insert(entity, Velocity(1.0, i as f32));
+            if i % 3 == 0 {
+                world.insert(entity, Health(100));
+            }
+            entity
+        })
+        .collect();
+    (world, entities)
+}
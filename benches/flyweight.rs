@@ -0,0 +1,83 @@
+//! Compares reading terrain stored per tile with terrain shared between tiles as flyweights, and
+//! comparing names as strings with comparing them as interned `Symbol`s.
+//!
+//! A flyweight makes each tile a pointer (or, with a palette, a byte) instead of a full copy of its
+//! terrain, so far more tiles fit in the cache:
+//!
+//! ```bash
+//! cargo bench --bench flyweight
+//! ```
+
+mod common;
+
+use common::{measure, report};
+use game_patterns_rs::symbol::Symbol;
+
+const TILES: usize = 256 * 256;
+
+/// What every tile of one kind of terrain has in common.
+#[derive(Clone)]
+struct TerrainData {
+    #[allow(dead_code)]
+    name: String,
+    movement_cost: u8,
+    is_water: bool,
+    #[allow(dead_code)]
+    texture: [u32; 4],
+}
+
+fn main() {
+    let palette: Vec<TerrainData> = [("grass", 1, false), ("hill", 3, false), ("river", 2, true)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, movement_cost, is_water))| TerrainData {
+            name: name.to_string(),
+            movement_cost,
+            is_water,
+            texture: [i as u32; 4],
+        })
+        .collect();
+    let kind = |i: usize| (i * 7 + i / 256) % palette.len();
+
+    let owned: Vec<TerrainData> = (0..TILES).map(|i| palette[kind(i)].clone()).collect();
+    let references: Vec<&TerrainData> = (0..TILES).map(|i| &palette[kind(i)]).collect();
+    let indices: Vec<u8> = (0..TILES).map(|i| kind(i) as u8).collect();
+
+    let cost = |terrain: &TerrainData| terrain.movement_cost as u32 + terrain.is_water as u32;
+
+    println!("{TILES} tiles, with best of 10 runs:");
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "owned", "flyweight");
+    report(
+        "sum costs (a reference per tile)",
+        measure(|| owned.iter().map(cost).sum::<u32>()),
+        measure(|| references.iter().map(|&terrain| cost(terrain)).sum::<u32>()),
+    );
+    report(
+        "sum costs (an index per tile)",
+        measure(|| owned.iter().map(cost).sum::<u32>()),
+        measure(|| {
+            indices
+                .iter()
+                .map(|&i| cost(&palette[i as usize]))
+                .sum::<u32>()
+        }),
+    );
+
+    let names = ["goblin", "goblin archer", "goblin shaman", "troll"];
+    let strings: Vec<String> = (0..TILES).map(|i| names[i % 4].to_string()).collect();
+    let symbols: Vec<Symbol> = (0..TILES).map(|i| Symbol::intern(names[i % 4])).collect();
+    let goblin = Symbol::intern("goblin");
+    report(
+        "count names (String vs Symbol)",
+        measure(|| strings.iter().filter(|name| *name == "goblin").count()),
+        measure(|| symbols.iter().filter(|&&name| name == goblin).count()),
+    );
+
+    println!();
+    println!(
+        "A tile is {} bytes owned, {} as a reference, and 1 as an index",
+        size_of::<TerrainData>(),
+        size_of::<&TerrainData>()
+    );
+}
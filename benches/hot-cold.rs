@@ -0,0 +1,55 @@
+//! Compares updating AI components with their loot stored inline, and split off into its own
+//! allocation.
+//!
+//! The update never reads the loot, so splitting it off means each cache line holds more of the
+//! data that the update does read:
+//!
+//! ```bash
+//! cargo bench --bench hot-cold
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+
+use common::{measure, report};
+use game_patterns_rs::ai::{AIComponent, LootDrop, LootType, SplitAIComponent};
+
+const COUNT: usize = 100_000;
+
+fn main() {
+    let loot = LootDrop {
+        drop: LootType::Potion,
+        min_drops: 1,
+        max_drops: 2,
+        chance_of_drop: 0.25,
+    };
+    let goal = |i: usize| [i as f32, -(i as f32)];
+    let mut inline: Vec<_> = (0..COUNT)
+        .map(|i| AIComponent::new(goal(i), loot))
+        .collect();
+    let mut split: Vec<_> = (0..COUNT)
+        .map(|i| SplitAIComponent::new(goal(i), loot))
+        .collect();
+
+    println!("{COUNT} components, with best of 10 runs:");
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "Inline", "Split");
+    report(
+        "update",
+        measure(|| inline.iter_mut().for_each(|ai| ai.update(black_box(0.1)))),
+        measure(|| split.iter_mut().for_each(|ai| ai.update(black_box(0.1)))),
+    );
+    report(
+        "roll loot (reads cold data)",
+        measure(|| inline.iter().map(|ai| ai.loot().roll(0.1)).sum::<u32>()),
+        measure(|| split.iter().map(|ai| ai.loot().roll(0.1)).sum::<u32>()),
+    );
+    println!();
+    println!(
+        "An inline component is {} bytes; a split one is {} (plus {} behind a pointer)",
+        size_of::<AIComponent>(),
+        size_of::<SplitAIComponent>(),
+        size_of::<LootDrop>()
+    );
+}
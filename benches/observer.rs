@@ -0,0 +1,69 @@
+//! Compares notifying observers directly with publishing through a `GameEventBus`.
+//!
+//! Calling observers as each event happens is the cheapest dispatch there is; the bus pays to box,
+//! queue, and look up each event by type, in exchange for decoupling when handlers run from when
+//! events are sent:
+//!
+//! ```bash
+//! cargo bench --bench observer
+//! ```
+
+mod common;
+
+use std::{cell::Cell, hint::black_box, rc::Rc};
+
+use common::{measure, report};
+use game_patterns_rs::event_bus::GameEventBus;
+
+const EVENTS: u32 = 10_000;
+const OBSERVERS: usize = 3;
+
+struct Damaged {
+    amount: u32,
+}
+
+type Observer = dyn Fn(&Damaged);
+
+fn main() {
+    let total = Rc::new(Cell::new(0u64));
+
+    let observers: Vec<Box<Observer>> = (0..OBSERVERS)
+        .map(|_| {
+            let total = Rc::clone(&total);
+            Box::new(move |event: &Damaged| total.set(total.get() + event.amount as u64))
+                as Box<Observer>
+        })
+        .collect();
+
+    let mut bus = GameEventBus::new();
+    for _ in 0..OBSERVERS {
+        let total = Rc::clone(&total);
+        bus.subscribe(move |event: &Damaged| total.set(total.get() + event.amount as u64));
+    }
+
+    println!("{EVENTS} events to {OBSERVERS} observers, with best of 10 runs:");
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "direct", "event bus");
+    report(
+        "notify",
+        measure(|| {
+            for amount in 0..EVENTS {
+                let event = Damaged {
+                    amount: black_box(amount),
+                };
+                for observer in &observers {
+                    observer(&event);
+                }
+            }
+        }),
+        measure(|| {
+            for amount in 0..EVENTS {
+                bus.publish(Damaged {
+                    amount: black_box(amount),
+                });
+            }
+            bus.flush()
+        }),
+    );
+    black_box(total.get());
+}
@@ -0,0 +1,114 @@
+//! Compares updating the ECS on one thread with updating it on every available thread.
+//!
+//! For a few entities, starting threads costs more than it saves; for thousands (with some work
+//! to do for each), the parallel version should approach one thread's time divided by the number
+//! of cores:
+//!
+//! ```bash
+//! cargo bench --bench parallel --features parallel
+//! ```
+//!
+//! The same goes for particles, which are also updated on a range of thread counts, to show how
+//! far the update scales.
+
+mod common;
+
+use std::hint::black_box;
+
+use common::measure;
+use game_patterns_rs::{
+    ecs::World,
+    particle::{Particle, ParticleSystem, SoaParticleSystem},
+};
+
+struct Position(f32, f32);
+struct Velocity(f32, f32);
+
+fn main() {
+    println!(
+        "{} thread(s), with best of 10 runs:",
+        std::thread::available_parallelism().map_or(1, |threads| threads.get())
+    );
+    println!();
+    println!("{:<12} {:>12} {:>12}", "entities", "serial", "parallel");
+
+    for entities in [100, 10_000, 100_000] {
+        let world = world(entities);
+        let serial = measure(|| {
+            let mut positions = world.storage_mut::<Position>();
+            let velocities = world.storage::<Velocity>();
+            for (_, position, velocity) in positions.join_mut(&velocities) {
+                step(position, velocity);
+            }
+        });
+        let parallel = measure(|| {
+            let mut positions = world.storage_mut::<Position>();
+            let velocities = world.storage::<Velocity>();
+            positions.par_join_mut(&velocities, |_, position, velocity| {
+                step(position, velocity);
+            });
+        });
+        println!(
+            "{entities:<12} {:>12} {:>12}",
+            format!("{serial:.2?}"),
+            format!("{parallel:.2?}")
+        );
+    }
+
+    println!();
+    particles();
+}
+
+/// Updates particles on a range of thread counts, to show how (and from when) it scales.
+fn particles() {
+    const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+    print!("{:<12} {:>12}", "particles", "serial");
+    for threads in THREADS {
+        print!(
+            " {:>12}",
+            if threads == 1 {
+                "1 thread".to_string()
+            } else {
+                format!("{threads} threads")
+            }
+        );
+    }
+    println!();
+
+    for count in [1_000, 10_000, ParticleSystem::MAX_PARTICLES] {
+        let mut particles = SoaParticleSystem::new();
+        for i in 0..count {
+            particles.spawn(Particle {
+                velocity: [1.0, i as f32 / 1000.0],
+                // Long enough that no particle expires while measuring.
+                lifetime: 1e9,
+                ..Particle::default()
+            });
+        }
+        let serial = measure(|| particles.update(black_box(1.0 / 60.0)));
+        print!("{count:<12} {:>12}", format!("{serial:.2?}"));
+        for threads in THREADS {
+            let parallel = measure(|| particles.par_update_on(black_box(1.0 / 60.0), threads));
+            print!(" {:>12}", format!("{parallel:.2?}"));
+        }
+        println!();
+    }
+}
+
+fn world(entities: usize) -> World {
+    let mut world = World::new();
+    for i in 0..entities {
+        let entity = world.spawn();
+        world.insert(entity, Position(0.0, 0.0));
+        world.insert(entity, Velocity(1.0, i as f32));
+    }
+    world
+}
+
+/// A little more work than adding the velocity, as a real system would do.
+fn step(position: &mut Position, velocity: &Velocity) {
+    let speed = velocity.0.hypot(velocity.1).sqrt();
+    position.0 += velocity.0 / speed;
+    position.1 += velocity.1 / speed;
+}
@@ -0,0 +1,63 @@
+//! Compares updating particles stored as an array of structs and as a struct of arrays.
+//!
+//! Both do the same arithmetic; the struct of arrays only reads the fields the update uses, so
+//! far less memory passes through the cache:
+//!
+//! ```bash
+//! cargo bench --bench particles
+//! ```
+//!
+//! With the `simd` feature, it also compares the struct of arrays' update with one that works on
+//! several floats at a time:
+//!
+//! ```bash
+//! cargo bench --bench particles --features simd
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+
+use common::{measure, report};
+use game_patterns_rs::particle::{Particle, ParticleSystem, SoaParticleSystem};
+
+fn main() {
+    let mut aos = ParticleSystem::new();
+    let mut soa = SoaParticleSystem::new();
+    for i in 0..ParticleSystem::MAX_PARTICLES {
+        let particle = Particle {
+            velocity: [1.0, i as f32 / 1000.0],
+            // Long enough that no particle expires while measuring.
+            lifetime: 1e9,
+            ..Particle::default()
+        };
+        aos.spawn(particle);
+        soa.spawn(particle);
+    }
+
+    println!("{} particles, with best of 10 runs:", aos.len());
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "AoS", "SoA");
+    report(
+        "update",
+        measure(|| aos.update(black_box(1.0 / 60.0))),
+        measure(|| soa.update(black_box(1.0 / 60.0))),
+    );
+
+    #[cfg(feature = "simd")]
+    {
+        println!();
+        println!("{:<32} {:>12} {:>12}", "", "SoA", "SoA (SIMD)");
+        report(
+            "update",
+            measure(|| soa.update(black_box(1.0 / 60.0))),
+            measure(|| soa.update_simd(black_box(1.0 / 60.0))),
+        );
+    }
+
+    println!();
+    println!(
+        "A particle is {} bytes; an update uses 20 of them",
+        size_of::<Particle>()
+    );
+}
@@ -0,0 +1,111 @@
+//! Compares finding every pair of units close enough to fight by checking every pair, and by first
+//! sorting units into a fixed grid of cells (`src/grid.rs`), as in the spatial partition chapter.
+//!
+//! With the grid, a unit is only compared with units in its own and neighboring cells, so the
+//! work grows with how crowded the battlefield is rather than with the square of its population.
+//! When most units crowd into a small part of the battlefield, finding the units in an area much
+//! smaller than a cell (i.e. hit by a splash) is compared with a quadtree as well, which divides
+//! crowded areas more finely:
+//!
+//! ```bash
+//! cargo bench --bench spatial
+//! ```
+
+mod common;
+
+use common::{measure, report};
+use game_patterns_rs::{
+    grid::Grid,
+    quadtree::{QuadTree, Rect},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const SIZE: f32 = 1_000.0;
+const CELL_SIZE: f32 = 20.0;
+const CELLS: usize = (SIZE / CELL_SIZE) as usize;
+const ATTACK_DISTANCE: f32 = 20.0;
+const SPLASH_RADIUS: f32 = 2.0;
+
+fn main() {
+    println!("Units on a {SIZE}x{SIZE} battlefield, with best of 10 runs:");
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "every pair", "grid");
+    for units in [100, 1_000, 10_000] {
+        let mut rng = StdRng::seed_from_u64(7);
+        let positions: Vec<[f32; 2]> = (0..units)
+            .map(|_| [rng.gen_range(0.0..SIZE), rng.gen_range(0.0..SIZE)])
+            .collect();
+        let grid = grid(&positions);
+        assert_eq!(naive_pairs(&positions), grid_pairs(&grid));
+
+        report(
+            &format!("find melee pairs ({units} units)"),
+            measure(|| naive_pairs(&positions)),
+            measure(|| grid_pairs(&grid)),
+        );
+    }
+
+    println!();
+    println!("{:<32} {:>12} {:>12}", "", "grid", "quadtree");
+    for units in [1_000, 10_000] {
+        // Four in five units crowd into a corner a tenth of the battlefield across.
+        let mut rng = StdRng::seed_from_u64(7);
+        let positions: Vec<[f32; 2]> = (0..units)
+            .map(|unit| {
+                let size = if unit % 5 == 0 { SIZE } else { SIZE / 10.0 };
+                [rng.gen_range(0.0..size), rng.gen_range(0.0..size)]
+            })
+            .collect();
+        let grid = grid(&positions);
+        let mut tree = QuadTree::new(Rect::new(0.0, 0.0, SIZE, SIZE));
+        for (unit, &position) in positions.iter().enumerate() {
+            tree.insert(position, unit).unwrap();
+        }
+
+        // Splashes much smaller than a cell, around every tenth unit (all of them in the crowd).
+        let splashes: Vec<Rect> = positions
+            .iter()
+            .skip(1)
+            .step_by(10)
+            .map(|&position| Rect::around(position, SPLASH_RADIUS))
+            .collect();
+        let grid_hits = || -> usize { splashes.iter().map(|&area| grid.query(area).count()).sum() };
+        let tree_hits = || -> usize { splashes.iter().map(|&area| tree.query(area).count()).sum() };
+        assert_eq!(grid_hits(), tree_hits());
+
+        report(
+            &format!("crowded splashes ({units} units)"),
+            measure(grid_hits),
+            measure(tree_hits),
+        );
+    }
+}
+
+fn in_range(a: [f32; 2], b: [f32; 2]) -> bool {
+    let (dx, dy) = (a[0] - b[0], a[1] - b[1]);
+    dx * dx + dy * dy < ATTACK_DISTANCE * ATTACK_DISTANCE
+}
+
+fn naive_pairs(positions: &[[f32; 2]]) -> usize {
+    let mut pairs = 0;
+    for (i, &a) in positions.iter().enumerate() {
+        for &b in &positions[i + 1..] {
+            pairs += in_range(a, b) as usize;
+        }
+    }
+    pairs
+}
+
+fn grid(positions: &[[f32; 2]]) -> Grid {
+    let mut grid = Grid::new(CELLS, CELL_SIZE);
+    for &position in positions {
+        grid.add(position);
+    }
+    grid
+}
+
+fn grid_pairs(grid: &Grid) -> usize {
+    let mut pairs = 0;
+    grid.handle_melee(ATTACK_DISTANCE, |_, _| pairs += 1);
+    pairs
+}
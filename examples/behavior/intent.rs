@@ -6,15 +6,34 @@
 //! either of these is true:
 //!
 //! - You don’t know what types you will need up front. (For example, what if our game needed to
-//! support downloading content that contained new breeds of monsters?)
+//!   support downloading content that contained new breeds of monsters?)
 //!
 //! - You want to be able to modify or add new types without having to recompile or change code.
 //!
 //! ```bash
 //! cargo run --example behavior-intent
 //! ```
+//!
+//! Breeds can also be defined entirely in data (see `intent.toml`), where a breed names its parent,
+//! and a [`BreedRegistry`] resolves the hierarchy once and hands out shared breeds. A breed can
+//! also refer to a script (`on_attack = "scripts/troll.bc"`), which runs on the bytecode VM from
+//! `behavior/bytecode.rs` whenever a monster of that breed attacks.
+
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    fs,
+    path::Path,
+    rc::Rc,
+};
 
-use std::{borrow::Borrow, rc::Rc};
+use game_patterns_rs::{
+    bytecode::{assemble, RunError, VM},
+    symbol::Symbol,
+};
+use serde_derive::Deserialize;
 
 fn main() {
     let goblin = Rc::new(Breed::new(10, "bite"));
@@ -24,30 +43,139 @@ fn main() {
         monster.current_health,
         monster.attack()
     );
+
+    // A breed that only overrides the attack, inheriting health from its parent.
+    let goblin_archer = Breed::partial(None, Some("arrow".to_string()))
+        .try_inherit(Rc::clone(&goblin))
+        .unwrap();
+    let monster = Monster::from(&goblin_archer);
+    println!(
+        "A monster with {} HP attacks with {}",
+        monster.current_health,
+        monster.attack()
+    );
+
+    // Or, copy the inherited attributes down when the breed is created.
+    let goblin_shaman = Breed::partial(None, Some("hex".to_string()))
+        .try_copy_down(&goblin)
+        .unwrap();
+    let monster = Monster::from(&goblin_shaman);
+    println!(
+        "A monster with {} HP attacks with {} (and its breed has no parent to look up)",
+        monster.current_health,
+        monster.attack()
+    );
+
+    // If nothing in the chain defines an attribute, that's found now, not when it's first used.
+    let shade = Rc::new(Breed::partial(None, Some("chill".to_string())));
+    if let Err(error) = Breed::partial(None, Some("wail".to_string())).try_inherit(shade) {
+        println!("Could not create a banshee: {error}");
+    }
+    println!();
+
+    // Load breeds from data, where parents are referred to by name.
+    let scripts = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/behavior");
+    let registry = match BreedRegistry::load(include_str!("intent.toml"), &scripts) {
+        Ok(registry) => registry,
+        Err(error) => {
+            eprintln!("{error}");
+            return;
+        }
+    };
+    for name in registry.names() {
+        let breed = registry.get(name).unwrap();
+        let monster = Monster::from(breed.as_ref());
+        println!(
+            "A {name} with {} HP: {}",
+            monster.current_health,
+            monster.attack()
+        );
+        if let Err(error) = monster.run_attack_script() {
+            eprintln!("The {name}'s attack script failed: {error}");
+        }
+    }
+
+    // Every monster of a breed (and every breed that inherits from it) shares the same handle.
+    let troll = Symbol::intern("troll");
+    let trolls: Vec<Rc<Breed>> = (0..3).map(|_| registry.get(troll).unwrap()).collect();
+    let archer = registry.get(Symbol::intern("troll-archer")).unwrap();
+    assert!(trolls.iter().all(|troll| Rc::ptr_eq(troll, &trolls[0])));
+    assert!(Rc::ptr_eq(archer.parent.as_ref().unwrap(), &trolls[0]));
+    println!(
+        "{} trolls and the troll archer's parent share one breed",
+        trolls.len()
+    );
+
+    // Mistakes in the hierarchy are reported when loading, not when spawning.
+    for broken in [
+        "[ogre]\nparent = \"giant\"",
+        "[ogre]\nparent = \"ogre\"\nhealth = 30\nattack = \"smash\"",
+        "[ogre]\nattack = \"smash\"",
+        "[ogre]\nhealth = 30\nattack = \"smash\"\non_attack = \"scripts/ogre.bc\"",
+    ] {
+        if let Err(error) = BreedRegistry::load(broken, &scripts) {
+            println!("Could not load breeds: {error}");
+        }
+    }
 }
 
-pub struct Breed<'a> {
+/// A kind of monster.
+///
+/// Parents are shared ([`Rc`]), not borrowed, so breeds can be stored in a long-lived registry and
+/// loaded once at startup, along with the breeds that inherit from them.
+pub struct Breed {
     health: Option<u8>,
     attack: Option<String>,
-    parent: Option<&'a Breed<'a>>,
+    parent: Option<Rc<Breed>>,
+    on_attack: Option<Rc<[u64]>>,
 }
 
-impl Breed<'_> {
+impl Breed {
     pub fn new(health: u8, attack: impl Into<String>) -> Self {
         Breed {
             health: Some(health),
             attack: Some(attack.into()),
             parent: None,
+            on_attack: None,
         }
     }
 
-    // TODO: Add a try_inherit method that fails if the parent(s) don't have the required fields.
+    /// Creates a breed that only defines some attributes, and must inherit the rest.
+    ///
+    /// Until it does (see [`Breed::try_inherit`]), don't use it to create monsters.
+    pub fn partial(health: Option<u8>, attack: Option<String>) -> Self {
+        Breed {
+            health,
+            attack,
+            parent: None,
+            on_attack: None,
+        }
+    }
+
+    /// Sets a script (assembled bytecode) to run whenever a monster of this breed attacks.
+    pub fn with_on_attack(self, script: Rc<[u64]>) -> Self {
+        Breed {
+            on_attack: Some(script),
+            ..self
+        }
+    }
+
+    /// Returns the script to run on attack, if this breed (or any of its parents) has one.
+    pub fn get_on_attack(&self) -> Option<&Rc<[u64]>> {
+        match &self.on_attack {
+            Some(script) => Some(script),
+            None => self
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.get_on_attack()),
+        }
+    }
 
     pub fn get_attack(&self) -> &str {
         let attack = &self.attack;
         match attack {
             Some(attack) => attack,
-            None => self.parent.unwrap().get_attack(),
+            None => self.parent.as_ref().unwrap().get_attack(),
         }
     }
 
@@ -55,7 +183,248 @@ impl Breed<'_> {
         let health = self.health;
         match health {
             Some(health) => health,
-            None => self.parent.unwrap().get_health(),
+            None => self.parent.as_ref().unwrap().get_health(),
+        }
+    }
+
+    /// Inherits any attributes this breed doesn't define from `parent` (and its parents).
+    ///
+    /// # Errors
+    ///
+    /// If neither this breed nor any of its parents define an attribute.
+    pub fn try_inherit(self, parent: Rc<Breed>) -> Result<Breed, MissingField> {
+        let breed = Breed {
+            parent: Some(parent),
+            ..self
+        };
+        if !breed.defines(|breed| breed.health.is_some()) {
+            return Err(MissingField::Health);
+        }
+        if !breed.defines(|breed| breed.attack.is_some()) {
+            return Err(MissingField::Attack);
+        }
+        Ok(breed)
+    }
+
+    /// Like [`Breed::try_inherit`], but copies every inherited attribute into the new breed.
+    ///
+    /// This is what the book calls _copy-down_ delegation, versus the _dynamic_ delegation of
+    /// `try_inherit`:
+    ///
+    /// - Lookups (i.e. `get_health()`) are a plain field read, instead of walking the parents.
+    /// - The new breed doesn't keep its parent (and the parent's parents) alive.
+    /// - But: attributes are duplicated in memory, and if the parent changes later (say, the data is
+    ///   re-loaded), the child keeps the old values until it is re-created.
+    ///
+    /// # Errors
+    ///
+    /// If neither this breed nor any of its parents define an attribute.
+    pub fn try_copy_down(self, parent: &Breed) -> Result<Breed, MissingField> {
+        let health = match self.health {
+            Some(health) => health,
+            None if parent.defines(|breed| breed.health.is_some()) => parent.get_health(),
+            None => return Err(MissingField::Health),
+        };
+        let attack = match self.attack {
+            Some(attack) => attack,
+            None if parent.defines(|breed| breed.attack.is_some()) => {
+                parent.get_attack().to_string()
+            }
+            None => return Err(MissingField::Attack),
+        };
+        Ok(Breed {
+            on_attack: self.on_attack.or_else(|| parent.get_on_attack().cloned()),
+            ..Breed::new(health, attack)
+        })
+    }
+
+    /// Returns whether this breed or any of its parents define an attribute.
+    fn defines(&self, attribute: impl Fn(&Breed) -> bool + Copy) -> bool {
+        attribute(self)
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.defines(attribute))
+    }
+}
+
+/// An attribute that a breed does not define, and cannot inherit.
+#[derive(Debug)]
+pub enum MissingField {
+    Health,
+    Attack,
+}
+
+impl Display for MissingField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissingField::Health => write!(f, "no breed in the chain defines health"),
+            MissingField::Attack => write!(f, "no breed in the chain defines an attack"),
+        }
+    }
+}
+
+/// How a breed is written in data; anything missing is inherited from `parent`.
+#[derive(Deserialize)]
+struct BreedConfig {
+    health: Option<u8>,
+    attack: Option<String>,
+    parent: Option<String>,
+    on_attack: Option<String>,
+}
+
+/// Breeds loaded from data, by name (interned, so looking one up doesn't compare strings).
+///
+/// Each breed shares its parent (see [`Breed::try_inherit`]), so a parent is loaded once, however
+/// many breeds inherit from it, and every breed can be handed out as an [`Rc`].
+pub struct BreedRegistry {
+    breeds: HashMap<Symbol, Rc<Breed>>,
+}
+
+impl BreedRegistry {
+    /// Parses breeds from TOML, where each table is a breed and `parent` names another breed.
+    ///
+    /// Scripts (`on_attack`) are read from, and relative to, the `scripts` directory. Breeds that
+    /// refer to the same script share it.
+    ///
+    /// # Errors
+    ///
+    /// If the data is malformed, a script can't be loaded, or a breed's hierarchy can't be resolved.
+    pub fn load(data: &str, scripts: &Path) -> Result<Self, BreedError> {
+        let configs: BTreeMap<String, BreedConfig> =
+            toml::from_str(data).map_err(BreedError::Malformed)?;
+
+        let mut loaded = HashMap::new();
+        for (name, config) in &configs {
+            let Some(path) = &config.on_attack else {
+                continue;
+            };
+            if loaded.contains_key(path) {
+                continue;
+            }
+            let script = fs::read_to_string(scripts.join(path))
+                .map_err(Box::<dyn Error>::from)
+                .and_then(|source| Ok(assemble(&source)?))
+                .map_err(|error| BreedError::Script {
+                    breed: Symbol::intern(name),
+                    path: path.clone(),
+                    error,
+                })?;
+            loaded.insert(path.clone(), Rc::from(script));
+        }
+
+        let mut breeds = HashMap::new();
+        for name in configs.keys() {
+            let name = Symbol::intern(name);
+            Self::resolve(name, &configs, &loaded, &mut breeds, &mut Vec::new())?;
+        }
+        Ok(BreedRegistry { breeds })
+    }
+
+    /// Returns a handle to the breed with the given name, if any.
+    pub fn get(&self, name: Symbol) -> Option<Rc<Breed>> {
+        self.breeds.get(&name).cloned()
+    }
+
+    /// Returns the name of every breed, in alphabetical order.
+    pub fn names(&self) -> Vec<Symbol> {
+        let mut names: Vec<Symbol> = self.breeds.keys().copied().collect();
+        names.sort_by_key(|name| name.as_str());
+        names
+    }
+
+    /// Resolves a breed, and (first) every breed it inherits from.
+    ///
+    /// `path` is the chain of breeds currently being resolved, to detect cycles.
+    fn resolve(
+        name: Symbol,
+        configs: &BTreeMap<String, BreedConfig>,
+        scripts: &HashMap<String, Rc<[u64]>>,
+        breeds: &mut HashMap<Symbol, Rc<Breed>>,
+        path: &mut Vec<Symbol>,
+    ) -> Result<Rc<Breed>, BreedError> {
+        if let Some(breed) = breeds.get(&name) {
+            return Ok(Rc::clone(breed));
+        }
+        if path.contains(&name) {
+            return Err(BreedError::Cycle { breed: name });
+        }
+
+        let config = &configs[name.as_str()];
+        let mut partial = Breed::partial(config.health, config.attack.clone());
+        if let Some(script) = &config.on_attack {
+            partial = partial.with_on_attack(Rc::clone(&scripts[script]));
+        }
+        let missing = |field| BreedError::MissingField { breed: name, field };
+        let breed = match &config.parent {
+            Some(parent) => {
+                let parent = Symbol::intern(parent);
+                if !configs.contains_key(parent.as_str()) {
+                    return Err(BreedError::UnknownParent {
+                        breed: name,
+                        parent,
+                    });
+                }
+                path.push(name);
+                let parent = Self::resolve(parent, configs, scripts, breeds, path)?;
+                path.pop();
+                partial.try_inherit(parent).map_err(missing)?
+            }
+            None => Breed {
+                on_attack: partial.on_attack,
+                ..Breed::new(
+                    partial
+                        .health
+                        .ok_or(MissingField::Health)
+                        .map_err(missing)?,
+                    partial
+                        .attack
+                        .ok_or(MissingField::Attack)
+                        .map_err(missing)?,
+                )
+            },
+        };
+
+        let breed = Rc::new(breed);
+        breeds.insert(name, Rc::clone(&breed));
+        Ok(breed)
+    }
+}
+
+/// Why breeds could not be loaded.
+#[derive(Debug)]
+pub enum BreedError {
+    Malformed(toml::de::Error),
+    UnknownParent {
+        breed: Symbol,
+        parent: Symbol,
+    },
+    MissingField {
+        breed: Symbol,
+        field: MissingField,
+    },
+    Cycle {
+        breed: Symbol,
+    },
+    Script {
+        breed: Symbol,
+        path: String,
+        error: Box<dyn Error>,
+    },
+}
+
+impl Display for BreedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreedError::Malformed(error) => write!(f, "malformed data: {error}"),
+            BreedError::UnknownParent { breed, parent } => {
+                write!(f, "{breed}: parent {parent} does not exist")
+            }
+            BreedError::MissingField { breed, field } => write!(f, "{breed}: {field}"),
+            BreedError::Cycle { breed } => write!(f, "{breed}: inherits from itself"),
+            BreedError::Script { breed, path, error } => {
+                write!(f, "{breed}: could not load {path}: {error}")
+            }
         }
     }
 }
@@ -63,16 +432,24 @@ impl Breed<'_> {
 pub struct Monster<'a> {
     #[allow(dead_code)]
     current_health: u8,
-    breed: &'a Breed<'a>,
+    breed: &'a Breed,
 }
 
 impl Monster<'_> {
     pub fn attack(&self) -> &str {
         self.breed.get_attack()
     }
+
+    /// Runs the breed's attack script, if it has one.
+    pub fn run_attack_script(&self) -> Result<(), RunError> {
+        match self.breed.get_on_attack() {
+            Some(script) => VM::new(script.to_vec()).run(),
+            None => Ok(()),
+        }
+    }
 }
 
-impl<'a> From<&'a Breed<'a>> for Monster<'a> {
+impl<'a> From<&'a Breed> for Monster<'a> {
     fn from(value: &'a Breed) -> Self {
         Monster {
             current_health: value.get_health(),
@@ -0,0 +1,188 @@
+//! Combat in the ECS (see `src/ecs.rs`), tying together three patterns:
+//!
+//! - attacks are _commands_, so a player, an AI, or a replay can queue them alike;
+//! - health and loot are _components_, so anything with health can be attacked;
+//! - deaths are _observed_, so the loot system reacts without combat knowing it exists.
+//!
+//! ```bash
+//! cargo run --example decouple-combat
+//! ```
+
+use std::collections::VecDeque;
+
+use game_patterns_rs::ecs::{Entity, World};
+
+fn main() {
+    let mut world = World::new();
+    let hero = world.spawn();
+    world.insert(hero, Name("hero"));
+    world.insert(hero, Health::new(30));
+    world.insert(hero, Inventory::default());
+
+    let goblin = world.spawn();
+    world.insert(goblin, Name("goblin"));
+    world.insert(goblin, Health::new(12));
+    world.insert(goblin, Loot(vec!["dagger", "3 gold"]));
+
+    // A barrel can be smashed too; anything with health can be attacked.
+    let barrel = world.spawn();
+    world.insert(barrel, Name("barrel"));
+    world.insert(barrel, Health::new(5));
+    world.insert(barrel, Loot(vec!["apple"]));
+
+    let mut combat = Combat::default();
+    combat.on_death(|death, world| {
+        println!(
+            "{} was killed by {}",
+            name(world, death.entity),
+            name(world, death.killer)
+        );
+    });
+    combat.on_death(loot);
+
+    // This turn's attacks, in the order they were decided. The goblin can't carry the barrel's
+    // loot, so it drops; and the goblin is dead by the time its second attack runs.
+    combat.queue(AttackCommand::new(goblin, barrel, 6));
+    combat.queue(AttackCommand::new(hero, goblin, 8));
+    combat.queue(AttackCommand::new(goblin, hero, 4));
+    combat.queue(AttackCommand::new(hero, goblin, 8));
+    combat.queue(AttackCommand::new(goblin, hero, 4));
+    combat.run(&mut world);
+
+    let health = *world.get::<Health>(hero).unwrap();
+    println!(
+        "The hero has {}/{} health, and carries {:?}",
+        health.current,
+        health.max,
+        world.get::<Inventory>(hero).unwrap().0
+    );
+    println!("{} entities are left", world.len());
+}
+
+/// What to call an entity when printing it.
+pub struct Name(&'static str);
+
+fn name(world: &World, entity: Entity) -> &'static str {
+    world.get::<Name>(entity).map_or("something", |name| name.0)
+}
+
+#[derive(Clone, Copy)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Health { current: max, max }
+    }
+}
+
+/// Items dropped when an entity dies.
+pub struct Loot(Vec<&'static str>);
+
+/// Items an entity carries.
+#[derive(Default)]
+pub struct Inventory(Vec<&'static str>);
+
+/// An entity that was killed; sent to [`Combat`]'s observers before it is despawned, so they can
+/// still read its components.
+pub struct Death {
+    pub entity: Entity,
+    pub killer: Entity,
+}
+
+/// A request to do something to the world, which may kill an entity.
+pub trait Command {
+    fn execute(&self, world: &mut World) -> Option<Death>;
+}
+
+/// Damages the target, if both it and the attacker are still alive.
+pub struct AttackCommand {
+    attacker: Entity,
+    target: Entity,
+    damage: u32,
+}
+
+impl AttackCommand {
+    pub fn new(attacker: Entity, target: Entity, damage: u32) -> Self {
+        AttackCommand {
+            attacker,
+            target,
+            damage,
+        }
+    }
+}
+
+impl Command for AttackCommand {
+    fn execute(&self, world: &mut World) -> Option<Death> {
+        if !world.is_alive(self.attacker) || !world.is_alive(self.target) {
+            println!("An attack was dropped; the attacker or target is gone");
+            return None;
+        }
+        let mut health = world.get_mut::<Health>(self.target)?;
+        health.current = health.current.saturating_sub(self.damage);
+        let remaining = health.current;
+        drop(health);
+
+        println!(
+            "{} hits {} for {} ({remaining} left)",
+            name(world, self.attacker),
+            name(world, self.target),
+            self.damage
+        );
+        (remaining == 0).then_some(Death {
+            entity: self.target,
+            killer: self.attacker,
+        })
+    }
+}
+
+type DeathObserver = Box<dyn FnMut(&Death, &mut World)>;
+
+/// Runs queued commands, and tells observers about the deaths they cause.
+#[derive(Default)]
+pub struct Combat {
+    commands: VecDeque<Box<dyn Command>>,
+    observers: Vec<DeathObserver>,
+}
+
+impl Combat {
+    pub fn queue(&mut self, command: impl Command + 'static) {
+        self.commands.push_back(Box::new(command));
+    }
+
+    pub fn on_death(&mut self, observer: impl FnMut(&Death, &mut World) + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Executes every queued command, in order; the dead are despawned as soon as they die.
+    pub fn run(&mut self, world: &mut World) {
+        while let Some(command) = self.commands.pop_front() {
+            let Some(death) = command.execute(world) else {
+                continue;
+            };
+            for observer in &mut self.observers {
+                observer(&death, world);
+            }
+            world.despawn(death.entity);
+        }
+    }
+}
+
+/// Gives the dead entity's loot to its killer, or drops it on the ground if the killer can't
+/// carry it.
+fn loot(death: &Death, world: &mut World) {
+    let Some(Loot(items)) = world.remove::<Loot>(death.entity) else {
+        return;
+    };
+    if let Some(mut inventory) = world.get_mut::<Inventory>(death.killer) {
+        inventory.0.extend(&items);
+        return;
+    }
+    for item in items {
+        println!("{item} drops to the ground");
+        let dropped = world.spawn();
+        world.insert(dropped, Name(item));
+    }
+}
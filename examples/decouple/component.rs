@@ -16,10 +16,27 @@
 
 // cSpell: ignore: Bjorn
 
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use game_patterns_rs::{
+    component::{
+        GameObject, GraphicsComponent, InputComponent, NullGraphics, NullInput, PhysicsComponent,
+    },
+    schedule::Schedule,
+};
+use serde_derive::Deserialize;
+
 fn main() {
-    let controller = Controller;
-    let world = World;
-    let graphics = Graphics;
+    let services = Services {
+        controller: Controller,
+        world: World,
+        graphics: Graphics::default(),
+    };
 
     // Example of a monolith.
     let mut bjorn = BjornMonolith {
@@ -27,30 +44,175 @@ fn main() {
         y: 0.0,
         velocity: 0.0,
         volume: Volume,
-        sprite_stand: Sprite,
-        sprite_walk_left: Sprite,
-        sprite_walk_right: Sprite,
+        sprite_stand: Sprite("bjorn-stand"),
+        sprite_walk_left: Sprite("bjorn-walk-left"),
+        sprite_walk_right: Sprite("bjorn-walk-right"),
     };
 
-    bjorn.update(&controller, &world, &graphics);
+    bjorn.update(&services.controller, &services.world, &services.graphics);
+    services.graphics.submit();
 
-    // Example of components.
-    let mut bjorn = Bjorn {
-        position: PositionData {
+    // Example of components, using the library's `GameObject`.
+    let mut bjorn = GameObject::new(
+        PositionData {
             velocity: 0.0,
             x: 0.0,
             y: 0.0,
         },
-        input: InputComponent,
-        physics: PhysicsComponent { volume: Volume },
-        graphics: GraphicsComponent {
-            sprite_stand: Sprite,
-            sprite_walk_left: Sprite,
-            sprite_walk_right: Sprite,
+        PlayerInputComponent,
+        BjornPhysicsComponent { volume: Volume },
+        BjornGraphicsComponent {
+            sprite_stand: Sprite("bjorn-stand"),
+            sprite_walk_left: Sprite("bjorn-walk-left"),
+            sprite_walk_right: Sprite("bjorn-walk-right"),
         },
-    };
+    );
+
+    bjorn.update(&services);
+    services.graphics.submit();
+
+    // The book's attract mode: while nobody is playing, an AI drives the same Bjorn. Each swap
+    // hands back the other input component, so the demo picks up where it left off.
+    let mut waiting: Box<dyn InputComponent<PositionData, Services>> =
+        Box::new(DemoInputComponent::default());
+    for mode in ["demo", "player", "demo"] {
+        waiting = bjorn.set_input(waiting);
+        println!("Switched to {mode} input");
+        for _ in 0..2 {
+            bjorn.update(&services);
+            services.graphics.submit();
+        }
+    }
+
+    // Headless, as in a test or on CI: null components stand in for the player and the screen,
+    // so only the physics under test runs (and prints).
+    let mut headless = GameObject::new(
+        PositionData {
+            velocity: 0.2,
+            x: 0.0,
+            y: 0.0,
+        },
+        NullInput,
+        BjornPhysicsComponent { volume: Volume },
+        NullGraphics,
+    );
+    for _ in 0..2 {
+        headless.update(&services);
+    }
+    assert!((headless.state.x - 0.4).abs() < f32::EPSILON);
 
-    bjorn.update(&controller, &world, &graphics);
+    // Example of an entity whose components can change while the game runs.
+    let mut bjorn = Entity::new(PositionData {
+        velocity: 0.0,
+        x: 0.0,
+        y: 3.0,
+    });
+    bjorn
+        .add(PlayerInputComponent)
+        .add(BjornPhysicsComponent { volume: Volume })
+        .add(BjornGraphicsComponent {
+            sprite_stand: Sprite("bjorn-stand"),
+            sprite_walk_left: Sprite("bjorn-walk-left"),
+            sprite_walk_right: Sprite("bjorn-walk-right"),
+        })
+        .add(AudioComponent);
+
+    // Bjorn starts in the air; physics lands him, and tells its siblings (without knowing them).
+    bjorn.update(&services);
+    services.graphics.submit();
+
+    // A cutscene takes control: without input, Bjorn keeps drifting, but can't be steered.
+    let input = bjorn.remove::<PlayerInputComponent>();
+    println!("Removed input: {}", input.is_some());
+    bjorn.update(&services);
+    services.graphics.submit();
+
+    // Bjorn is hurt, and blinks: graphics reads (and counts down) its sibling each frame.
+    bjorn.add(Blink { frames: 3 });
+    for _ in 0..3 {
+        bjorn.update(&services);
+        services.graphics.submit();
+    }
+
+    // Muted: the same messages are delivered, but nothing is played.
+    let mut muted = Entity::new(PositionData {
+        velocity: 0.0,
+        x: 0.0,
+        y: 3.0,
+    });
+    muted
+        .add(BjornPhysicsComponent { volume: Volume })
+        .add(NullAudioComponent);
+    muted.update(&services);
+
+    // A decoration is only drawn; it doesn't need input or physics at all.
+    let mut flag = Entity::new(PositionData {
+        velocity: 0.0,
+        x: 10.0,
+        y: 5.0,
+    });
+    flag.add(BjornGraphicsComponent {
+        sprite_stand: Sprite("flag"),
+        sprite_walk_left: Sprite("flag"),
+        sprite_walk_right: Sprite("flag"),
+    });
+    println!(
+        "Flag has physics: {}",
+        flag.get::<BjornPhysicsComponent>().is_some()
+    );
+    flag.update(&services);
+    services.graphics.submit();
+
+    // Entities assembled from data, from components registered by name.
+    let mut factory = EntityFactory::new();
+    factory.register_input("player", |_| Ok(PlayerInputComponent));
+    factory.register_input("patrol", |params| params.try_into::<PatrolInputComponent>());
+    factory.register_physics("bjorn", |_| Ok(BjornPhysicsComponent { volume: Volume }));
+    factory.register_graphics("bjorn", |_| {
+        Ok(BjornGraphicsComponent {
+            sprite_stand: Sprite("bjorn-stand"),
+            sprite_walk_left: Sprite("bjorn-walk-left"),
+            sprite_walk_right: Sprite("bjorn-walk-right"),
+        })
+    });
+    if let Err(error) = factory.load(include_str!("component.toml")) {
+        eprintln!("{error}");
+        return;
+    }
+    let mut objects: Vec<_> = ["bjorn", "guard"]
+        .into_iter()
+        .map(|name| factory.spawn(name).unwrap())
+        .collect();
+
+    // Rather than calling each object's components in turn, each system updates one domain for
+    // every object, and the schedule works out which system runs first. Graphics components only
+    // queue what to draw; rendering then draws the whole frame, batched by sprite.
+    type Objects = Vec<GameObject<PositionData, Services>>;
+    let mut schedule = Schedule::builder()
+        .add("graphics", &["physics"], |objects: &mut Objects| {
+            objects
+                .iter_mut()
+                .for_each(|o| o.update_graphics(&services))
+        })
+        .add("physics", &["input"], |objects: &mut Objects| {
+            objects.iter_mut().for_each(|o| o.update_physics(&services))
+        })
+        .add("render", &["graphics"], |_: &mut Objects| {
+            services.graphics.submit()
+        })
+        .add("input", &[], |objects: &mut Objects| {
+            objects.iter_mut().for_each(|o| o.update_input(&services))
+        })
+        .build()
+        .unwrap();
+    println!("Systems run in order: {}", schedule.order().join(", "));
+    for tick in 0..3 {
+        println!("Tick {tick}:");
+        schedule.run(&mut objects);
+    }
+    if let Err(error) = factory.spawn("dragon") {
+        println!("{error}");
+    }
 }
 
 pub struct Controller;
@@ -77,15 +239,50 @@ impl World {
     }
 }
 
-pub struct Graphics;
+/// Collects what to draw during a frame, and draws it all at once.
+///
+/// Drawing as each graphics component updates would switch sprites (textures, on a GPU) for
+/// nearly every draw; sorting a frame's worth of draws by sprite first means switching once per
+/// sprite instead. Gameplay code never knows the difference.
+#[derive(Default)]
+pub struct Graphics {
+    frame: RefCell<Vec<DrawCommand>>,
+}
 
 impl Graphics {
-    pub fn draw(&self, _sprite: &Sprite, x: f32, y: f32) {
-        println!("Drawing sprite at ({}, {})", x, y);
+    /// Queues the sprite to be drawn by the next [`Graphics::submit`].
+    pub fn draw(&self, sprite: &Sprite, x: f32, y: f32) {
+        self.frame.borrow_mut().push(DrawCommand {
+            sprite: *sprite,
+            x,
+            y,
+        });
     }
+
+    /// Draws everything queued this frame, one batch per sprite.
+    pub fn submit(&self) {
+        let mut frame = self.frame.borrow_mut();
+        frame.sort_by_key(|command| command.sprite);
+        for batch in frame.chunk_by(|a, b| a.sprite == b.sprite) {
+            let positions: Vec<String> = batch
+                .iter()
+                .map(|command| format!("({}, {})", command.x, command.y))
+                .collect();
+            println!("Drawing {} at {}", batch[0].sprite.0, positions.join(", "));
+        }
+        frame.clear();
+    }
+}
+
+/// A request to draw a sprite, queued by [`Graphics::draw`].
+struct DrawCommand {
+    sprite: Sprite,
+    x: f32,
+    y: f32,
 }
 
-pub struct Sprite;
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Sprite(&'static str);
 
 pub struct Volume;
 
@@ -129,22 +326,6 @@ impl BjornMonolith {
     }
 }
 
-#[allow(dead_code)]
-pub struct Bjorn {
-    position: PositionData,
-    input: InputComponent,
-    physics: PhysicsComponent,
-    graphics: GraphicsComponent,
-}
-
-impl Bjorn {
-    pub fn update(&mut self, controller: &Controller, world: &World, graphics: &Graphics) {
-        self.input.update(&mut self.position, controller);
-        self.physics.update(&mut self.position, world);
-        self.graphics.update(&mut self.position, graphics);
-    }
-}
-
 // This could be split into Move and Position, but ... this is an example.
 pub struct PositionData {
     pub velocity: f32,
@@ -152,38 +333,86 @@ pub struct PositionData {
     pub y: f32,
 }
 
-pub struct InputComponent;
+pub struct PlayerInputComponent;
 
-impl InputComponent {
+impl PlayerInputComponent {
     const WALK_ACCELERATION: f32 = 0.1;
+}
 
-    pub fn update(&self, target: &mut PositionData, controller: &Controller) {
-        match controller.get_joystick_direction() {
+impl InputComponent<PositionData, Services> for PlayerInputComponent {
+    fn update(&mut self, target: &mut PositionData, services: &Services) {
+        match services.controller.get_joystick_direction() {
             Direction::Left => target.velocity -= Self::WALK_ACCELERATION,
             Direction::Right => target.velocity += Self::WALK_ACCELERATION,
         }
     }
 }
 
-pub struct PhysicsComponent {
+/// Plays the game by itself, for the attract mode shown while no one is playing.
+#[derive(Default)]
+pub struct DemoInputComponent {
+    frame: u32,
+}
+
+impl InputComponent<PositionData, Services> for DemoInputComponent {
+    fn update(&mut self, target: &mut PositionData, _: &Services) {
+        // A real demo would replay a recording, or run an AI; this one walks right, then left.
+        let direction = if (self.frame / 3) % 2 == 0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        };
+        self.frame += 1;
+        match direction {
+            Direction::Left => target.velocity -= PlayerInputComponent::WALK_ACCELERATION,
+            Direction::Right => target.velocity += PlayerInputComponent::WALK_ACCELERATION,
+        }
+    }
+}
+
+/// Walks back and forth, `range` either side of where it started, instead of reading a controller.
+#[derive(Deserialize)]
+pub struct PatrolInputComponent {
+    speed: f32,
+    range: f32,
+    #[serde(skip)]
+    start: Option<f32>,
+}
+
+impl InputComponent<PositionData, Services> for PatrolInputComponent {
+    fn update(&mut self, target: &mut PositionData, _: &Services) {
+        let start = *self.start.get_or_insert(target.x);
+        if target.velocity == 0.0 || (target.x - start).abs() >= self.range {
+            target.velocity = if target.x > start {
+                -self.speed
+            } else {
+                self.speed
+            };
+        }
+    }
+}
+
+pub struct BjornPhysicsComponent {
     volume: Volume,
 }
 
-impl PhysicsComponent {
-    pub fn update(&self, target: &mut PositionData, world: &World) {
+impl PhysicsComponent<PositionData, Services> for BjornPhysicsComponent {
+    fn update(&mut self, target: &mut PositionData, services: &Services) {
         target.x += target.velocity;
-        world.resolve_collision(&self.volume, target.x, target.y, target.velocity)
+        services
+            .world
+            .resolve_collision(&self.volume, target.x, target.y, target.velocity)
     }
 }
 
-pub struct GraphicsComponent {
+pub struct BjornGraphicsComponent {
     sprite_stand: Sprite,
     sprite_walk_left: Sprite,
     sprite_walk_right: Sprite,
 }
 
-impl GraphicsComponent {
-    pub fn update(&self, target: &mut PositionData, graphics: &Graphics) {
+impl GraphicsComponent<PositionData, Services> for BjornGraphicsComponent {
+    fn update(&mut self, target: &mut PositionData, services: &Services) {
         let sprite = if target.velocity < 0.0 {
             &self.sprite_walk_left
         } else if target.velocity > 0.0 {
@@ -192,6 +421,387 @@ impl GraphicsComponent {
             &self.sprite_stand
         };
 
-        graphics.draw(sprite, target.x, target.y);
+        services.graphics.draw(sprite, target.x, target.y);
+    }
+}
+
+/// Everything outside of an entity that its components may use.
+pub struct Services {
+    pub controller: Controller,
+    pub world: World,
+    pub graphics: Graphics,
+}
+
+/// A message from one component of an [`Entity`] to its siblings.
+///
+/// The book uses an `int` message id; in Rust, each variant is an id (that can carry data).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    /// The entity hit the ground after falling from `height`.
+    Landed { height: f32 },
+}
+
+/// Messages waiting to be delivered to every component of an entity.
+#[derive(Default)]
+pub struct Mailbox {
+    messages: VecDeque<Message>,
+}
+
+impl Mailbox {
+    pub fn send(&mut self, message: Message) {
+        self.messages.push_back(message);
+    }
+}
+
+/// What a component of an [`Entity`] can reach while it updates: the entity's position, its
+/// mailbox, and its other components.
+///
+/// The component being updated is borrowed separately from its siblings (the components before
+/// and after it), so it can read or change them without any runtime borrow checks.
+pub struct EntityContext<'a> {
+    pub position: &'a mut PositionData,
+    pub mailbox: &'a mut Mailbox,
+    before: &'a mut [Box<dyn Component>],
+    after: &'a mut [Box<dyn Component>],
+}
+
+impl EntityContext<'_> {
+    /// Returns the first other component of type `C`, if any.
+    pub fn sibling<C: Component>(&self) -> Option<&C> {
+        self.before
+            .iter()
+            .chain(self.after.iter())
+            .find_map(|component| (component.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    /// Returns the first other component of type `C` mutably, if any.
+    pub fn sibling_mut<C: Component>(&mut self) -> Option<&mut C> {
+        self.before
+            .iter_mut()
+            .chain(self.after.iter_mut())
+            .find_map(|component| (component.as_mut() as &mut dyn Any).downcast_mut())
+    }
+}
+
+/// Any component of an [`Entity`].
+pub trait Component: Any {
+    fn update(&mut self, context: &mut EntityContext, services: &Services);
+
+    /// Receives a message sent by a component of the same entity (including this one).
+    fn receive(&mut self, _message: &Message, _mailbox: &mut Mailbox) {}
+}
+
+impl Component for PlayerInputComponent {
+    fn update(&mut self, context: &mut EntityContext, services: &Services) {
+        InputComponent::update(self, context.position, services);
+    }
+}
+
+impl Component for BjornPhysicsComponent {
+    fn update(&mut self, context: &mut EntityContext, services: &Services) {
+        PhysicsComponent::update(self, context.position, services);
+
+        // Fall back to the ground; whoever cares (graphics, audio) finds out by message.
+        if context.position.y > 0.0 {
+            let height = context.position.y;
+            context.mailbox.send(Message::Landed { height });
+            context.position.y = 0.0;
+        }
+    }
+}
+
+impl Component for BjornGraphicsComponent {
+    fn update(&mut self, context: &mut EntityContext, services: &Services) {
+        // While blinking (i.e. after being hurt), skip every other frame.
+        if let Some(blink) = context.sibling_mut::<Blink>() {
+            if blink.frames > 0 {
+                blink.frames -= 1;
+                if blink.frames % 2 == 1 {
+                    println!("Blinking; not drawn this frame");
+                    return;
+                }
+            }
+        }
+        GraphicsComponent::update(self, context.position, services);
+    }
+
+    fn receive(&mut self, message: &Message, _: &mut Mailbox) {
+        match message {
+            Message::Landed { height } if *height > 2.0 => println!("Spawning dust particles"),
+            Message::Landed { .. } => {}
+        }
+    }
+}
+
+/// Plays sounds for an entity; it only reacts to messages.
+pub struct AudioComponent;
+
+impl Component for AudioComponent {
+    fn update(&mut self, _: &mut EntityContext, _: &Services) {}
+
+    fn receive(&mut self, message: &Message, _: &mut Mailbox) {
+        match message {
+            Message::Landed { height } => println!("Playing thud (volume {height})"),
+        }
+    }
+}
+
+/// Plays no sounds; for entities simulated without an audio device.
+pub struct NullAudioComponent;
+
+impl Component for NullAudioComponent {
+    fn update(&mut self, _: &mut EntityContext, _: &Services) {}
+}
+
+/// Makes a sibling graphics component blink for a number of frames; it has no behavior of its own.
+pub struct Blink {
+    frames: u32,
+}
+
+impl Component for Blink {
+    fn update(&mut self, _: &mut EntityContext, _: &Services) {}
+}
+
+/// An entity made of any set of components, which can be added and removed at runtime.
+///
+/// Unlike a [`GameObject`], which always has exactly three components, this can represent a
+/// decoration (graphics only), a trigger zone (physics only), or Bjorn during a cutscene (no input).
+pub struct Entity {
+    position: PositionData,
+    components: Vec<Box<dyn Component>>,
+    mailbox: Mailbox,
+}
+
+impl Entity {
+    /// The most messages delivered at once, in case components keep replying to each other.
+    const MAX_MESSAGES: usize = 64;
+
+    pub fn new(position: PositionData) -> Self {
+        Entity {
+            position,
+            components: Vec::new(),
+            mailbox: Mailbox::default(),
+        }
+    }
+
+    /// Adds a component, which is updated after the ones already added.
+    pub fn add(&mut self, component: impl Component) -> &mut Self {
+        self.components.push(Box::new(component));
+        self
+    }
+
+    /// Removes and returns the first component of type `C`, if any.
+    pub fn remove<C: Component>(&mut self) -> Option<C> {
+        let index = self
+            .components
+            .iter()
+            .position(|component| (component.as_ref() as &dyn Any).is::<C>())?;
+        let component: Box<dyn Any> = self.components.remove(index);
+        component.downcast().ok().map(|component| *component)
+    }
+
+    /// Returns the first component of type `C`, if any.
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.components
+            .iter()
+            .find_map(|component| (component.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    pub fn update(&mut self, services: &Services) {
+        for index in 0..self.components.len() {
+            let (before, rest) = self.components.split_at_mut(index);
+            let (component, after) = rest.split_first_mut().unwrap();
+            let mut context = EntityContext {
+                position: &mut self.position,
+                mailbox: &mut self.mailbox,
+                before,
+                after,
+            };
+            component.update(&mut context, services);
+        }
+        self.dispatch();
+    }
+
+    /// Sends a message to every component, as if one of them had sent it.
+    pub fn send(&mut self, message: Message) {
+        self.mailbox.send(message);
+        self.dispatch();
+    }
+
+    /// Delivers pending messages (including any sent in response) to every component.
+    fn dispatch(&mut self) {
+        for _ in 0..Self::MAX_MESSAGES {
+            let Some(message) = self.mailbox.messages.pop_front() else {
+                return;
+            };
+            for component in &mut self.components {
+                component.receive(&message, &mut self.mailbox);
+            }
+        }
+        self.mailbox.messages.clear();
+    }
+}
+
+/// Builds a component from its parameters.
+type Build<T> = Box<dyn Fn(toml::Value) -> Result<Box<T>, toml::de::Error>>;
+
+/// How an entity is written in data.
+#[derive(Deserialize)]
+struct EntityDefinition {
+    x: f32,
+    y: f32,
+    input: ComponentDefinition,
+    physics: ComponentDefinition,
+    graphics: ComponentDefinition,
+}
+
+/// A component's type, and any parameters it takes.
+#[derive(Deserialize)]
+struct ComponentDefinition {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    params: toml::value::Table,
+}
+
+/// Spawns [`GameObject`]s from definitions in data, by looking up each component type by name.
+///
+/// This is the prototype pattern (data describes what to create) applied to the component
+/// pattern (what's created is only a set of components), so new kinds of entities need no code.
+#[derive(Default)]
+pub struct EntityFactory {
+    definitions: BTreeMap<String, EntityDefinition>,
+    inputs: HashMap<&'static str, Build<dyn InputComponent<PositionData, Services>>>,
+    physics: HashMap<&'static str, Build<dyn PhysicsComponent<PositionData, Services>>>,
+    graphics: HashMap<&'static str, Build<dyn GraphicsComponent<PositionData, Services>>>,
+}
+
+impl EntityFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an input component type, built from its parameters by `build`.
+    pub fn register_input<T>(
+        &mut self,
+        kind: &'static str,
+        build: impl Fn(toml::Value) -> Result<T, toml::de::Error> + 'static,
+    ) where
+        T: InputComponent<PositionData, Services> + 'static,
+    {
+        self.inputs.insert(
+            kind,
+            Box::new(move |params| Ok(Box::new(build(params)?) as Box<_>)),
+        );
+    }
+
+    /// Registers a physics component type, built from its parameters by `build`.
+    pub fn register_physics<T>(
+        &mut self,
+        kind: &'static str,
+        build: impl Fn(toml::Value) -> Result<T, toml::de::Error> + 'static,
+    ) where
+        T: PhysicsComponent<PositionData, Services> + 'static,
+    {
+        self.physics.insert(
+            kind,
+            Box::new(move |params| Ok(Box::new(build(params)?) as Box<_>)),
+        );
+    }
+
+    /// Registers a graphics component type, built from its parameters by `build`.
+    pub fn register_graphics<T>(
+        &mut self,
+        kind: &'static str,
+        build: impl Fn(toml::Value) -> Result<T, toml::de::Error> + 'static,
+    ) where
+        T: GraphicsComponent<PositionData, Services> + 'static,
+    {
+        self.graphics.insert(
+            kind,
+            Box::new(move |params| Ok(Box::new(build(params)?) as Box<_>)),
+        );
+    }
+
+    /// Parses entity definitions from TOML, adding to (or replacing) any already loaded.
+    pub fn load(&mut self, data: &str) -> Result<(), FactoryError> {
+        let definitions: BTreeMap<String, EntityDefinition> =
+            toml::from_str(data).map_err(FactoryError::Malformed)?;
+        self.definitions.extend(definitions);
+        Ok(())
+    }
+
+    /// Assembles a new entity from the definition with the given name.
+    pub fn spawn(&self, name: &str) -> Result<GameObject<PositionData, Services>, FactoryError> {
+        let definition = self
+            .definitions
+            .get(name)
+            .ok_or_else(|| FactoryError::UnknownEntity(name.to_string()))?;
+        Ok(GameObject::new(
+            PositionData {
+                velocity: 0.0,
+                x: definition.x,
+                y: definition.y,
+            },
+            build(name, &self.inputs, &definition.input)?,
+            build(name, &self.physics, &definition.physics)?,
+            build(name, &self.graphics, &definition.graphics)?,
+        ))
+    }
+}
+
+/// Builds a component of one domain, using whichever builder is registered for its type.
+fn build<T: ?Sized>(
+    entity: &str,
+    builders: &HashMap<&'static str, Build<T>>,
+    definition: &ComponentDefinition,
+) -> Result<Box<T>, FactoryError> {
+    let build =
+        builders
+            .get(definition.kind.as_str())
+            .ok_or_else(|| FactoryError::UnknownComponent {
+                entity: entity.to_string(),
+                kind: definition.kind.clone(),
+            })?;
+    build(toml::Value::Table(definition.params.clone())).map_err(|error| FactoryError::Parameters {
+        entity: entity.to_string(),
+        kind: definition.kind.clone(),
+        error,
+    })
+}
+
+/// Why an entity could not be loaded or spawned.
+#[derive(Debug)]
+pub enum FactoryError {
+    Malformed(toml::de::Error),
+    UnknownEntity(String),
+    UnknownComponent {
+        entity: String,
+        kind: String,
+    },
+    Parameters {
+        entity: String,
+        kind: String,
+        error: toml::de::Error,
+    },
+}
+
+impl Display for FactoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FactoryError::Malformed(error) => write!(f, "Malformed data: {error}"),
+            FactoryError::UnknownEntity(name) => write!(f, "No entity is named {name}"),
+            FactoryError::UnknownComponent { entity, kind } => {
+                write!(f, "[{entity}] uses unknown component type \"{kind}\"")
+            }
+            FactoryError::Parameters {
+                entity,
+                kind,
+                error,
+            } => write!(
+                f,
+                "[{entity}] has invalid parameters for \"{kind}\": {error}"
+            ),
+        }
     }
 }
@@ -0,0 +1,64 @@
+//! Updating the ECS (see `src/ecs.rs`) from several threads at once.
+//!
+//! Systems only touch the component types they ask for, and each entity's components only
+//! depend on that entity, so a system can split its components into chunks and update each chunk
+//! on its own thread, without any locking.
+//!
+//! ```bash
+//! cargo run --example decouple-ecs-parallel --features parallel
+//! ```
+
+use game_patterns_rs::ecs::World;
+
+fn main() {
+    // A swarm of boids, each flying in its own direction.
+    let mut world = World::new();
+    for i in 0..10_000 {
+        let boid = world.spawn();
+        let angle = i as f32 * 0.01;
+        world.insert(boid, Position { x: 0.0, y: 0.0 });
+        world.insert(
+            boid,
+            Velocity {
+                x: angle.cos(),
+                y: angle.sin(),
+            },
+        );
+    }
+
+    for _ in 0..60 {
+        movement(&world);
+    }
+
+    // The same result as updating them one at a time, whatever the number of threads.
+    let positions = world.storage::<Position>();
+    let furthest = positions
+        .iter()
+        .map(|(_, position)| position.x.hypot(position.y))
+        .fold(0.0, f32::max);
+    println!(
+        "Moved {} boids on {} thread(s); the furthest is {furthest:.1} away",
+        positions.len(),
+        std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+    );
+}
+
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Moves everything that has both a position and a velocity, in parallel.
+fn movement(world: &World) {
+    let mut positions = world.storage_mut::<Position>();
+    let velocities = world.storage::<Velocity>();
+    positions.par_join_mut(&velocities, |_, position, velocity| {
+        position.x += velocity.x;
+        position.y += velocity.y;
+    });
+}
@@ -0,0 +1,216 @@
+//! Saving and loading an ECS world (see `src/ecs.rs`) with serde.
+//!
+//! The world stores components of any type, so it can't serialize them itself; instead, each
+//! saveable component type is registered by name (as the component factory does), and the save
+//! file is a list of entities, each a table of its components:
+//!
+//! ```toml
+//! [[entities]]
+//! [entities.position]
+//! x = 1.0
+//! y = 2.0
+//! ```
+//!
+//! ```bash
+//! cargo run --example decouple-ecs-save
+//! ```
+
+use std::fs;
+
+use game_patterns_rs::ecs::{Entity, World};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_derive::{Deserialize, Serialize};
+
+fn main() {
+    let saves = saves();
+    let world = adventure();
+
+    // Play for a while, then save.
+    for _ in 0..3 {
+        tick(&world);
+    }
+    let path = std::env::temp_dir().join("game-patterns-rs-save.toml");
+    fs::write(&path, saves.save(&world).unwrap()).unwrap();
+    println!("Saved to {}:", path.display());
+    println!("{}", fs::read_to_string(&path).unwrap());
+
+    // Keep playing the original, and play the loaded copy just as long; they should agree.
+    let loaded = saves.load(&fs::read_to_string(&path).unwrap()).unwrap();
+    for _ in 0..3 {
+        tick(&world);
+        tick(&loaded);
+    }
+    let matches = snapshot(&world) == snapshot(&loaded);
+    println!("After 3 more ticks, the loaded game matches the original: {matches}");
+    for state in snapshot(&world) {
+        println!("  {state:?}");
+    }
+    fs::remove_file(path).unwrap();
+}
+
+/// Every component type that is saved.
+fn saves() -> SaveRegistry {
+    let mut saves = SaveRegistry::default();
+    saves.register::<Position>("position");
+    saves.register::<Velocity>("velocity");
+    saves.register::<Health>("health");
+    saves.register::<Inventory>("inventory");
+    saves
+}
+
+/// A hero, and a slime heading into the swamp.
+fn adventure() -> World {
+    let mut world = World::new();
+    let hero = world.spawn();
+    world.insert(hero, Position { x: 0.0, y: 0.0 });
+    world.insert(hero, Velocity { x: 1.0, y: 0.5 });
+    world.insert(hero, Health(20));
+    world.insert(hero, Inventory(vec!["sword".into(), "antidote".into()]));
+    let slime = world.spawn();
+    world.insert(slime, Position { x: 4.0, y: 1.0 });
+    world.insert(slime, Velocity { x: -0.5, y: 0.0 });
+    world.insert(slime, Health(8));
+    world
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Health(u32);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Inventory(Vec<String>);
+
+/// Moves everything, and hurts everything that has walked into the swamp (x > 2).
+fn tick(world: &World) {
+    let mut positions = world.storage_mut::<Position>();
+    let velocities = world.storage::<Velocity>();
+    for (_, position, velocity) in positions.join_mut(&velocities) {
+        position.x += velocity.x;
+        position.y += velocity.y;
+    }
+    let mut health = world.storage_mut::<Health>();
+    for (_, health, position) in health.join_mut(&positions) {
+        if position.x > 2.0 {
+            health.0 = health.0.saturating_sub(1);
+        }
+    }
+}
+
+/// Every entity's state worth comparing, in order.
+fn snapshot(world: &World) -> Vec<(Option<Position>, Option<Health>, Option<Inventory>)> {
+    world
+        .entities()
+        .map(|entity| {
+            (
+                world.get::<Position>(entity).map(|p| *p),
+                world.get::<Health>(entity).map(|h| *h),
+                world.get::<Inventory>(entity).map(|i| i.clone()),
+            )
+        })
+        .collect()
+}
+
+type Save = Box<dyn Fn(&World, Entity) -> Option<Result<toml::Value, toml::ser::Error>>>;
+type Load = Box<dyn Fn(&mut World, Entity, toml::Value) -> Result<(), toml::de::Error>>;
+
+/// Component types that are saved, by the name they are saved as.
+#[derive(Default)]
+pub struct SaveRegistry {
+    components: Vec<(&'static str, Save, Load)>,
+}
+
+/// The contents of a save file.
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    entities: Vec<toml::value::Table>,
+}
+
+impl SaveRegistry {
+    pub fn register<T: Serialize + DeserializeOwned + 'static>(&mut self, name: &'static str) {
+        self.components.push((
+            name,
+            Box::new(|world, entity| {
+                let component = world.get::<T>(entity)?;
+                Some(toml::Value::try_from(&*component))
+            }),
+            Box::new(|world, entity, value| {
+                world.insert(entity, value.try_into::<T>()?);
+                Ok(())
+            }),
+        ));
+    }
+
+    /// Writes every entity's registered components; other components aren't saved.
+    ///
+    /// Entities are saved in order, but not their ids, so components that refer to other entities
+    /// would need those references re-mapped on load.
+    pub fn save(&self, world: &World) -> Result<String, toml::ser::Error> {
+        let mut entities = Vec::new();
+        for entity in world.entities() {
+            let mut table = toml::value::Table::new();
+            for (name, save, _) in &self.components {
+                if let Some(value) = save(world, entity) {
+                    table.insert(name.to_string(), value?);
+                }
+            }
+            entities.push(table);
+        }
+        toml::to_string(&SaveFile { entities })
+    }
+
+    /// Creates a world with the saved entities; components of unregistered types are an error.
+    pub fn load(&self, data: &str) -> Result<World, toml::de::Error> {
+        let file: SaveFile = toml::from_str(data)?;
+        let mut world = World::new();
+        for table in file.entities {
+            let entity = world.spawn();
+            for (name, value) in table {
+                let Some((_, _, load)) = self.components.iter().find(|(n, ..)| *n == name) else {
+                    return Err(serde::de::Error::custom(format!(
+                        "unknown component {name}"
+                    )));
+                };
+                load(&mut world, entity, value)?;
+            }
+        }
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_continues_identically_after_load() {
+        let saves = saves();
+        let world = adventure();
+        for _ in 0..3 {
+            tick(&world);
+        }
+        let loaded = saves.load(&saves.save(&world).unwrap()).unwrap();
+        assert_eq!(snapshot(&loaded), snapshot(&world));
+
+        // Long enough for the slime to walk out of the swamp, while the hero walks further in.
+        for ticks in 1..=10 {
+            tick(&world);
+            tick(&loaded);
+            assert_eq!(
+                snapshot(&loaded),
+                snapshot(&world),
+                "differs after {ticks} ticks"
+            );
+        }
+    }
+}
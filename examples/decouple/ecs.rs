@@ -0,0 +1,130 @@
+//! A tiny entity component system (see `src/ecs.rs`), where the component pattern ends up.
+//!
+//! Entities are only ids. Components are plain data, stored by type rather than by entity, and
+//! behavior lives in systems: functions that loop over every entity with the components they need.
+//!
+//! ```bash
+//! cargo run --example decouple-ecs
+//! ```
+
+use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
+
+use game_patterns_rs::{
+    ecs::{ComponentEvent, Entity, World},
+    query,
+};
+
+fn main() {
+    let mut world = World::new();
+
+    // A render batcher keeps its own list of what to draw, in sync by observing sprites come and
+    // go, rather than scanning every entity each frame.
+    let batch = Rc::new(RefCell::new(BTreeSet::new()));
+    let observed = batch.clone();
+    world.observe(move |event| {
+        if !event.component().is::<Sprite>() {
+            return;
+        }
+        match event {
+            ComponentEvent::Attached(entity, _) => observed.borrow_mut().insert(entity),
+            ComponentEvent::Detached(entity, _) => observed.borrow_mut().remove(&entity),
+        };
+    });
+
+    // Bjorn walks right, past a tree that only needs to be drawn.
+    let bjorn = world.spawn();
+    world.insert(bjorn, Position { x: 2.0, y: 0.0 });
+    world.insert(bjorn, Velocity { x: 1.0, y: 0.0 });
+    world.insert(bjorn, Sprite('B'));
+
+    let tree = world.spawn();
+    world.insert(tree, Position { x: 6.0, y: 0.0 });
+    world.insert(tree, Sprite('♣'));
+
+    // Dust kicked up behind him, which fades after a few frames.
+    for (velocity, frames) in [(-1.0, 2), (-0.5, 3)] {
+        let dust = world.spawn();
+        world.insert(dust, Position { x: 2.0, y: 0.0 });
+        world.insert(
+            dust,
+            Velocity {
+                x: velocity,
+                y: 0.0,
+            },
+        );
+        world.insert(dust, Sprite('*'));
+        world.insert(dust, Lifetime(frames));
+    }
+
+    for frame in 0..5 {
+        movement(&world);
+        let expired = lifetime(&world);
+        for entity in expired {
+            world.despawn(entity);
+        }
+        println!(
+            "{frame}: {} ({} entities, {} sprites batched)",
+            render(&world),
+            world.len(),
+            batch.borrow().len()
+        );
+    }
+
+    // A new entity reuses a despawned one's index, but not its id.
+    let spark = world.spawn();
+    println!(
+        "Spawned {spark:?}; Bjorn is still alive: {}",
+        world.is_alive(bjorn)
+    );
+}
+
+/// Where an entity is.
+#[derive(Debug)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// How far an entity moves each frame.
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// How an entity is drawn.
+pub struct Sprite(char);
+
+/// How many more frames an entity exists for.
+pub struct Lifetime(u32);
+
+/// Moves everything that has both a position and a velocity.
+fn movement(world: &World) {
+    query!(world, |_, position: &mut Position, velocity: &Velocity| {
+        position.x += velocity.x;
+        position.y += velocity.y;
+    });
+}
+
+/// Counts down lifetimes, returning the entities whose time is up.
+fn lifetime(world: &World) -> Vec<Entity> {
+    let mut lifetimes = world.storage_mut::<Lifetime>();
+    let mut expired = Vec::new();
+    for (entity, lifetime) in lifetimes.iter_mut() {
+        lifetime.0 -= 1;
+        if lifetime.0 == 0 {
+            expired.push(entity);
+        }
+    }
+    expired
+}
+
+/// Draws everything that has both a position and a sprite, on a single line.
+fn render(world: &World) -> String {
+    let mut line = vec!['.'; 12];
+    query!(world, |_, sprite: &Sprite, position: &Position| {
+        if let Some(cell) = line.get_mut(position.x.round() as usize) {
+            *cell = sprite.0;
+        }
+    });
+    line.into_iter().collect()
+}
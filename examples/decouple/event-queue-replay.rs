@@ -0,0 +1,173 @@
+//! Recording every event published to a bus (see `src/event_bus.rs`), and replaying them later.
+//!
+//! Bugs that depend on the order events arrive in are hard to reproduce by hand. If the game's
+//! systems only react to events, though, recording the events (and the frame each was published
+//! on) to a file is enough to play the exact same game again, from the recording instead of from
+//! (random, in this case) input:
+//!
+//! ```toml
+//! [[events]]
+//! frame = 0
+//!
+//! [events.event]
+//! type = "Hit"
+//! target = "goblin"
+//! damage = 3
+//! ```
+//!
+//! ```bash
+//! cargo run --example decouple-event-queue-replay
+//! ```
+
+use std::{cell::RefCell, collections::VecDeque, fs, rc::Rc};
+
+use game_patterns_rs::event_bus::GameEventBus;
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+
+fn main() {
+    // Plays with random input, recording every event.
+    let mut bus = RecordingBus::new();
+    let live = track_health(&mut bus.bus);
+    let mut rng = rand::thread_rng();
+    for _ in 0..8 {
+        let target = ["goblin", "orc"][rng.gen_range(0..2)];
+        bus.publish(GameEvent::Hit {
+            target: target.to_string(),
+            damage: rng.gen_range(1..=5),
+        });
+        if rng.gen_bool(0.25) {
+            bus.publish(GameEvent::Healed {
+                target: target.to_string(),
+                amount: 2,
+            });
+        }
+        bus.end_frame();
+    }
+
+    let path = std::env::temp_dir().join("game-patterns-rs-events.toml");
+    fs::write(&path, toml::to_string(&bus.recording).unwrap()).unwrap();
+    println!("Recorded {} events", bus.recording.events.len());
+    for line in live.borrow().iter() {
+        println!("  {line}");
+    }
+
+    // Plays again, from the recording instead of input.
+    let recording: Recording = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let mut replay = Replay::new(recording);
+    let mut bus = GameEventBus::new();
+    let replayed = track_health(&mut bus);
+    let mut frame = 0;
+    while !replay.is_done() {
+        replay.publish(frame, &mut bus);
+        bus.flush();
+        frame += 1;
+    }
+    println!(
+        "Replayed {frame} frames; the same game: {}",
+        live == replayed
+    );
+    assert_eq!(live, replayed);
+    fs::remove_file(path).unwrap();
+}
+
+/// Everything that happens in this (very small) game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum GameEvent {
+    Hit { target: String, damage: u32 },
+    Healed { target: String, amount: u32 },
+}
+
+/// A system that only reacts to events: tracks each enemy's health, logging every change.
+fn track_health(bus: &mut GameEventBus) -> Rc<RefCell<Vec<String>>> {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut health = [("goblin", 10), ("orc", 15)];
+    let lines = Rc::clone(&log);
+    bus.subscribe(move |event: &GameEvent| {
+        let (target, change) = match event {
+            GameEvent::Hit { target, damage } => (target, -(*damage as i32)),
+            GameEvent::Healed { target, amount } => (target, *amount as i32),
+        };
+        let (name, hp) = health.iter_mut().find(|(name, _)| name == target).unwrap();
+        *hp = (*hp + change).max(0);
+        lines
+            .borrow_mut()
+            .push(format!("{name}: {change:+} = {hp}"));
+    });
+    log
+}
+
+/// Every event published, in order, with the frame it was published on.
+#[derive(Default, Serialize, Deserialize)]
+struct Recording {
+    events: Vec<Recorded>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Recorded {
+    frame: u64,
+    event: GameEvent,
+}
+
+/// A bus that records every event published to it.
+struct RecordingBus {
+    bus: GameEventBus,
+    frame: u64,
+    recording: Recording,
+}
+
+impl RecordingBus {
+    fn new() -> Self {
+        RecordingBus {
+            bus: GameEventBus::new(),
+            frame: 0,
+            recording: Recording::default(),
+        }
+    }
+
+    fn publish(&mut self, event: GameEvent) {
+        self.recording.events.push(Recorded {
+            frame: self.frame,
+            event: event.clone(),
+        });
+        self.bus.publish(event);
+    }
+
+    /// Delivers the frame's events, and starts the next frame.
+    fn end_frame(&mut self) {
+        self.bus.flush();
+        self.frame += 1;
+    }
+}
+
+/// Publishes recorded events again, each on the same frame it was first published on.
+struct Replay {
+    events: VecDeque<Recorded>,
+}
+
+impl Replay {
+    /// A recording edited by hand may be out of order, so it's sorted by frame; events on the same
+    /// frame keep their order.
+    fn new(mut recording: Recording) -> Self {
+        recording.events.sort_by_key(|recorded| recorded.frame);
+        Replay {
+            events: recording.events.into(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Publishes the events recorded on `frame`, and any from earlier frames that are still due.
+    fn publish(&mut self, frame: u64, bus: &mut GameEventBus) {
+        while self
+            .events
+            .front()
+            .is_some_and(|recorded| recorded.frame <= frame)
+        {
+            bus.publish(self.events.pop_front().unwrap().event);
+        }
+    }
+}
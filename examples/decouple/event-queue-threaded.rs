@@ -0,0 +1,149 @@
+//! An event queue shared between threads: gameplay threads request sounds, an audio thread plays.
+//!
+//! As the chapter suggests, sounds are played on a thread of their own, so a slow sound card
+//! never stalls a frame. The queue (the library's `EventQueue`) is guarded by a mutex, and two
+//! condition variables let each side wait, rather than spin, for the other: the audio thread
+//! waits while there is nothing to play, and gameplay waits in the rare case that the queue is
+//! full. Shutting down wakes the audio thread, which plays whatever is left, then exits.
+//!
+//! ```bash
+//! cargo run --example decouple-event-queue-threaded
+//! ```
+
+use std::{
+    iter,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use game_patterns_rs::{event_queue::EventQueue, symbol::Symbol};
+
+fn main() {
+    let audio = AudioQueue::<4>::new();
+    let player = audio.spawn_player();
+
+    let gameplay: Vec<_> = ["jump", "land", "shoot"]
+        .into_iter()
+        .map(|name| {
+            let audio = audio.clone();
+            thread::spawn(move || {
+                for i in 1..=5 {
+                    audio.play(Symbol::intern(name), i as f32 / 5.0);
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        })
+        .collect();
+    for thread in gameplay {
+        thread.join().unwrap();
+    }
+
+    audio.shutdown();
+    let played = player.join().unwrap();
+    println!("Played {played} sounds");
+    assert_eq!(played, 15);
+}
+
+/// Sounds are referred to by (interned) name, which is as cheap to copy and compare as an integer.
+pub type SoundId = Symbol;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlayMessage {
+    id: SoundId,
+    volume: f32,
+}
+
+/// A queue of requests to play sounds, which can be shared (cloned) between threads.
+pub struct AudioQueue<const MAX: usize> {
+    shared: Arc<Shared<MAX>>,
+}
+
+struct Shared<const MAX: usize> {
+    state: Mutex<State<MAX>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+struct State<const MAX: usize> {
+    pending: EventQueue<PlayMessage, MAX>,
+    shutdown: bool,
+}
+
+impl<const MAX: usize> AudioQueue<MAX> {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    pending: EventQueue::new(),
+                    shutdown: false,
+                }),
+                not_empty: Condvar::new(),
+                not_full: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Queues a request to play a sound, waiting for room if the queue is full.
+    pub fn play(&self, id: SoundId, volume: f32) {
+        let state = self.shared.state.lock().unwrap();
+        let mut state = self
+            .shared
+            .not_full
+            .wait_while(state, |state| state.pending.is_full())
+            .unwrap();
+        // Waited for room, so this can't overflow.
+        state.pending.push(PlayMessage { id, volume }).unwrap();
+        self.shared.not_empty.notify_one();
+    }
+
+    /// Stops the audio thread, once it has played every request already queued.
+    pub fn shutdown(&self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.not_empty.notify_all();
+    }
+
+    /// Starts the audio thread, which returns how many sounds it played once shut down.
+    pub fn spawn_player(&self) -> thread::JoinHandle<usize> {
+        let shared = Arc::clone(&self.shared);
+        thread::spawn(move || {
+            let mut played = 0;
+            let mut playing = Vec::with_capacity(MAX);
+            loop {
+                // Holds the lock only long enough to take every pending request.
+                {
+                    let state = shared.state.lock().unwrap();
+                    let mut state = shared
+                        .not_empty
+                        .wait_while(state, |state| state.pending.is_empty() && !state.shutdown)
+                        .unwrap();
+                    if state.pending.is_empty() {
+                        return played;
+                    }
+                    playing.extend(iter::from_fn(|| state.pending.pop()));
+                    shared.not_full.notify_all();
+                }
+
+                // In practice, we'd find a sound channel, load the sound, and play it here.
+                for message in playing.drain(..) {
+                    println!("Playing {:?} at volume {}", message.id, message.volume);
+                    played += 1;
+                }
+            }
+        })
+    }
+}
+
+impl<const MAX: usize> Clone for AudioQueue<MAX> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<const MAX: usize> Default for AudioQueue<MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -4,25 +4,218 @@
 //! Command will take care of this with less complexity. You only need a queue when you want to
 //! decouple something in time.
 //!
+//! Playing a sound means finding a free channel (of the few the hardware mixes together) and
+//! starting it there, which gameplay code shouldn't wait on; [`SimpleAudioQueue`] holds each
+//! request until its `update()`, which hands them to a small [`AudioEngine`] all at once.
+//!
+//! That presumes every request is processed in one call to `update()`, though; the
+//! chapter's [`AudioQueue`] is a ring buffer instead (the library's `EventQueue`, which works for
+//! any type of event), so requests can be processed a few at a time, while more are added behind
+//! them.
+//!
+//! Requests waiting in the queue can also be aggregated: two requests to play the same sound in
+//! the same frame would just sound like one loud one, so by default they're merged, keeping the
+//! louder volume. How to merge (or not) is up to an [`Aggregate`] policy.
+//!
+//! Where a sound comes from matters too: [`SpatialSounds`] collects the sounds emitted during a
+//! frame in parallel arrays (one of positions, one of volumes, ...), and works out how loud each
+//! is for the listeners in a single pass over them, before queueing the audible ones.
+//!
+//! Events can also be scheduled for later, such as a grenade's fuse: the library's `Timers` hold
+//! them until they're due, then push them to the queue with everything else.
+//!
+//! When several systems (audio, achievements, logging) care about the same events, a
+//! `BroadcastQueue` lets each read at its own pace, with its own cursor, from one shared ring.
+//!
+//! Or, with the library's `GameEventBus`, systems publish typed events as they update, and each
+//! subscriber receives just the types it asked for, all at once when the frame flushes the bus.
+//!
 //! ```bash
 //! cargo run --example decouple-event-queue
 //! ```
 
+use std::{cell::Cell, collections::HashMap, rc::Rc, time::Duration};
+
+use game_patterns_rs::{
+    event_bus::{Budget, GameEventBus},
+    event_queue::{BroadcastQueue, Delay, EventQueue, Overflow, QueueFull, Timers},
+    symbol::Symbol,
+};
+
 fn main() {
+    let mut sounds = SoundRegistry::default();
+    sounds.register(Symbol::intern("jump"), Duration::from_millis(200), 0.5);
+    sounds.register(Symbol::intern("land"), Duration::from_millis(300), 0.8);
+    sounds.register(Symbol::intern("explode"), Duration::from_secs(1), 1.0);
+    let mut engine = AudioEngine::<2>::new(sounds);
+
+    // Three sounds requested at once, but only two channels to play them on.
     let mut audio = SimpleAudioQueue::<16>::new();
+    audio.play(Symbol::intern("jump"), 0.1);
+    audio.play(Symbol::intern("land"), 0.2);
+    audio.play(Symbol::intern("jump"), 0.3);
+    audio.update(&mut engine);
+    println!("Mixed output level: {:.2}", engine.mix());
+
+    // Louder than anything playing, so an explosion takes over the quietest channel.
+    audio.play(Symbol::intern("explode"), 1.0);
+    audio.update(&mut engine);
+    println!("Mixed output level: {:.2}", engine.mix());
+
+    engine.advance(Duration::from_millis(500));
+    println!("After 500ms, mixed output level: {:.2}", engine.mix());
+    assert_eq!(engine.playing(), 1);
+
+    // Only plays one sound per update; the rest wait their turn.
+    let mut audio = AudioQueue::<4>::new();
+    audio.play(Symbol::intern("jump"), 0.1).unwrap();
+    audio.play(Symbol::intern("land"), 0.2).unwrap();
+    audio.play(Symbol::intern("jump"), 0.3).unwrap();
+    assert_eq!(audio.len(), 2);
+    while !audio.is_empty() {
+        audio.update(1);
+    }
+
+    // Without aggregation, every request is played, even if it's the same sound.
+    let mut audio = AudioQueue::<4, _>::with_aggregate(NoAggregate);
+    audio.play(Symbol::intern("jump"), 0.1).unwrap();
+    audio.play(Symbol::intern("jump"), 0.3).unwrap();
+    assert_eq!(audio.update(4), 2);
+
+    // When full, by default the request is handed back; or, the oldest request can make way.
+    let mut audio = AudioQueue::<2, _>::with_aggregate(NoAggregate);
+    audio.play(Symbol::intern("step"), 0.1).unwrap();
+    audio.play(Symbol::intern("step"), 0.2).unwrap();
+    assert!(audio.play(Symbol::intern("step"), 0.3).is_err());
+    let mut audio = AudioQueue::<2, _>::with_policies(NoAggregate, Overflow::DropOldest);
+    for volume in [0.1, 0.2, 0.3] {
+        audio.play(Symbol::intern("step"), volume).unwrap();
+    }
+    assert_eq!(audio.update(2), 2);
+
+    // Footsteps and a far-off explosion, heard by two players (split-screen); only sounds near
+    // either are queued, and the two nearby footsteps merge into one request.
+    let mut emitted = SpatialSounds::default();
+    emitted.emit(Symbol::intern("step"), [1.0, 0.0], 0.5);
+    emitted.emit(Symbol::intern("step"), [52.0, 3.0], 0.5);
+    emitted.emit(Symbol::intern("explode"), [500.0, 0.0], 1.0);
+    emitted.emit(Symbol::intern("coin"), [45.0, 0.0], 0.8);
+    let mut audio = AudioQueue::<4>::new();
+    let falloff = Falloff {
+        near: 2.0,
+        far: 20.0,
+    };
+    let queued = emitted.flush([[0.0, 0.0], [50.0, 0.0]], falloff, &mut audio);
+    println!("Queued {queued} of 4 emitted sounds");
+    assert_eq!(queued, 3);
+    assert_eq!(audio.len(), 2);
+    audio.update(2);
+
+    // A grenade beeps after 2 seconds' worth of frames, and explodes after 3 seconds.
+    let mut timers = Timers::new();
+    timers.schedule(Delay::Frames(120), Symbol::intern("beep"));
+    timers.schedule(
+        Delay::Time(Duration::from_secs(3)),
+        Symbol::intern("explode"),
+    );
+    let mut events = EventQueue::<Symbol, 4>::new();
+    for frame in 1..=240 {
+        timers.update(Duration::from_secs(1) / 60, &mut events);
+        while let Some(event) = events.pop() {
+            println!("Frame {frame}: {event}!");
+        }
+    }
+    assert!(timers.is_empty());
+
+    // Audio and logging read every frame; achievements are only checked every few frames.
+    let mut events = BroadcastQueue::<Symbol, 8>::new();
+    let mut audio = events.subscribe();
+    let mut achievements = events.subscribe();
+    let mut logging = events.subscribe();
+    let jump = Symbol::intern("jump");
+    let mut jumps = 0;
+    for (frame, event) in ["jump", "land", "jump", "land", "coin", "jump"]
+        .into_iter()
+        .map(Symbol::intern)
+        .enumerate()
+    {
+        events.push(event);
+        for event in events.read(&mut audio) {
+            println!("Audio: playing {event}");
+        }
+        for event in events.read(&mut logging) {
+            println!("Log: frame {frame}, {event}");
+        }
+        if frame % 3 == 2 {
+            jumps += events
+                .read(&mut achievements)
+                .filter(|e| **e == jump)
+                .count();
+            println!("Achievements: {jumps} jumps so far");
+        }
+    }
+    assert_eq!(events.missed(&achievements), 0);
+
+    // Physics and combat publish while they update; audio and achievements hear about it later.
+    struct Collided {
+        speed: f32,
+    }
+    struct Died {
+        name: Symbol,
+    }
+    let mut bus = GameEventBus::new();
+    bus.subscribe(|event: &Collided| println!("Audio: thud at speed {}", event.speed));
+    let screams = bus.subscribe(|event: &Died| println!("Audio: {} screams", event.name));
+    let kills = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&kills);
+    bus.subscribe(move |_: &Died| counter.set(counter.get() + 1));
 
-    audio.play(SoundId, 0.1);
-    audio.play(SoundId, 0.2);
-    audio.play(SoundId, 0.3);
+    // Physics update.
+    bus.publish(Collided { speed: 3.5 });
+    // Combat update.
+    bus.publish(Died {
+        name: Symbol::intern("goblin"),
+    });
+    bus.publish(Died {
+        name: Symbol::intern("orc"),
+    });
+    println!("End of frame: delivering {} events", bus.pending());
+    bus.flush();
+    println!("Achievements: {} kills", kills.get());
+    assert_eq!(kills.get(), 2);
 
-    audio.update();
+    // A frame where a hundred enemies die at once (quietly); only 40 are delivered each frame.
+    bus.unsubscribe(screams);
+    let skeleton = Symbol::intern("skeleton");
+    for _ in 0..100 {
+        bus.publish(Died { name: skeleton });
+    }
+    kills.set(0);
+    let mut frame = 0;
+    while bus.pending() > 0 {
+        bus.flush_within(Budget::Events(40));
+        println!(
+            "Frame {frame}: {} kills, backlog of {}",
+            kills.get(),
+            bus.pending()
+        );
+        frame += 1;
+    }
+    let stats = bus.stats();
+    println!(
+        "Bus: at most {} events pending, {} delivered, after {:?} on average",
+        stats.high_water,
+        stats.delivered,
+        stats.average_latency()
+    );
+    assert_eq!(stats.high_water, 100);
 }
 
-#[derive(Clone, Copy)]
-pub struct SoundId;
+/// Sounds are referred to by (interned) name, which is as cheap to copy and compare as an integer.
+pub type SoundId = Symbol;
 
 #[allow(dead_code)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct PlayMessage {
     id: SoundId,
     volume: f32,
@@ -48,14 +241,297 @@ impl<const MAX: usize> SimpleAudioQueue<MAX> {
         self.pending += 1;
     }
 
-    pub fn update(&mut self) {
-        // In practice, we'd find sound channels, load sounds, and play them here.
+    pub fn update<const CHANNELS: usize>(&mut self, engine: &mut AudioEngine<CHANNELS>) {
+        for message in &mut self.buffer[..self.pending] {
+            engine.start(message.take().unwrap());
+        }
         self.pending = 0;
     }
 }
 
+/// What each sound is: how long it plays, and how loud it is at full volume.
+#[derive(Default)]
+pub struct SoundRegistry {
+    sounds: HashMap<SoundId, Sound>,
+}
+
+#[derive(Clone, Copy)]
+struct Sound {
+    length: Duration,
+    gain: f32,
+}
+
+impl SoundRegistry {
+    pub fn register(&mut self, id: SoundId, length: Duration, gain: f32) {
+        self.sounds.insert(id, Sound { length, gain });
+    }
+}
+
+/// A stand-in for the sound card: a fixed number of channels, mixed into one output.
+pub struct AudioEngine<const CHANNELS: usize> {
+    sounds: SoundRegistry,
+    channels: [Option<Channel>; CHANNELS],
+}
+
+/// A sound that is playing.
+#[derive(Clone, Copy)]
+struct Channel {
+    id: SoundId,
+    volume: f32,
+    remaining: Duration,
+}
+
+impl<const CHANNELS: usize> AudioEngine<CHANNELS> {
+    pub fn new(sounds: SoundRegistry) -> Self {
+        Self {
+            sounds,
+            channels: [None; CHANNELS],
+        }
+    }
+
+    /// Starts playing a sound on a free channel.
+    ///
+    /// If every channel is busy, the sound replaces the quietest one playing, if that is quieter;
+    /// otherwise, it isn't played at all.
+    pub fn start(&mut self, message: PlayMessage) {
+        let Some(sound) = self.sounds.sounds.get(&message.id) else {
+            println!("No such sound: {:?}", message.id);
+            return;
+        };
+        let started = Channel {
+            id: message.id,
+            volume: message.volume * sound.gain,
+            remaining: sound.length,
+        };
+
+        if let Some(free) = self.channels.iter().position(Option::is_none) {
+            println!("Playing {:?} on channel {free}", started.id);
+            self.channels[free] = Some(started);
+            return;
+        }
+        let (quietest, playing) = self
+            .channels
+            .iter_mut()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.unwrap().volume.total_cmp(&b.unwrap().volume))
+            .unwrap();
+        if playing.unwrap().volume < started.volume {
+            println!(
+                "All channels busy; {:?} replaces {:?} on channel {quietest}",
+                started.id,
+                playing.unwrap().id
+            );
+            *playing = Some(started);
+        } else {
+            println!("All channels busy; {:?} isn't played", started.id);
+        }
+    }
+
+    /// Plays every channel for `elapsed`, freeing those whose sounds are over.
+    pub fn advance(&mut self, elapsed: Duration) {
+        for slot in &mut self.channels {
+            if let Some(channel) = slot {
+                channel.remaining = channel.remaining.saturating_sub(elapsed);
+                if channel.remaining.is_zero() {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    /// Returns how many channels are playing.
+    pub fn playing(&self) -> usize {
+        self.channels.iter().flatten().count()
+    }
+
+    /// Returns the output level: every channel's volume added together, clipped to 1.
+    pub fn mix(&self) -> f32 {
+        let level: f32 = self.channels.iter().flatten().map(|c| c.volume).sum();
+        level.min(1.0)
+    }
+}
+
 impl<const MAX: usize> Default for SimpleAudioQueue<MAX> {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Requests to play sounds, in a ring buffer, aggregated as they are queued.
+pub struct AudioQueue<const MAX: usize, A = KeepLoudest> {
+    pending: EventQueue<PlayMessage, MAX>,
+    aggregate: A,
+}
+
+impl<const MAX: usize> AudioQueue<MAX> {
+    /// Creates a queue that merges requests for the same sound, keeping the louder volume.
+    pub fn new() -> Self {
+        Self::with_aggregate(KeepLoudest)
+    }
+}
+
+impl<const MAX: usize, A: Aggregate> AudioQueue<MAX, A> {
+    /// Creates a queue that merges requests (or not) as `aggregate` decides.
+    pub fn with_aggregate(aggregate: A) -> Self {
+        Self::with_policies(aggregate, Overflow::default())
+    }
+
+    /// Creates a queue that merges requests as `aggregate` decides, and overflows as `overflow`
+    /// says.
+    pub fn with_policies(aggregate: A, overflow: Overflow) -> Self {
+        Self {
+            pending: EventQueue::with_overflow(overflow),
+            aggregate,
+        }
+    }
+
+    /// Returns the number of pending requests.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns whether there are no pending requests.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns whether another request would not fit.
+    pub fn is_full(&self) -> bool {
+        self.pending.is_full()
+    }
+
+    /// Queues a request to play a sound, unless it can be merged into one already pending.
+    ///
+    /// If the queue is full (and the request wasn't merged), does as its [`Overflow`] says.
+    pub fn play(&mut self, id: SoundId, volume: f32) -> Result<(), QueueFull<PlayMessage>> {
+        let message = PlayMessage { id, volume };
+        for pending in self.pending.iter_mut() {
+            if self.aggregate.merge(pending, &message) {
+                return Ok(());
+            }
+        }
+        self.pending.push(message)
+    }
+
+    /// Processes up to `max` of the oldest requests, leaving the rest; returns how many.
+    pub fn update(&mut self, max: usize) -> usize {
+        let count = max.min(self.len());
+        for _ in 0..count {
+            let message = self.pending.pop().unwrap();
+
+            // In practice, we'd find a sound channel, load the sound, and play it here.
+            println!("Playing {:?} at volume {}", message.id, message.volume);
+        }
+        count
+    }
+}
+
+impl<const MAX: usize> Default for AudioQueue<MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a sound's volume falls off with its distance from a listener.
+#[derive(Clone, Copy, Debug)]
+pub struct Falloff {
+    /// Up to this distance, sounds are at full volume.
+    pub near: f32,
+
+    /// From this distance, sounds are silent; in between, they fade linearly.
+    pub far: f32,
+}
+
+impl Falloff {
+    fn gain(&self, distance: f32) -> f32 {
+        ((self.far - distance) / (self.far - self.near)).clamp(0.0, 1.0)
+    }
+}
+
+/// Sounds emitted somewhere in the world this frame, as parallel arrays, indexed by sound.
+///
+/// Working out how loud each sound is only reads positions and volumes, so they're kept in arrays
+/// of their own, walked once, front to back (see `optimize/data-locality.rs`).
+#[derive(Default)]
+pub struct SpatialSounds {
+    ids: Vec<SoundId>,
+    positions: Vec<[f32; 2]>,
+    volumes: Vec<f32>,
+
+    /// How loud each sound is where it's heard; reused every frame.
+    heard: Vec<f32>,
+}
+
+impl SpatialSounds {
+    /// Records a sound played at `position`, at `volume` (at its source).
+    pub fn emit(&mut self, id: SoundId, position: [f32; 2], volume: f32) {
+        self.ids.push(id);
+        self.positions.push(position);
+        self.volumes.push(volume);
+    }
+
+    /// Queues every sound audible to any of the listeners, at the volume the nearest one hears it;
+    /// returns how many were queued (or merged into a pending request), and forgets them all.
+    pub fn flush<const MAX: usize, A: Aggregate>(
+        &mut self,
+        listeners: impl AsRef<[[f32; 2]]>,
+        falloff: Falloff,
+        queue: &mut AudioQueue<MAX, A>,
+    ) -> usize {
+        let listeners = listeners.as_ref();
+
+        // One pass over the positions and volumes, writing to a third array; no branching on ids.
+        self.heard.clear();
+        self.heard.extend(
+            self.positions
+                .iter()
+                .zip(&self.volumes)
+                .map(|(&[x, y], volume)| {
+                    let nearest = listeners
+                        .iter()
+                        .map(|&[lx, ly]| (x - lx).hypot(y - ly))
+                        .fold(f32::INFINITY, f32::min);
+                    volume * falloff.gain(nearest)
+                }),
+        );
+
+        let mut queued = 0;
+        for (&id, &volume) in self.ids.iter().zip(&self.heard) {
+            if volume > 0.0 && queue.play(id, volume).is_ok() {
+                queued += 1;
+            }
+        }
+        self.ids.clear();
+        self.positions.clear();
+        self.volumes.clear();
+        queued
+    }
+}
+
+/// Decides whether a request can be merged into one already pending, rather than queued.
+pub trait Aggregate {
+    /// Merges `incoming` into `pending` and returns `true`, or returns `false` to queue it.
+    fn merge(&self, pending: &mut PlayMessage, incoming: &PlayMessage) -> bool;
+}
+
+/// Merges requests for the same sound, keeping the louder volume.
+pub struct KeepLoudest;
+
+impl Aggregate for KeepLoudest {
+    fn merge(&self, pending: &mut PlayMessage, incoming: &PlayMessage) -> bool {
+        if pending.id != incoming.id {
+            return false;
+        }
+        pending.volume = pending.volume.max(incoming.volume);
+        true
+    }
+}
+
+/// Never merges requests; every one is played.
+pub struct NoAggregate;
+
+impl Aggregate for NoAggregate {
+    fn merge(&self, _: &mut PlayMessage, _: &PlayMessage) -> bool {
+        false
+    }
+}
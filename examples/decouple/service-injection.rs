@@ -0,0 +1,122 @@
+//! Passing services in, as a contrast to locating them (see `service-locator.rs`).
+//!
+//! The chapter's alternative to a service locator is to pass each service to the code that needs
+//! it. Here, an [`AppBuilder`] is given every service once, and builds an [`App`] context, which
+//! is passed (explicitly) to everything that plays sounds or draws:
+//!
+//! - Every function says which services it uses, in its signature, and tests can pass doubles in
+//!   without any global state to reset.
+//! - But the context must be threaded through every layer between `main` and the code that uses
+//!   it, even layers that never use a service themselves.
+//!
+//! A locator makes the opposite trade: any code can find a service, without being handed it, but
+//! nothing in a function's signature says that it does.
+//!
+//! ```bash
+//! cargo run --example decouple-service-injection
+//! ```
+
+use std::{error::Error, fmt};
+
+fn main() {
+    let mut app = AppBuilder::default()
+        .with_audio(Box::new(ConsoleAudio))
+        .with_graphics(Box::new(ConsoleGraphics))
+        .build()
+        .unwrap();
+    update(&mut app);
+
+    // Audio is optional (it's silent if not given), but graphics are not.
+    let error = AppBuilder::default().build().err().unwrap();
+    println!("Couldn't build the app: {error}");
+}
+
+/// Has no use for services itself, but has to pass them on to code that does.
+fn update(app: &mut App) {
+    for frame in 0..2 {
+        player_jump(app, frame);
+    }
+}
+
+fn player_jump(app: &mut App, frame: u32) {
+    app.audio.play_sound();
+    app.graphics
+        .draw(&format!("the player jumping on frame {frame}"));
+}
+
+/// Every service, built once and passed to whatever needs them.
+pub struct App {
+    pub audio: Box<dyn Audio>,
+    pub graphics: Box<dyn Graphics>,
+}
+
+/// Collects services, checking that none are missing before building an [`App`].
+#[derive(Default)]
+pub struct AppBuilder {
+    audio: Option<Box<dyn Audio>>,
+    graphics: Option<Box<dyn Graphics>>,
+}
+
+impl AppBuilder {
+    pub fn with_audio(mut self, audio: Box<dyn Audio>) -> Self {
+        self.audio = Some(audio);
+        self
+    }
+
+    pub fn with_graphics(mut self, graphics: Box<dyn Graphics>) -> Self {
+        self.graphics = Some(graphics);
+        self
+    }
+
+    /// Builds the app; audio defaults to [`NullAudio`], but graphics must have been given.
+    pub fn build(self) -> Result<App, MissingService> {
+        Ok(App {
+            audio: self.audio.unwrap_or_else(|| Box::new(NullAudio)),
+            graphics: self.graphics.ok_or(MissingService("graphics"))?,
+        })
+    }
+}
+
+/// Returned by [`AppBuilder::build`] for a required service that was never given.
+#[derive(Debug)]
+pub struct MissingService(&'static str);
+
+impl fmt::Display for MissingService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no {} service was given", self.0)
+    }
+}
+
+impl Error for MissingService {}
+
+pub trait Audio {
+    fn play_sound(&mut self);
+}
+
+pub struct ConsoleAudio;
+
+impl Audio for ConsoleAudio {
+    fn play_sound(&mut self) {
+        println!("Playing sound");
+    }
+}
+
+pub struct NullAudio;
+
+impl Audio for NullAudio {
+    fn play_sound(&mut self) {
+        // Intentionally left blank.
+    }
+}
+
+pub trait Graphics {
+    fn draw(&mut self, what: &str);
+}
+
+pub struct ConsoleGraphics;
+
+impl Graphics for ConsoleGraphics {
+    fn draw(&mut self, what: &str) {
+        println!("Drawing {what}");
+    }
+}
@@ -6,34 +6,485 @@
 //! environment, so plumbing it through ten layers of methods just so one deeply nested call can get
 //! to it is adding needless complexity to your code.
 //!
+//! The [`Locator`] is where services are provided, and found; until an audio service is provided
+//! (or if it is taken away again), it finds a [`NullAudio`] instead, so callers never need to
+//! check whether there is one.
+//!
+//! Services can also be decorated: [`LoggedAudio`] logs every call before passing it on to the
+//! service it wraps, and can be switched on and off while the game runs, without changing any of
+//! the code that plays sounds.
+//!
+//! A game has more services than audio, though; a [`Registry`] holds any number of them, each
+//! found by its type (usually a trait object, such as `dyn Graphics`), and reports which one is
+//! missing if it was never provided.
+//!
+//! When the service doesn't need to change while the game runs, it can be chosen when compiling
+//! instead: [`DefaultAudio`] is picked by a cargo feature (`audio-console`, the default,
+//! `audio-debug`, or `audio-null`), and calling it needs no virtual dispatch at all.
+//!
+//! The [`Locator`] is thread-local, so each thread silently gets services of its own (none, unless
+//! it provides them). [`SyncLocator`] is global instead: a service provided on the main thread is
+//! found by every worker thread, too. It can also be given a constructor instead of a service,
+//! for services that are expensive to create (such as opening the audio device), which is only
+//! called when (and if) the service is first used, and only once, even if several threads ask for
+//! it at the same time.
+//!
+//! Tests often swap a service for a test double; [`Locator::override_audio`] does so until the
+//! guard it returns is dropped, then restores whatever was there before, so one test's double
+//! can't leak into the next.
+//!
+//! Finally, a service doesn't have to do its work right away: [`QueuedAudio`] only queues each
+//! sound (with the library's `EventQueue`, as in the Event Queue chapter), and an [`AudioSystem`]
+//! plays them all once per frame. Code that plays sounds through the locator can't tell the
+//! difference.
+//!
+//! Code that caches something about a service (such as a volume slider, which shows the audio
+//! device's maximum volume) needs to know when the service is replaced; [`Locator::observe`] adds
+//! an observer that is told whenever it is.
+//!
 //! ```bash
 //! cargo run --example decouple-service-locator
+//! cargo run --example decouple-service-locator --features audio-debug
 //! ```
 
-use std::cell::RefCell;
+use std::{
+    any::{self, Any, TypeId},
+    cell::{Cell, RefCell, RefMut},
+    collections::HashMap,
+    error::Error,
+    fmt, mem,
+    ops::DerefMut,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LazyLock, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use game_patterns_rs::event_queue::{EventQueue, Overflow};
 
 thread_local! {
     // ^^^^^^
     // thread_local gives us "static-like" access.
     //
-    //                Interior mutability, checked at runtime.
-    //                vvvvvvv
-    pub static AUDIO: RefCell<Box<dyn Audio>> = RefCell::new(Box::new(ConsoleAudio));
-    //                        ^^^^^^^^^^^^^^^
-    //                        Virtual dispatch.
-    //
-    // This could be combined further with #[cfg(feature = "...")] tags in order to have different
-    // implementations wired up at compile-time (e.g. a Debug-variant, a Null-variant for tests).
+    // For services wired up at compile-time instead (e.g. a Debug-variant, a Null-variant for
+    // tests), see DefaultAudio, below.
+    pub static LOCATOR: Locator = Locator::new();
+
+    pub static SERVICES: RefCell<Registry> = RefCell::new(Registry::default());
 }
 
 fn main() {
-    AUDIO.with(|cell| cell.borrow_mut().play_sound());
+    // Nothing provided yet, so this plays nothing (rather than crashing).
+    LOCATOR.with(|locator| locator.audio().play_sound());
+
+    LOCATOR.with(|locator| locator.provide(Box::new(ConsoleAudio)));
+    LOCATOR.with(|locator| locator.audio().play_sound());
+
+    // Logs each sound played, while debugging.
+    LOCATOR.with(|locator| locator.enable_audio_logging());
+    LOCATOR.with(|locator| locator.audio().play_sound());
+    LOCATOR.with(|locator| locator.disable_audio_logging());
+    LOCATOR.with(|locator| locator.audio().play_sound());
+
+    // Audio can be turned off again, i.e. while muted.
+    LOCATOR.with(|locator| locator.take_audio());
+    LOCATOR.with(|locator| locator.audio().play_sound());
+
+    // Any number of services, each found by type.
+    SERVICES.with_borrow_mut(|services| {
+        services.provide::<dyn Graphics>(Box::new(ConsoleGraphics));
+        services.provide::<dyn Random>(Box::new(Lcg(7)));
+    });
+    SERVICES
+        .with_borrow_mut(|services| -> Result<(), MissingService> {
+            let roll = services.get::<dyn Random>()?.next() % 6 + 1;
+            services
+                .get::<dyn Graphics>()?
+                .draw(&format!("a die showing {roll}"));
+            Ok(())
+        })
+        .unwrap();
+    let error = SERVICES
+        .with_borrow_mut(|services| services.get::<dyn SaveSystem>().map(|_| ()))
+        .unwrap_err();
+    println!("Saving failed: {error}");
+
+    // Chosen at compile time, and called directly.
+    let mut audio = DefaultAudio::default();
+    Audio::play_sound(&mut audio);
+
+    // Provided on the main thread; played on several others.
+    let counter = Arc::new(CountingAudio::default());
+    SYNC_LOCATOR.provide(counter.clone());
+    LOCATOR.with(|locator| locator.provide(Box::new(ConsoleAudio)));
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                SYNC_LOCATOR.audio().play_sound();
+
+                // This thread's (thread-local) locator has no audio; this plays nothing.
+                LOCATOR.with(|locator| locator.audio().play_sound());
+            });
+        }
+    });
+    println!("Worker threads played {} sounds", counter.played());
+    assert_eq!(counter.played(), 4);
+
+    // Opened on first use, by whichever thread gets there first.
+    let device = Arc::new(CountingAudio::default());
+    let opened = Arc::clone(&device);
+    SYNC_LOCATOR.provide_with(move || {
+        println!("Opening the audio device...");
+        thread::sleep(Duration::from_millis(50));
+        opened
+    });
+    println!("Provided the audio device; it isn't open yet");
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| SYNC_LOCATOR.audio().play_sound());
+        }
+    });
+    assert_eq!(device.played(), 4);
+
+    // As a test would: the double only counts sounds, and only until the guard is dropped.
+    {
+        let played = Rc::new(Cell::new(0));
+        let _guard = Locator::override_audio(RecordingAudio(Rc::clone(&played)));
+        jump();
+        jump();
+        assert_eq!(played.get(), 2);
+    }
+    jump();
+
+    // Sounds are queued while the frame updates, and played at the end of it.
+    let (mut system, queued) = AudioSystem::new(Box::new(ConsoleAudio));
+    LOCATOR.with(|locator| locator.provide(Box::new(queued)));
+    jump();
+    jump();
+    println!("End of frame; {} sounds queued", system.pending());
+    assert_eq!(system.update(), 2);
+
+    // A volume slider caches the device's maximum volume, refreshing it when the device changes.
+    let slider = Rc::new(VolumeSlider::default());
+    let observer = LOCATOR.with(|locator| {
+        let slider = Rc::clone(&slider);
+        locator.observe(move |ServiceEvent::AudioReplaced| slider.refresh())
+    });
+    slider.refresh();
+    LOCATOR.with(|locator| locator.take_audio());
+    LOCATOR.with(|locator| locator.provide(Box::new(ConsoleAudio)));
+    assert_eq!(slider.max.get(), 1.0);
+    LOCATOR.with(|locator| locator.unobserve(observer));
+}
+
+/// Shows (and caches) the audio service's maximum volume.
+#[derive(Default)]
+pub struct VolumeSlider {
+    max: Cell<f32>,
+}
+
+impl VolumeSlider {
+    fn refresh(&self) {
+        let max = LOCATOR.with(|locator| locator.audio().max_volume());
+        self.max.set(max);
+        println!("Volume slider: 0 to {max}");
+    }
+}
+
+/// Game code, which plays a sound through the locator.
+fn jump() {
+    LOCATOR.with(|locator| locator.audio().play_sound());
+}
+
+/// Like [`Audio`], but can be played from any thread at once.
+pub trait SharedAudio: Send + Sync {
+    fn play_sound(&self);
+}
+
+/// Finds services from any thread.
+pub static SYNC_LOCATOR: SyncLocator = SyncLocator::new();
+
+pub struct SyncLocator {
+    audio: RwLock<Option<Service>>,
+}
+
+/// Creates a service, the first time it is used.
+type Constructor = Box<dyn FnOnce() -> Arc<dyn SharedAudio> + Send>;
+
+enum Service {
+    Ready(Arc<dyn SharedAudio>),
+    Lazy(Arc<LazyLock<Arc<dyn SharedAudio>, Constructor>>),
 }
 
-pub trait Audio {
+impl SyncLocator {
+    pub const fn new() -> Self {
+        SyncLocator {
+            audio: RwLock::new(None),
+        }
+    }
+
+    /// Returns the audio service; [`NullAudio`] if none was provided.
+    ///
+    /// The service is shared, not borrowed, so providing another doesn't wait for callers to be
+    /// finished with this one.
+    pub fn audio(&self) -> Arc<dyn SharedAudio> {
+        let lazy = match &*self.audio.read().unwrap() {
+            Some(Service::Ready(audio)) => return Arc::clone(audio),
+            Some(Service::Lazy(lazy)) => Arc::clone(lazy),
+            None => return Arc::new(NullAudio),
+        };
+
+        // Created outside the lock, so a slow constructor doesn't block providing another.
+        Arc::clone(LazyLock::force(&lazy))
+    }
+
+    /// Provides the audio service.
+    pub fn provide(&self, audio: Arc<dyn SharedAudio>) {
+        *self.audio.write().unwrap() = Some(Service::Ready(audio));
+    }
+
+    /// Provides the audio service, to be created by `constructor` the first time it is used.
+    pub fn provide_with(
+        &self,
+        constructor: impl FnOnce() -> Arc<dyn SharedAudio> + Send + 'static,
+    ) {
+        let lazy = LazyLock::new(Box::new(constructor) as Constructor);
+        *self.audio.write().unwrap() = Some(Service::Lazy(Arc::new(lazy)));
+    }
+}
+
+impl Default for SyncLocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedAudio for ConsoleAudio {
+    fn play_sound(&self) {
+        println!("Playing sound");
+    }
+}
+
+impl SharedAudio for NullAudio {
+    fn play_sound(&self) {
+        // Intentionally left blank.
+    }
+}
+
+/// Counts the sounds played, from every thread.
+#[derive(Default)]
+pub struct CountingAudio {
+    played: AtomicUsize,
+}
+
+impl CountingAudio {
+    pub fn played(&self) -> usize {
+        self.played.load(Ordering::Relaxed)
+    }
+}
+
+impl SharedAudio for CountingAudio {
+    fn play_sound(&self) {
+        self.played.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The audio service chosen at compile time, by cargo feature.
+#[cfg(feature = "audio-null")]
+pub type DefaultAudio = NullAudio;
+
+/// The audio service chosen at compile time, by cargo feature.
+#[cfg(all(feature = "audio-debug", not(feature = "audio-null")))]
+pub type DefaultAudio = DebugAudio;
+
+/// The audio service chosen at compile time, by cargo feature.
+#[cfg(not(any(feature = "audio-debug", feature = "audio-null")))]
+pub type DefaultAudio = ConsoleAudio;
+
+/// Services of any type, found by type.
+#[derive(Default)]
+pub struct Registry {
+    // Each value is a `Box<T>`, where `T` is the key's type.
+    services: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Registry {
+    /// Provides the service of type `T`, returning the one it replaces, if any.
+    pub fn provide<T: ?Sized + 'static>(&mut self, service: Box<T>) -> Option<Box<T>> {
+        let previous = self.services.insert(TypeId::of::<T>(), Box::new(service))?;
+        Some(*previous.downcast().unwrap())
+    }
+
+    /// Returns the service of type `T`, or an error naming `T` if none was provided.
+    pub fn get<T: ?Sized + 'static>(&mut self) -> Result<&mut T, MissingService> {
+        let service = self
+            .services
+            .get_mut(&TypeId::of::<T>())
+            .ok_or(MissingService(any::type_name::<T>()))?;
+        Ok(service.downcast_mut::<Box<T>>().unwrap())
+    }
+}
+
+/// Returned by [`Registry::get`] for a service that was never provided.
+#[derive(Debug)]
+pub struct MissingService(&'static str);
+
+impl fmt::Display for MissingService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no {} service was provided", self.0)
+    }
+}
+
+impl Error for MissingService {}
+
+pub struct Locator {
+    //        Interior mutability, checked at runtime.
+    //        vvvvvvv
+    services: RefCell<Services>,
+    observers: RefCell<Vec<(ObserverId, Box<Observer>)>>,
+    next_observer: Cell<usize>,
+}
+
+/// Sent to observers (see [`Locator::observe`]) when a service is replaced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceEvent {
+    AudioReplaced,
+}
+
+type Observer = dyn Fn(ServiceEvent);
+
+/// Identifies an observer added with [`Locator::observe`], so it can be removed again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObserverId(usize);
+
+struct Services {
+    audio: Option<Box<dyn Audio>>,
+    //            ^^^^^^^^^^^^^^
+    //            Virtual dispatch.
+    null: NullAudio,
+}
+
+impl Locator {
+    pub fn new() -> Self {
+        Locator {
+            services: RefCell::new(Services {
+                audio: None,
+                null: NullAudio,
+            }),
+            observers: RefCell::new(Vec::new()),
+            next_observer: Cell::new(0),
+        }
+    }
+
+    /// Returns the audio service; [`NullAudio`] if none was provided.
+    pub fn audio(&self) -> impl DerefMut<Target = dyn Audio> + '_ {
+        RefMut::map(self.services.borrow_mut(), |services| {
+            match &mut services.audio {
+                Some(audio) => audio.as_mut(),
+                None => &mut services.null,
+            }
+        })
+    }
+
+    /// Provides the audio service, returning the one it replaces, if any.
+    pub fn provide(&self, audio: Box<dyn Audio>) -> Option<Box<dyn Audio>> {
+        self.replace_audio(Some(audio))
+    }
+
+    /// Removes the audio service, returning it; until another is provided, audio is silent.
+    pub fn take_audio(&self) -> Option<Box<dyn Audio>> {
+        self.replace_audio(None)
+    }
+
+    /// Adds an observer, which is called whenever a service is replaced.
+    pub fn observe(&self, observer: impl Fn(ServiceEvent) + 'static) -> ObserverId {
+        let id = ObserverId(self.next_observer.get());
+        self.next_observer.set(id.0 + 1);
+        self.observers.borrow_mut().push((id, Box::new(observer)));
+        id
+    }
+
+    /// Removes an observer, returning `false` if it was already removed.
+    pub fn unobserve(&self, observer: ObserverId) -> bool {
+        let mut observers = self.observers.borrow_mut();
+        let len = observers.len();
+        observers.retain(|(id, _)| *id != observer);
+        observers.len() != len
+    }
+
+    fn replace_audio(&self, audio: Option<Box<dyn Audio>>) -> Option<Box<dyn Audio>> {
+        let previous = mem::replace(&mut self.services.borrow_mut().audio, audio);
+
+        // Observers are called once the new service is in place, so they can use it.
+        for (_, observer) in self.observers.borrow().iter() {
+            observer(ServiceEvent::AudioReplaced);
+        }
+        previous
+    }
+
+    /// Provides `audio` (on this thread) until the returned guard is dropped, which restores the
+    /// audio service it replaced.
+    #[must_use = "the override ends as soon as the guard is dropped"]
+    pub fn override_audio(audio: impl Audio) -> AudioOverride {
+        let previous = LOCATOR.with(|locator| locator.provide(Box::new(audio)));
+        AudioOverride { previous }
+    }
+
+    /// Wraps the audio service in a [`LoggedAudio`], unless it already is one.
+    pub fn enable_audio_logging(&self) {
+        let mut services = self.services.borrow_mut();
+        let audio = services.audio.take().unwrap_or_else(|| Box::new(NullAudio));
+        services.audio = Some(if (audio.as_ref() as &dyn Any).is::<LoggedAudio>() {
+            audio
+        } else {
+            Box::new(LoggedAudio { wrapped: audio })
+        });
+    }
+
+    /// Unwraps the audio service, if it is a [`LoggedAudio`].
+    pub fn disable_audio_logging(&self) {
+        let mut services = self.services.borrow_mut();
+        if let Some(audio) = services
+            .audio
+            .take_if(|audio| (audio.as_ref() as &dyn Any).is::<LoggedAudio>())
+        {
+            let logged = (audio as Box<dyn Any>).downcast::<LoggedAudio>().unwrap();
+            services.audio = Some(logged.wrapped);
+        }
+    }
+}
+
+impl Default for Locator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returned by [`Locator::override_audio`]; restores the previous audio service when dropped.
+pub struct AudioOverride {
+    previous: Option<Box<dyn Audio>>,
+}
+
+impl Drop for AudioOverride {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        LOCATOR.with(|locator| locator.replace_audio(previous));
+    }
+}
+
+pub trait Audio: Any {
     fn play_sound(&mut self);
+
+    /// Returns the loudest the service can play.
+    fn max_volume(&self) -> f32 {
+        1.0
+    }
 }
 
+#[derive(Default)]
 pub struct ConsoleAudio;
 
 impl Audio for ConsoleAudio {
@@ -42,10 +493,134 @@ impl Audio for ConsoleAudio {
     }
 }
 
+#[derive(Default)]
 pub struct NullAudio;
 
 impl Audio for NullAudio {
     fn play_sound(&mut self) {
         // Intentionally left blank.
     }
+
+    fn max_volume(&self) -> f32 {
+        0.0
+    }
+}
+
+/// Counts and describes every sound played, for debugging.
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct DebugAudio {
+    played: usize,
+}
+
+impl Audio for DebugAudio {
+    fn play_sound(&mut self) {
+        self.played += 1;
+        println!("[debug audio] sound #{} played", self.played);
+    }
+}
+
+/// Logs every call, then passes it on to the audio service it wraps.
+pub struct LoggedAudio {
+    wrapped: Box<dyn Audio>,
+}
+
+impl Audio for LoggedAudio {
+    fn play_sound(&mut self) {
+        println!("[audio] play_sound()");
+        self.wrapped.play_sound();
+    }
+
+    fn max_volume(&self) -> f32 {
+        self.wrapped.max_volume()
+    }
+}
+
+pub trait Graphics {
+    fn draw(&mut self, what: &str);
+}
+
+pub struct ConsoleGraphics;
+
+impl Graphics for ConsoleGraphics {
+    fn draw(&mut self, what: &str) {
+        println!("Drawing {what}");
+    }
+}
+
+pub trait Random {
+    fn next(&mut self) -> u32;
+}
+
+/// A (tiny, and not very random) linear congruential generator.
+pub struct Lcg(u32);
+
+impl Random for Lcg {
+    fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        self.0 >> 16
+    }
+}
+
+#[allow(dead_code)]
+pub trait SaveSystem {
+    fn save(&mut self, slot: u32);
+}
+
+/// A test double, which only counts the sounds it is asked to play.
+pub struct RecordingAudio(Rc<Cell<usize>>);
+
+impl Audio for RecordingAudio {
+    fn play_sound(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// A request to play a sound, waiting in the [`AudioSystem`]'s queue.
+pub struct PlaySound;
+
+/// An audio service that only queues each sound, for an [`AudioSystem`] to play later.
+pub struct QueuedAudio {
+    queue: Rc<RefCell<EventQueue<PlaySound, 16>>>,
+}
+
+impl Audio for QueuedAudio {
+    fn play_sound(&mut self) {
+        // Drops sounds if the queue is full, so this can't fail.
+        let _ = self.queue.borrow_mut().push(PlaySound);
+    }
+}
+
+/// Plays the sounds queued by a [`QueuedAudio`], once per frame.
+pub struct AudioSystem {
+    queue: Rc<RefCell<EventQueue<PlaySound, 16>>>,
+    output: Box<dyn Audio>,
+}
+
+impl AudioSystem {
+    /// Creates the system, which plays sounds to `output`, and the service that queues them.
+    pub fn new(output: Box<dyn Audio>) -> (Self, QueuedAudio) {
+        let queue = Rc::new(RefCell::new(EventQueue::with_overflow(
+            Overflow::DropNewest,
+        )));
+        let service = QueuedAudio {
+            queue: Rc::clone(&queue),
+        };
+        (AudioSystem { queue, output }, service)
+    }
+
+    /// Returns the number of sounds waiting to be played.
+    pub fn pending(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    /// Plays every queued sound; returns how many.
+    pub fn update(&mut self) -> usize {
+        let mut played = 0;
+        while self.queue.borrow_mut().pop().is_some() {
+            self.output.play_sound();
+            played += 1;
+        }
+        played
+    }
 }
@@ -0,0 +1,62 @@
+//! A parent/child hierarchy of transforms (see `src/transform.rs`): a turret on a tank.
+//!
+//! The turret's transform is relative to the tank, and the gun's to the turret, so each only
+//! describes its own movement; world positions are worked out once per frame, parents first.
+//!
+//! ```bash
+//! cargo run --example decouple-transform
+//! ```
+
+use std::f32::consts::FRAC_PI_4;
+
+use game_patterns_rs::{
+    ecs::{Entity, World},
+    transform::{self, GlobalTransform, Transform},
+};
+
+fn main() {
+    let mut world = World::new();
+    let tank = world.spawn();
+    world.insert(tank, Transform::at(0.0, 0.0));
+    world.insert(tank, Name("tank"));
+
+    let turret = world.spawn();
+    world.insert(turret, Transform::at(0.0, 1.0));
+    world.insert(turret, Name("turret"));
+    transform::set_parent(&mut world, turret, tank);
+
+    let gun = world.spawn();
+    world.insert(gun, Transform::at(2.0, 0.0));
+    world.insert(gun, Name("gun"));
+    transform::set_parent(&mut world, gun, turret);
+
+    // The tank drives forward, and the turret turns an eighth of a turn each frame; the gun
+    // follows both without being touched.
+    for frame in 0..3 {
+        world.get_mut::<Transform>(tank).unwrap().x += 1.0;
+        world.get_mut::<Transform>(turret).unwrap().rotation += FRAC_PI_4;
+        transform::propagate(&mut world);
+        println!("Frame {frame}:");
+        for entity in [tank, turret, gun] {
+            print_global(&world, entity);
+        }
+    }
+
+    // Destroying the tank destroys everything mounted on it.
+    transform::despawn_recursive(&mut world, tank);
+    println!("After the tank is destroyed: {} entities", world.len());
+}
+
+/// What to call an entity when printing it.
+pub struct Name(&'static str);
+
+fn print_global(world: &World, entity: Entity) {
+    let name = world.get::<Name>(entity).unwrap().0;
+    let global = world.get::<GlobalTransform>(entity).unwrap().0;
+    println!(
+        "  {name:<6} at ({:>5.2}, {:>5.2}), facing {:>3.0}°",
+        global.x,
+        global.y,
+        global.rotation.to_degrees()
+    );
+}
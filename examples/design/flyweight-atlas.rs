@@ -0,0 +1,201 @@
+//! A texture atlas: the flyweight pattern as GPUs see it.
+//!
+//! Rather than a texture per tile, every tile image is packed into one large texture (a "sprite
+//! sheet"), and each tile type is just a region (UV rectangle) of it. A map of thousands of tiles
+//! then needs one texture bind and one draw call; each cell only stores a tiny [`TileId`].
+//!
+//! ```bash
+//! cargo run --example design-flyweight-atlas
+//! ```
+
+use std::collections::HashMap;
+
+fn main() {
+    // A 64x64 sheet of 16x16 tiles (4x4 tiles), with names for the ones we use.
+    let mut atlas = TileAtlas::new(64, 64, 16);
+    let grass = atlas.name("grass", 0, 0);
+    let water = atlas.name("water", 1, 0);
+    let wall = atlas.name("wall", 0, 1);
+
+    for name in ["grass", "water", "wall"] {
+        let tile = atlas.find(name).unwrap();
+        println!("{name:>5} => {:?}", atlas.region(tile));
+    }
+    println!();
+
+    // A small map, which is only tile ids (the extrinsic state is just the position in the map).
+    #[rustfmt::skip]
+    let map = TileMap {
+        width: 6,
+        tiles: vec![
+            wall,  wall,  wall,  wall,  wall,  wall,
+            wall,  grass, grass, water, grass, wall,
+            wall,  grass, water, water, grass, wall,
+            wall,  wall,  wall,  wall,  wall,  wall,
+        ],
+    };
+
+    // Any renderer backend can consume the atlas and map.
+    let mut backend = ConsoleBackend::default();
+    map.render(&atlas, &mut backend);
+}
+
+/// Identifies a tile (a region of the atlas texture).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileId(u16);
+
+/// A rectangle in texture coordinates, where `(0.0, 0.0)` is the top-left of the texture and
+/// `(1.0, 1.0)` is the bottom-right.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+/// The shared data for every tile: one texture, divided into equally sized regions.
+pub struct TileAtlas {
+    size: [u32; 2],
+    tile_size: u32,
+    regions: Vec<UvRect>,
+    names: HashMap<String, TileId>,
+}
+
+impl TileAtlas {
+    /// Creates an atlas for a texture of the given size, divided into square tiles.
+    ///
+    /// Tiles are numbered left-to-right, top-to-bottom.
+    ///
+    /// # Panics
+    ///
+    /// If the texture isn't evenly divisible into tiles.
+    pub fn new(width: u32, height: u32, tile_size: u32) -> Self {
+        assert!(tile_size > 0);
+        assert!(width % tile_size == 0 && height % tile_size == 0);
+
+        let (columns, rows) = (width / tile_size, height / tile_size);
+        let mut regions = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * tile_size, row * tile_size);
+                regions.push(UvRect {
+                    min: [x as f32 / width as f32, y as f32 / height as f32],
+                    max: [
+                        (x + tile_size) as f32 / width as f32,
+                        (y + tile_size) as f32 / height as f32,
+                    ],
+                });
+            }
+        }
+
+        TileAtlas {
+            size: [width, height],
+            tile_size,
+            regions,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Names the tile at the given column and row of the atlas, and returns its id.
+    ///
+    /// # Panics
+    ///
+    /// If the column or row is outside the atlas, or the tile's index doesn't fit in a [`TileId`].
+    pub fn name(&mut self, name: impl Into<String>, column: u32, row: u32) -> TileId {
+        let (columns, rows) = (self.size[0] / self.tile_size, self.size[1] / self.tile_size);
+        assert!(column < columns, "column {column} is outside the atlas");
+        assert!(row < rows, "row {row} is outside the atlas");
+        let index = u64::from(row) * u64::from(columns) + u64::from(column);
+        let tile = TileId(u16::try_from(index).expect("Too many tiles for a TileId"));
+        self.names.insert(name.into(), tile);
+        tile
+    }
+
+    /// Returns the tile with the given name, if any.
+    pub fn find(&self, name: &str) -> Option<TileId> {
+        self.names.get(name).copied()
+    }
+
+    /// Returns the region of the texture that the given tile occupies.
+    pub fn region(&self, tile: TileId) -> UvRect {
+        self.regions[tile.0 as usize]
+    }
+
+    /// Returns the size, in pixels, of the texture.
+    pub fn texture_size(&self) -> [u32; 2] {
+        self.size
+    }
+}
+
+/// A quad (two triangles, in practice) to draw: where on screen, and which part of the atlas.
+#[derive(Clone, Copy, Debug)]
+pub struct Quad {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv: UvRect,
+}
+
+/// What a renderer (OpenGL, wgpu, a terminal, ...) implements to draw from an atlas.
+pub trait RenderBackend {
+    /// Binds the atlas texture; called once per frame, not once per tile.
+    fn bind_atlas(&mut self, atlas: &TileAtlas);
+
+    /// Draws every quad in one batch.
+    fn draw_quads(&mut self, quads: &[Quad]);
+}
+
+/// A map of tiles, each of which is only a [`TileId`].
+pub struct TileMap {
+    width: usize,
+    tiles: Vec<TileId>,
+}
+
+impl TileMap {
+    /// Converts the map to quads and hands them to the backend.
+    pub fn render(&self, atlas: &TileAtlas, backend: &mut impl RenderBackend) {
+        let size = atlas.tile_size as f32;
+        let quads: Vec<Quad> = self
+            .tiles
+            .iter()
+            .enumerate()
+            .map(|(i, &tile)| Quad {
+                position: [
+                    (i % self.width) as f32 * size,
+                    (i / self.width) as f32 * size,
+                ],
+                size: [size, size],
+                uv: atlas.region(tile),
+            })
+            .collect();
+
+        backend.bind_atlas(atlas);
+        backend.draw_quads(&quads);
+    }
+}
+
+/// A backend that describes what it would have sent to the GPU.
+#[derive(Default)]
+pub struct ConsoleBackend {
+    binds: usize,
+    draws: usize,
+}
+
+impl RenderBackend for ConsoleBackend {
+    fn bind_atlas(&mut self, atlas: &TileAtlas) {
+        self.binds += 1;
+        let [width, height] = atlas.texture_size();
+        println!("bindTexture({width}x{height} atlas)");
+    }
+
+    fn draw_quads(&mut self, quads: &[Quad]) {
+        self.draws += 1;
+        println!("drawQuads({} quads)", quads.len());
+        for quad in quads.iter().take(3) {
+            println!("  {:?}", quad);
+        }
+        println!("  ...");
+        println!(
+            "{} texture bind(s) and {} draw call(s) this frame.",
+            self.binds, self.draws
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! A grid split into fixed-size chunks, so only the part of a (huge) world near the camera exists.
+//!
+//! Each chunk is a small grid of references to shared terrain (see `design/flyweight.rs`). Chunks
+//! are loaded (here, generated) as the camera approaches and unloaded once it moves away, so memory
+//! use stays flat no matter how far the camera travels.
+//!
+//! ```bash
+//! cargo run --example design-flyweight-chunks
+//! ```
+
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn main() {
+    let mut world = ChunkedGrid::<&TerrainData>::new();
+    let (mut x, mut y) = (0, 0);
+    assert!(world.is_empty());
+
+    for step in 0..4 {
+        let (loaded, unloaded) = world.stream_around(x, y, 2, generate);
+
+        // Pitch a tent where the camera starts; it's forgotten once its chunk is unloaded.
+        if step == 0 {
+            world.set(x, y, &TENT);
+        }
+
+        println!(
+            "Camera at ({x}, {y}): loaded {loaded}, unloaded {unloaded}, {} chunks in memory",
+            world.len()
+        );
+
+        // Print what the camera sees; anything in an unloaded chunk would be blank.
+        for view_y in y - 4..y + 4 {
+            for view_x in x - 24..x + 24 {
+                let terrain = world.get(view_x, view_y).copied().unwrap_or(&EMPTY);
+                print!("{}", terrain.display_as);
+            }
+            println!();
+        }
+        println!();
+
+        // Pan the camera.
+        x += 40;
+        y += 8;
+    }
+}
+
+/// The shared (intrinsic) state of a terrain type.
+pub struct TerrainData {
+    display_as: char,
+}
+
+static EMPTY: TerrainData = TerrainData { display_as: ' ' };
+static GRASS: TerrainData = TerrainData { display_as: '.' };
+static HILL: TerrainData = TerrainData { display_as: '^' };
+static TREE: TerrainData = TerrainData { display_as: '♣' };
+static TENT: TerrainData = TerrainData { display_as: 'A' };
+
+impl Default for &TerrainData {
+    fn default() -> Self {
+        &EMPTY
+    }
+}
+
+/// Generates the chunk at the given position.
+///
+/// The same position always generates the same chunk, so unloading a chunk loses nothing. A game
+/// that lets players change terrain would save modified chunks instead.
+fn generate(position: ChunkPos) -> Chunk<&'static TerrainData> {
+    let seed = (position.0 as u64) << 32 | position.1 as u32 as u64;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut chunk = Chunk::default();
+    for cell in &mut chunk.cells {
+        *cell = match rng.gen_range(0..10) {
+            0 => &HILL,
+            1 => &TREE,
+            _ => &GRASS,
+        };
+    }
+    chunk
+}
+
+/// The position of a chunk, in chunks (not cells).
+pub type ChunkPos = (i64, i64);
+
+/// A square of `Chunk::SIZE * Chunk::SIZE` cells.
+pub struct Chunk<T> {
+    cells: Vec<T>,
+}
+
+impl<T> Chunk<T> {
+    /// The width and height of a chunk, in cells.
+    pub const SIZE: usize = 16;
+
+    /// Returns the cell at the given coordinates, relative to the chunk.
+    pub fn get(&self, x: usize, y: usize) -> &T {
+        &self.cells[y * Self::SIZE + x]
+    }
+
+    /// Sets the cell at the given coordinates, relative to the chunk.
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        self.cells[y * Self::SIZE + x] = value;
+    }
+}
+
+impl<T> Default for Chunk<T>
+where
+    T: Clone + Default,
+{
+    fn default() -> Self {
+        Chunk {
+            cells: vec![T::default(); Self::SIZE * Self::SIZE],
+        }
+    }
+}
+
+/// An unbounded grid, of which only the loaded chunks are stored.
+pub struct ChunkedGrid<T> {
+    chunks: HashMap<ChunkPos, Chunk<T>>,
+}
+
+impl<T> ChunkedGrid<T> {
+    /// Creates a new grid with no chunks loaded.
+    pub fn new() -> Self {
+        ChunkedGrid {
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Translates world coordinates to the chunk containing them and the offset within that chunk.
+    pub fn locate(x: i64, y: i64) -> (ChunkPos, (usize, usize)) {
+        let size = Chunk::<T>::SIZE as i64;
+        (
+            (x.div_euclid(size), y.div_euclid(size)),
+            (x.rem_euclid(size) as usize, y.rem_euclid(size) as usize),
+        )
+    }
+
+    /// Returns the cell at the given world coordinates, or `None` if its chunk is not loaded.
+    pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+        let (chunk, (x, y)) = Self::locate(x, y);
+        self.chunks.get(&chunk).map(|chunk| chunk.get(x, y))
+    }
+
+    /// Sets the cell at the given world coordinates, returning `false` if its chunk is not loaded.
+    pub fn set(&mut self, x: i64, y: i64, value: T) -> bool {
+        let (chunk, (x, y)) = Self::locate(x, y);
+        match self.chunks.get_mut(&chunk) {
+            Some(chunk) => {
+                chunk.set(x, y, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns whether the chunk at the given position is loaded.
+    pub fn is_loaded(&self, position: ChunkPos) -> bool {
+        self.chunks.contains_key(&position)
+    }
+
+    /// Loads the given chunk, replacing (and returning) any chunk already loaded at that position.
+    pub fn load(&mut self, position: ChunkPos, chunk: Chunk<T>) -> Option<Chunk<T>> {
+        self.chunks.insert(position, chunk)
+    }
+
+    /// Unloads and returns the chunk at the given position, if it was loaded.
+    pub fn unload(&mut self, position: ChunkPos) -> Option<Chunk<T>> {
+        self.chunks.remove(&position)
+    }
+
+    /// Returns the number of loaded chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns whether no chunks are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Loads every chunk within `radius` chunks of the given world coordinates, and unloads the rest.
+    ///
+    /// Returns how many chunks were loaded and unloaded, respectively.
+    pub fn stream_around(
+        &mut self,
+        x: i64,
+        y: i64,
+        radius: i64,
+        mut load: impl FnMut(ChunkPos) -> Chunk<T>,
+    ) -> (usize, usize) {
+        let ((center_x, center_y), _) = Self::locate(x, y);
+        let in_range = |(chunk_x, chunk_y): ChunkPos| {
+            (chunk_x - center_x).abs() <= radius && (chunk_y - center_y).abs() <= radius
+        };
+
+        let far: Vec<ChunkPos> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&position| !in_range(position))
+            .collect();
+        for &position in &far {
+            self.unload(position);
+        }
+
+        let mut loaded = 0;
+        for chunk_y in center_y - radius..=center_y + radius {
+            for chunk_x in center_x - radius..=center_x + radius {
+                if !self.is_loaded((chunk_x, chunk_y)) {
+                    self.load((chunk_x, chunk_y), load((chunk_x, chunk_y)));
+                    loaded += 1;
+                }
+            }
+        }
+
+        (loaded, far.len())
+    }
+}
+
+impl<T> Default for ChunkedGrid<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
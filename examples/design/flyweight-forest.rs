@@ -0,0 +1,133 @@
+//! The book's opening flyweight example: a forest of thousands of trees.
+//!
+//! Each tree has its own position and scale (the _extrinsic_ state), but the mesh and textures (the
+//! _intrinsic_ state) are shared, so only a handful of [`TreeModel`]s exist no matter how many
+//! trees are planted.
+//!
+//! ```bash
+//! cargo run --example design-flyweight-forest
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::Arc,
+};
+
+use rand::Rng;
+
+fn main() {
+    // Load each model once (in a real game, from disk, which is expensive).
+    let models = [
+        Arc::new(TreeModel::new("oak", 4_800)),
+        Arc::new(TreeModel::new("pine", 3_200)),
+        Arc::new(TreeModel::new("birch", 2_400)),
+    ];
+
+    // Plant a forest, where each tree refers to one of the shared models.
+    let mut rng = rand::thread_rng();
+    let forest: Vec<Tree> = (0..10_000)
+        .map(|_| Tree {
+            model: Arc::clone(&models[rng.gen_range(0..models.len())]),
+            position: [rng.gen_range(0.0..1000.0), 0.0, rng.gen_range(0.0..1000.0)],
+            scale: rng.gen_range(0.8..1.2),
+        })
+        .collect();
+
+    // Count how many unique models are actually referenced.
+    let unique: HashSet<*const TreeModel> =
+        forest.iter().map(|tree| Arc::as_ptr(&tree.model)).collect();
+    println!("{} trees share {} models.", forest.len(), unique.len());
+
+    let shared = models.iter().map(|model| model.size()).sum::<usize>()
+        + forest.len() * mem::size_of::<Tree>();
+    let unshared = forest
+        .iter()
+        .map(|tree| tree.model.size() + mem::size_of::<Tree>())
+        .sum::<usize>();
+    println!("Memory used with shared models: {:>10} bytes", shared);
+    println!("Memory used if each tree owned: {:>10} bytes", unshared);
+    println!();
+
+    // Draw the forest, sending each model to the GPU only once.
+    draw_instanced(&forest);
+}
+
+/// Data that is the same for every tree of a kind, and expensive to store more than once.
+pub struct TreeModel {
+    name: String,
+    mesh: Vec<[f32; 3]>,
+    bark: Texture,
+    leaves: Texture,
+}
+
+impl TreeModel {
+    /// Creates a model with the given number of (placeholder) mesh vertices.
+    pub fn new(name: impl Into<String>, vertices: usize) -> Self {
+        TreeModel {
+            name: name.into(),
+            mesh: vec![[0.0; 3]; vertices],
+            bark: Texture::new(64, 64),
+            leaves: Texture::new(128, 128),
+        }
+    }
+
+    /// Returns the (approximate) number of bytes used by the model.
+    pub fn size(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.name.len()
+            + self.mesh.len() * mem::size_of::<[f32; 3]>()
+            + self.bark.pixels.len()
+            + self.leaves.pixels.len()
+    }
+}
+
+/// A stand-in for an image uploaded to the GPU.
+pub struct Texture {
+    pixels: Vec<u8>,
+}
+
+impl Texture {
+    /// Creates a blank RGBA texture of the given size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Texture {
+            pixels: vec![0; width * height * 4],
+        }
+    }
+}
+
+/// Data that is unique to each tree.
+pub struct Tree {
+    model: Arc<TreeModel>,
+    position: [f32; 3],
+    scale: f32,
+}
+
+/// A stand-in for instanced rendering.
+///
+/// The shared model (mesh and textures) is sent once per kind, and only the small per-tree data is
+/// sent for each instance, which is exactly the split the flyweight pattern makes.
+fn draw_instanced(trees: &[Tree]) {
+    let mut batches = HashMap::<*const TreeModel, Vec<&Tree>>::new();
+    for tree in trees {
+        batches
+            .entry(Arc::as_ptr(&tree.model))
+            .or_default()
+            .push(tree);
+    }
+
+    for instances in batches.values() {
+        let model = &instances[0].model;
+        let transforms: Vec<([f32; 3], f32)> = instances
+            .iter()
+            .map(|tree| (tree.position, tree.scale))
+            .collect();
+        println!(
+            "drawInstanced({}: {} vertices, {} instances, {} bytes of instance data)",
+            model.name,
+            model.mesh.len(),
+            transforms.len(),
+            transforms.len() * mem::size_of::<([f32; 3], f32)>()
+        );
+    }
+}
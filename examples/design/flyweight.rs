@@ -5,50 +5,395 @@
 //! ```bash
 //! cargo run --example design-flyweight
 //! ```
+//!
+//! Two variants are shown:
+//!
+//! - A `Grid<&TerrainData>`, where each cell is a reference (pointer) to shared terrain data.
+//! - A `Grid<u8>`, where each cell is an index into a [`Palette`] of terrain data.
+//!
+//! The latter is 1/8th the size on a 64-bit machine, which means more cells fit in a cache line,
+//! at the cost of an extra (palette) lookup per read. Run it with `--release` to compare both, or
+//! see `cargo bench --bench flyweight`.
+//!
+//! The terrain types themselves are loaded from `flyweight.toml`, so the flyweights are data (see
+//! also `design/prototype.rs`), not code.
 
-use std::fmt::Display;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    fmt::Display,
+    hint::black_box,
+    mem,
+    time::{Duration, Instant},
+};
 
 use rand::Rng;
+use serde::{
+    de::{self, DeserializeSeed},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_derive::{Deserialize, Serialize};
 
 fn main() {
+    // Read "flyweight.toml" into a palette of terrain types.
+    let data: TerrainFile = toml::from_str(include_str!("flyweight.toml")).unwrap();
+    let palette = Palette::new(data.terrain);
+
+    // Output what was loaded.
+    println!(
+        "Loaded {} terrain types: {}",
+        palette.terrain.len(),
+        palette
+            .terrain
+            .iter()
+            .map(|terrain| format!(
+                "{} '{}' (cost {}{}{})",
+                terrain.name,
+                terrain.display_as,
+                terrain.movement_cost,
+                if terrain.is_water { ", water" } else { "" },
+                if terrain.is_impassable {
+                    ", impassable"
+                } else {
+                    ""
+                }
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!();
+
     // Example, using references (could be local, static, or reference counted).
-    let mut grid = Grid::<&TerrainData>::new(14, 6);
     let mut rng = rand::thread_rng();
+    let grid = generate(&palette, 48, 16, &mut rng);
+
+    // Print the grid.
+    for row in grid.rows() {
+        for terrain in row {
+            print!("{}", terrain);
+        }
+        println!();
+    }
+    println!();
+
+    // Some simple spatial queries, without any index math.
+    let shore = grid
+        .enumerate()
+        .filter(|&((x, y), terrain)| {
+            !terrain.is_water && grid.neighbors4(x, y).any(|(_, other)| other.is_water)
+        })
+        .count();
+    let peaks = grid
+        .enumerate()
+        .filter(|&((x, y), terrain)| {
+            terrain.name == "hill" && grid.neighbors8(x, y).all(|(_, other)| other.name == "hill")
+        })
+        .count();
+    println!("{shore} tiles are on the shore, and {peaks} hills are surrounded by other hills.");
+
+    // The river always starts on the top row.
+    let ((source, _), _) = grid
+        .enumerate()
+        .find(|(_, terrain)| terrain.name == "river")
+        .unwrap();
+    let nearby = Rect::new(source.saturating_sub(3), 0, 7, 3);
+    let water = grid
+        .window(nearby)
+        .filter(|(_, terrain)| terrain.is_water)
+        .count();
+    println!("{water} of the tiles near the river's source are water.");
+    println!();
+
+    // Find the cheapest way across the map, from the left edge to the right edge.
+    let is_passable = |x: usize, y: usize| !grid.get(x, y).is_impassable;
+    let start = (0..grid.height())
+        .map(|y| (0, y))
+        .find(|&(x, y)| is_passable(x, y));
+    let goal = (0..grid.height())
+        .rev()
+        .map(|y| (grid.width() - 1, y))
+        .find(|&(x, y)| is_passable(x, y));
+    match start
+        .zip(goal)
+        .and_then(|(start, goal)| astar(&grid, start, goal))
+    {
+        Some(path) => {
+            let cost: u32 = path[1..]
+                .iter()
+                .map(|&(x, y)| grid.get(x, y).movement_cost as u32)
+                .sum();
+            println!(
+                "Found a path across the map ({} steps, cost {cost}):",
+                path.len()
+            );
+            let path: HashSet<_> = path.into_iter().collect();
+            for ((x, y), terrain) in grid.enumerate() {
+                if path.contains(&(x, y)) {
+                    print!("*");
+                } else {
+                    print!("{}", terrain);
+                }
+                if x == grid.width() - 1 {
+                    println!();
+                }
+            }
+        }
+        None => println!("There is no path across the map."),
+    }
+    println!();
+
+    // Save the map as palette indices, compressed into runs of the same terrain, and load it back.
+    let mut indices = grid.map(|terrain| palette.index_of(&terrain.name).unwrap());
+    let saved = toml::to_string(&indices).unwrap();
+    let loaded = PaletteIndices(&palette)
+        .deserialize(&mut toml::Deserializer::new(&saved))
+        .unwrap();
+    assert!(loaded == indices, "The map should round-trip through TOML");
+    println!(
+        "Saved {} cells as {} runs ({} bytes of TOML).",
+        indices.cells.len(),
+        indices.runs().len(),
+        saved.len()
+    );
+
+    // Drain a lake, like a paint bucket tool would.
+    let (lake, grass) = (
+        palette.index_of("lake").unwrap(),
+        palette.index_of("grass").unwrap(),
+    );
+    let first_lake = indices
+        .enumerate()
+        .find(|&(_, &index)| index == lake)
+        .map(|(position, _)| position);
+    if let Some((x, y)) = first_lake {
+        let drained = indices.flood_fill(x, y, grass);
+        println!("Drained a lake at ({x}, {y}), turning {drained} tiles into grass.");
+    }
+    println!();
+
+    // The same idea, but each cell is a (tiny) index into a palette instead of a (big) pointer.
+    compare_footprint(&palette, 2048, 2048);
+}
 
+/// Generates coherent regions of grass, hills, and lakes, with a river meandering through them.
+fn generate<'a>(
+    palette: &'a Palette,
+    width: usize,
+    height: usize,
+    rng: &mut impl Rng,
+) -> Grid<&'a TerrainData> {
     // Terrain types
-    let (grass, hill, river) = (
-        TerrainData { display_as: '.' },
-        TerrainData { display_as: '^' },
-        TerrainData { display_as: '~' },
+    let (grass, hill, lake, river) = (
+        palette.find("grass").unwrap(),
+        palette.find("hill").unwrap(),
+        palette.find("lake").unwrap(),
+        palette.find("river").unwrap(),
     );
 
-    // Fill the ground with grass.
-    for x in 0..grid.width() {
-        for y in 0..grid.height() {
-            // Sprinkle in some hills.
-            if rng.gen_ratio(1, 10) {
-                grid.set(x, y, &hill);
+    // Treat the noise as elevation: low ground is water, high ground is hills.
+    let noise = ValueNoise::new(16, rng);
+    let mut grid = Grid::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let elevation = noise.fractal(x as f64 / 8.0, y as f64 / 8.0, 3);
+            let terrain = if elevation < 0.35 {
+                lake
+            } else if elevation > 0.62 {
+                hill
             } else {
-                grid.set(x, y, &grass);
+                grass
+            };
+            grid.set(x, y, terrain);
+        }
+    }
+
+    // Clear a meadow in the middle of the map (say, for the player's village).
+    grid.fill_region(Rect::new(width / 2 - 4, height / 2 - 2, 8, 4), grass);
+
+    // Lay a river, drifting left or right every so often so it meanders from top to bottom.
+    let mut x = rng.gen_range(0..width) as isize;
+    let mut drift = 0;
+    for y in 0..height {
+        grid.set(x as usize, y, river);
+        if rng.gen_ratio(1, 3) {
+            drift = rng.gen_range(-1..=1);
+        }
+        x = (x + drift).clamp(0, width as isize - 1);
+    }
+
+    grid
+}
+
+/// Finds the cheapest path from `start` to `goal` (inclusive), or `None` if there isn't one.
+///
+/// Entering a cell costs its terrain's movement cost, and impassable terrain is never entered. All
+/// of that is read from the shared [`TerrainData`], so the pathfinder never needs to know about
+/// specific kinds of terrain.
+fn astar(
+    grid: &Grid<&TerrainData>,
+    start: (usize, usize),
+    goal: (usize, usize),
+) -> Option<Vec<(usize, usize)>> {
+    // Manhattan distance, assuming every step costs the minimum (1), so it never overestimates.
+    let estimate = |(x, y): (usize, usize)| (x.abs_diff(goal.0) + y.abs_diff(goal.1)) as u32;
+
+    let mut cost = Grid::<Option<u32>>::new(grid.width(), grid.height());
+    let mut came_from = Grid::<Option<(usize, usize)>>::new(grid.width(), grid.height());
+    let mut open = BinaryHeap::new();
+
+    cost.set(start.0, start.1, Some(0));
+    open.push(Reverse((estimate(start), start)));
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut step = current;
+            while let Some(previous) = *came_from.get(step.0, step.1) {
+                path.push(previous);
+                step = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let so_far = cost.get(current.0, current.1).unwrap();
+        for (next, terrain) in grid.neighbors4(current.0, current.1) {
+            if terrain.is_impassable {
+                continue;
+            }
+            let through = so_far + terrain.movement_cost as u32;
+            if cost.get(next.0, next.1).is_none_or(|best| through < best) {
+                cost.set(next.0, next.1, Some(through));
+                came_from.set(next.0, next.1, Some(current));
+                open.push(Reverse((through + estimate(next), next)));
             }
         }
     }
 
-    // Lay a river
-    let x = rng.gen_range(0..grid.width());
-    for y in 0..grid.height() {
-        grid.set(x, y, &river);
+    None
+}
+
+/// Smooth random values in the range `0.0..1.0` that can be sampled anywhere on a plane.
+///
+/// Random values are placed on a coarse lattice, and points in between are interpolated, so nearby
+/// points have similar values (unlike calling `rng.gen()` for every cell, which looks like static).
+struct ValueNoise {
+    lattice: Vec<f64>,
+    size: usize,
+}
+
+impl ValueNoise {
+    /// Creates a new (wrapping) lattice of `size * size` random values.
+    fn new(size: usize, rng: &mut impl Rng) -> Self {
+        ValueNoise {
+            lattice: (0..size * size).map(|_| rng.gen()).collect(),
+            size,
+        }
     }
 
-    // Print the grid.
-    for y in 0..grid.height() {
-        for x in 0..grid.width() {
-            print!("{}", grid.get(x, y));
+    /// Samples the noise at the given (non-negative) point, where lattice points are `1.0` apart.
+    fn sample(&self, x: f64, y: f64) -> f64 {
+        fn lerp(a: f64, b: f64, t: f64) -> f64 {
+            a + (b - a) * t
         }
-        println!();
+
+        // Smoothstep, so the interpolation doesn't have visible creases at lattice points.
+        fn fade(t: f64) -> f64 {
+            t * t * (3.0 - 2.0 * t)
+        }
+
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (tx, ty) = (fade(x.fract()), fade(y.fract()));
+        let top = lerp(self.at(x0, y0), self.at(x0 + 1, y0), tx);
+        let bottom = lerp(self.at(x0, y0 + 1), self.at(x0 + 1, y0 + 1), tx);
+        lerp(top, bottom, ty)
+    }
+
+    /// Sums several octaves of noise, each at double the frequency and half the amplitude.
+    ///
+    /// This gives large features (continents) with smaller details (coastlines) layered on top.
+    fn fractal(&self, x: f64, y: f64, octaves: u32) -> f64 {
+        let (mut total, mut max) = (0.0, 0.0);
+        let (mut amplitude, mut frequency) = (1.0, 1.0);
+        for _ in 0..octaves {
+            total += self.sample(x * frequency, y * frequency) * amplitude;
+            max += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        total / max
+    }
+
+    /// Returns the lattice value at the given point, wrapping around the edges.
+    fn at(&self, x: usize, y: usize) -> f64 {
+        self.lattice[(y % self.size) * self.size + x % self.size]
+    }
+}
+
+/// Builds the same terrain as both a reference grid and a palette grid, and compares them.
+fn compare_footprint(palette: &Palette, width: usize, height: usize) {
+    let hill = palette.index_of("hill").unwrap();
+
+    let mut rng = rand::thread_rng();
+    let mut indices = Grid::<u8>::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            if rng.gen_ratio(1, 10) {
+                indices.set(x, y, hill);
+            }
+        }
+    }
+
+    // Resolve every index to a reference, so both grids describe identical terrain.
+    let references = indices.map(|&index| palette.get(index));
+
+    println!("Comparing a {width}x{height} grid:");
+    println!(
+        "  Grid<&TerrainData>: {:>10} bytes",
+        references.cells.len() * mem::size_of::<&TerrainData>()
+    );
+    println!(
+        "  Grid<u8>:           {:>10} bytes (+ {} bytes of palette)",
+        indices.cells.len() * mem::size_of::<u8>(),
+        palette.terrain.len() * mem::size_of::<TerrainData>()
+    );
+
+    // Walk every cell, reading the shared data, which is what rendering or pathfinding would do.
+    let (cost, by_reference) = measure(|| {
+        references
+            .cells
+            .iter()
+            .map(|terrain| terrain.movement_cost as u64)
+            .sum::<u64>()
+    });
+    let (_, by_index) = measure(|| {
+        indices
+            .cells
+            .iter()
+            .map(|&index| palette.get(index).movement_cost as u64)
+            .sum::<u64>()
+    });
+
+    println!("Summing a total movement cost of {cost} (best of 10 runs):");
+    println!("  Grid<&TerrainData>: {:?}", by_reference);
+    println!("  Grid<u8>:           {:?}", by_index);
+}
+
+/// Runs `f` a few times and returns the result and the fastest run.
+///
+/// This is a rough measurement, not a rigorous benchmark.
+fn measure<R>(mut f: impl FnMut() -> R) -> (R, Duration) {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..10 {
+        let start = Instant::now();
+        result = Some(black_box(f()));
+        best = best.min(start.elapsed());
     }
+    (result.unwrap(), best)
 }
 
+#[derive(PartialEq)]
 struct Grid<T> {
     cells: Vec<T>,
     width: usize,
@@ -91,19 +436,250 @@ impl<T> Grid<T> {
     fn height(&self) -> usize {
         self.cells.len() / self.width
     }
+
+    /// Returns each row of the grid as a slice, from top to bottom.
+    fn rows(&self) -> impl Iterator<Item = &[T]> + '_ {
+        self.cells.chunks(self.width)
+    }
+
+    /// Returns each cell along with its coordinates, in row-major order.
+    fn enumerate(&self) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| ((i % width, i / width), cell))
+    }
+
+    /// Returns the cells within the given region, clipped to the bounds of the grid.
+    fn window(&self, rect: Rect) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        let (right, bottom) = self.clip(rect);
+        (rect.y..bottom).flat_map(move |y| (rect.x..right).map(move |x| ((x, y), self.get(x, y))))
+    }
+
+    /// Sets every cell within the given region, clipped to the bounds of the grid, to a value.
+    fn fill_region(&mut self, rect: Rect, value: T)
+    where
+        T: Clone,
+    {
+        let (right, bottom) = self.clip(rect);
+        for y in rect.y..bottom {
+            let row = y * self.width;
+            self.cells[row + rect.x.min(right)..row + right].fill(value.clone());
+        }
+    }
+
+    /// Returns a new grid of the same size, where each cell is the result of `f` on this grid's.
+    fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            cells: self.cells.iter().map(f).collect(),
+            width: self.width,
+        }
+    }
+
+    /// Replaces the cell at the given coordinates, and every connected (up, down, left, right) cell
+    /// equal to it, with a value, like a paint bucket tool.
+    ///
+    /// Returns the number of cells that were replaced.
+    fn flood_fill(&mut self, x: usize, y: usize, value: T) -> usize
+    where
+        T: Clone + PartialEq,
+    {
+        let target = self.get(x, y).clone();
+        if target == value {
+            return 0;
+        }
+
+        let mut filled = 0;
+        let mut pending = vec![(x, y)];
+        while let Some((x, y)) = pending.pop() {
+            if *self.get(x, y) != target {
+                continue;
+            }
+            self.set(x, y, value.clone());
+            filled += 1;
+            pending.extend(self.neighbors4(x, y).map(|(next, _)| next));
+        }
+        filled
+    }
+
+    /// Returns the (exclusive) right and bottom edges of a region, clipped to the grid.
+    fn clip(&self, rect: Rect) -> (usize, usize) {
+        (
+            (rect.x + rect.width).min(self.width()),
+            (rect.y + rect.height).min(self.height()),
+        )
+    }
+
+    /// Returns the (up to 4) cells directly above, below, left, and right of the given cell.
+    fn neighbors4(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        self.offsets(x, y, &[(0, -1), (-1, 0), (1, 0), (0, 1)])
+    }
+
+    /// Returns the (up to 8) cells surrounding the given cell, including diagonals.
+    fn neighbors8(&self, x: usize, y: usize) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        self.offsets(
+            x,
+            y,
+            &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        )
+    }
+
+    /// Returns the cells at the given offsets from a cell, skipping any outside of the grid.
+    fn offsets(
+        &self,
+        x: usize,
+        y: usize,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = ((usize, usize), &T)> + '_ {
+        offsets.iter().filter_map(move |&(dx, dy)| {
+            let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+            (x < self.width() && y < self.height()).then(|| ((x, y), self.get(x, y)))
+        })
+    }
+}
+
+/// How a `Grid<u8>` is saved: its size, and runs of `[index, count]`, in row-major order.
+///
+/// Terrain comes in large regions of the same type, so this is much smaller than storing each cell.
+#[derive(Serialize, Deserialize)]
+struct RunLengthGrid {
+    width: usize,
+    height: usize,
+    runs: Vec<(u8, u32)>,
+}
+
+/// The most cells a saved map may have, so a corrupt (or malicious) file can't claim more memory
+/// than a real map would need.
+const MAX_SAVED_CELLS: usize = 4096 * 4096;
+
+impl Grid<u8> {
+    /// Returns the cells as runs of `(index, count)`, in row-major order.
+    fn runs(&self) -> Vec<(u8, u32)> {
+        let mut runs: Vec<(u8, u32)> = Vec::new();
+        for &index in &self.cells {
+            match runs.last_mut() {
+                Some((last, count)) if *last == index => *count += 1,
+                _ => runs.push((index, 1)),
+            }
+        }
+        runs
+    }
+}
+
+impl Serialize for Grid<u8> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RunLengthGrid {
+            width: self.width,
+            height: self.height(),
+            runs: self.runs(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Loads a saved `Grid<u8>`, checking that every index is in the palette.
+struct PaletteIndices<'a>(&'a Palette);
+
+impl<'de> DeserializeSeed<'de> for PaletteIndices<'_> {
+    type Value = Grid<u8>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Grid<u8>, D::Error> {
+        let RunLengthGrid {
+            width,
+            height,
+            runs,
+        } = RunLengthGrid::deserialize(deserializer)?;
+        let size = (width.checked_mul(height))
+            .filter(|&size| size > 0 && size <= MAX_SAVED_CELLS)
+            .ok_or_else(|| {
+                de::Error::custom(format!(
+                    "a {width}x{height} map must have between 1 and {MAX_SAVED_CELLS} cells"
+                ))
+            })?;
+
+        // Each run is checked before it's expanded, so the cells never outgrow the map.
+        let mut cells = Vec::with_capacity(size);
+        for (index, count) in runs {
+            if usize::from(index) >= self.0.terrain.len() {
+                return Err(de::Error::custom(format!(
+                    "terrain {index} is not in the palette of {}",
+                    self.0.terrain.len()
+                )));
+            }
+            let total = (cells.len().checked_add(count as usize))
+                .filter(|&total| total <= size)
+                .ok_or_else(|| {
+                    de::Error::custom(format!("runs overflow the {size} cells of the map"))
+                })?;
+            cells.resize(total, index);
+        }
+        if cells.len() != size {
+            return Err(de::Error::custom(format!(
+                "runs fill {} of the {size} cells of the map",
+                cells.len()
+            )));
+        }
+        Ok(Grid { cells, width })
+    }
 }
 
+/// A rectangular region of a grid.
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Rect {
+    /// Creates a new rectangle with the given top-left corner and size.
+    fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// The intrinsic (shared) state of a terrain type.
+#[derive(Deserialize)]
 struct TerrainData {
+    name: String,
     display_as: char,
-}
+    movement_cost: u8,
 
-impl TerrainData {
-    const EMPTY: TerrainData = TerrainData { display_as: ' ' };
+    #[serde(default)]
+    is_water: bool,
+
+    #[serde(default)]
+    is_impassable: bool,
 }
 
+/// Used as the default (unset) cell of a `Grid<&TerrainData>`.
+static EMPTY: TerrainData = TerrainData {
+    name: String::new(),
+    display_as: ' ',
+    movement_cost: 0,
+    is_water: false,
+    is_impassable: false,
+};
+
 impl Default for &TerrainData {
     fn default() -> Self {
-        &TerrainData::EMPTY
+        &EMPTY
     }
 }
 
@@ -112,3 +688,95 @@ impl Display for TerrainData {
         write!(f, "{}", self.display_as)
     }
 }
+
+/// A fixed set of terrain types, referenced by a `u8` index.
+///
+/// A `u8` allows up to 256 distinct terrain types, which is plenty for most tile-based games.
+struct Palette {
+    terrain: Vec<TerrainData>,
+}
+
+impl Palette {
+    /// Creates a new palette from the given terrain types.
+    ///
+    /// # Panics
+    ///
+    /// If there are more terrain types than can be indexed by a `u8`.
+    fn new(terrain: Vec<TerrainData>) -> Self {
+        assert!(terrain.len() <= u8::MAX as usize + 1);
+        Palette { terrain }
+    }
+
+    /// Returns the terrain at the given index.
+    fn get(&self, index: u8) -> &TerrainData {
+        &self.terrain[index as usize]
+    }
+
+    /// Returns the index of the terrain with the given name, if any.
+    fn index_of(&self, name: &str) -> Option<u8> {
+        self.terrain
+            .iter()
+            .position(|terrain| terrain.name == name)
+            .map(|index| index as u8)
+    }
+
+    /// Returns the terrain with the given name, if any.
+    fn find(&self, name: &str) -> Option<&TerrainData> {
+        self.index_of(name).map(|index| self.get(index))
+    }
+}
+
+/// The layout of `flyweight.toml`.
+#[derive(Deserialize)]
+struct TerrainFile {
+    terrain: Vec<TerrainData>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let data: TerrainFile = toml::from_str(include_str!("flyweight.toml")).unwrap();
+        Palette::new(data.terrain)
+    }
+
+    fn load(palette: &Palette, saved: &str) -> Result<Grid<u8>, toml::de::Error> {
+        PaletteIndices(palette).deserialize(&mut toml::Deserializer::new(saved))
+    }
+
+    #[test]
+    fn saved_maps_round_trip() {
+        let palette = palette();
+        let mut grid = Grid::new(4, 3);
+        grid.fill_region(Rect::new(1, 1, 2, 2), 1);
+        let saved = toml::to_string(&grid).unwrap();
+        assert!(load(&palette, &saved).unwrap() == grid);
+    }
+
+    #[test]
+    fn runs_past_the_end_of_the_map_are_rejected() {
+        let saved = "width = 2\nheight = 2\nruns = [[0, 4294967295], [0, 4294967295]]";
+        let error = load(&palette(), saved).err().expect("should be rejected");
+        assert!(error.to_string().contains("overflow"), "{error}");
+    }
+
+    #[test]
+    fn maps_too_big_to_load_are_rejected() {
+        let saved = "width = 65536\nheight = 65536\nruns = [[0, 1]]";
+        assert!(load(&palette(), saved).is_err());
+    }
+
+    #[test]
+    fn indices_outside_the_palette_are_rejected() {
+        let saved = "width = 2\nheight = 1\nruns = [[0, 1], [255, 1]]";
+        let error = load(&palette(), saved).err().expect("should be rejected");
+        assert!(error.to_string().contains("not in the palette"), "{error}");
+    }
+
+    #[test]
+    fn runs_short_of_the_map_are_rejected() {
+        let saved = "width = 2\nheight = 2\nruns = [[0, 3]]";
+        assert!(load(&palette(), saved).is_err());
+    }
+}
@@ -87,7 +87,7 @@ where
     }
 
     pub fn detach(&mut self, observer: Observer<E, S>) {
-        self.observers.retain(|o| *o != observer);
+        self.observers.retain(|o| !std::ptr::fn_addr_eq(*o, observer));
     }
 
     pub fn notify(&self, event: E, source: S) {
@@ -3,83 +3,547 @@
 //! Rust already provides [`Clone`], which combined with structs is enough to express prototypes.
 //!
 //! So instead, this is an example of storing and retrieving data from an external source (in this
-//! case TOML, but it could be anything, JSON, XML if you hate yourself, etc).
+//! case TOML, but it could be anything, JSON, XML if you hate yourself, etc; see [`ConfigFormat`]).
 //!
 //! ```bash
 //! cargo run --example design-prototype
 //! ```
 //!
+//! With the `hot-reload` feature, the example keeps running and re-loads `prototype.toml` whenever
+//! it changes, so stats can be tuned without restarting:
+//!
+//! ```bash
+//! cargo run --example design-prototype --features hot-reload
+//! ```
+//!
+//! Loading is graceful: [`load_monsters`] reports _every_ problem in the data (unknown prototypes,
+//! missing fields, prototypes that refer back to themselves) at once, instead of panicking on the
+//! first one. Prototypes can have prototypes of their own, as long as they don't form a cycle.
+//! Entries are also checked against a small schema (see [`MonsterConfig::validate`]).
+//!
+//! Merging itself isn't specific to monsters: it's the library's `PrototypeRegistry`, which any
+//! config type (items, spells, levels) can use by implementing `Merge`, as [`MonsterConfig`] does.
+//!
 //! Some things I would have done different for a more production system:
 //!
 //! - Deserialize eagerly (if the TOML is malformed find out early in test time)
-//! - Deserialize gracefully (i.e. using try patterns instead of panics)
-//! - Make prototypes able to have prototypes themselves, as long as its not a circular loop
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
     fmt::Display,
+    rc::Rc,
 };
 
-use serde_derive::Deserialize;
+use game_patterns_rs::{
+    prototype::{ChainError, Merge, PrototypeRegistry},
+    symbol::Symbol,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_derive::{Deserialize, Serialize};
 
 fn main() {
     // Read "prototype.toml".
     let data = include_str!("prototype.toml");
 
-    // Parse data as TOML map.
-    let data: HashMap<String, MonsterConfig> = toml::from_str(data).unwrap();
+    // Parse and resolve every monster.
+    let monsters = match load_monsters(data) {
+        Ok(monsters) => monsters,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{error}");
+            }
+            return;
+        }
+    };
 
     // Output what was loaded.
     println!(
-        "Loaded {} monsters with the following keys: {}",
-        data.len(),
-        data.keys().cloned().collect::<Vec<_>>().join(", ")
+        "Loaded {} monsters: {}",
+        monsters.len(),
+        monsters
+            .iter()
+            .map(|monster| monster.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // Print out the monsters.
+    for monster in &monsters {
+        println!("{monster}");
+    }
+
+    // Dump what a chain of prototypes actually produces, which loads back as the same monster.
+    let pyromancer = monsters
+        .iter()
+        .filter(|monster| monster.name.as_str() == "goblin pyromancer")
+        .cloned()
+        .collect::<Vec<_>>();
+    let dumped = dump_monsters(&pyromancer).unwrap();
+    println!("Flattened:\n{dumped}");
+    assert_eq!(load_monsters(&dumped).unwrap(), pyromancer);
+
+    // Each resolved monster is a prototype for a spawner, which stamps out fresh instances.
+    let spawners: Vec<Spawner> = monsters
+        .iter()
+        .map(|monster| Spawner::new(Rc::new(monster.clone())))
+        .collect();
+    for spawner in &spawners {
+        let spawned: Vec<String> = (0..3).map(|_| spawner.spawn().health.to_string()).collect();
+        println!(
+            "Spawned 3 {}s with {} HP",
+            spawner.prototype.name,
+            spawned.join(", ")
+        );
+    }
+
+    // With a seeded generator, the same monsters are spawned every time.
+    let roll = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..5)
+            .map(|_| spawners[0].spawn_with(&mut rng).health)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(roll(42), roll(42));
+    println!(
+        "Spawned 5 {}s (seed 42) with {:?} HP",
+        spawners[0].prototype.name,
+        roll(42)
     );
+    println!();
+
+    // Data with mistakes in it reports all of them, not just the first.
+    let broken = r#"
+        [goblin-shaman]
+        prototype = ["goblin-grunt"]
+
+        [orc]
+        min_health = 40
+
+        [troll]
+        prototype = ["cave-troll"]
+
+        [cave-troll]
+        prototype = ["troll"]
+
+        [imp]
+        name = ""
+        min_health = 10
+        max_health = 5
+        resist = ["brimstone"]
+
+        [imp-lord]
+        prototype = ["imp"]
+        name = "imp lord"
+        min_health = 8
+    "#;
+    println!("Loading broken data:");
+    for error in load_monsters(broken).unwrap_err() {
+        println!("  {error}");
+    }
+
+    #[cfg(feature = "hot-reload")]
+    hot_reload::run(monsters);
+}
+
+#[cfg(feature = "hot-reload")]
+mod hot_reload {
+    //! Watches `prototype.toml` for changes, re-loading it while the "game" keeps running.
+    //!
+    //! This polls the file's modification time, which is simple and portable; a real editor
+    //! integration would use OS notifications (i.e. the `notify` crate) instead.
+
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock},
+        thread,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+
+    /// The current set of monsters, replaced as a whole when the data changes.
+    ///
+    /// Readers clone the inner [`Arc`], so they keep a consistent snapshot for as long as they need
+    /// it, and a reload never exposes a half-built registry.
+    pub type Registry = RwLock<Arc<Vec<Monster>>>;
 
-    // Load each entry as a MonsterConfig.
-    let monsters = data.values().map(|config| {
-        let mut prototypes = vec![config.clone()];
+    /// Watches the data file on a background thread, and prints the monsters every few seconds.
+    pub fn run(monsters: Vec<Monster>) -> ! {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/design/prototype.toml");
+        let registry = Arc::new(RwLock::new(Arc::new(monsters)));
 
-        // Load the prototype data.
-        for prototype in &config.prototype {
-            let prototype = data.get(prototype).unwrap();
-            prototypes.push(prototype.clone());
+        println!();
+        println!(
+            "Watching {} for changes (Ctrl+C to exit)...",
+            path.display()
+        );
+        watch(path, Arc::clone(&registry));
+
+        loop {
+            let snapshot = Arc::clone(&registry.read().unwrap());
+            for monster in snapshot.iter() {
+                println!(
+                    "  {}: {}-{} HP",
+                    monster.name, monster.min_health, monster.max_health
+                );
+            }
+            println!();
+            thread::sleep(Duration::from_secs(3));
         }
+    }
 
-        // Create the finalized monster.
-        Monster::from(prototypes.as_slice())
-    });
+    /// Re-loads `path` into `registry` whenever its modification time changes.
+    ///
+    /// If the new data has errors, they are printed and the previous monsters are kept.
+    fn watch(path: PathBuf, registry: Arc<Registry>) {
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last: Option<SystemTime> = modified(&path);
 
-    // Print out the monsters.
-    for monster in monsters {
-        println!("{monster}");
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(250));
+            let current = modified(&path);
+            if current == last {
+                continue;
+            }
+            last = current;
+
+            let Ok(data) = fs::read_to_string(&path) else {
+                continue;
+            };
+            match load_monsters(&data) {
+                Ok(monsters) => {
+                    println!("Reloaded {} monsters.", monsters.len());
+                    *registry.write().unwrap() = Arc::new(monsters);
+                }
+                Err(errors) => {
+                    println!("Kept the previous monsters; the changes have errors:");
+                    for error in errors {
+                        println!("  {error}");
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Parses the given TOML and resolves each entry (and its prototypes) into a [`Monster`].
+///
+/// See [`load_monsters_with`] for other formats.
+///
+/// # Errors
+///
+/// Every problem found, if any, rather than stopping at the first.
+pub fn load_monsters(data: &str) -> Result<Vec<Monster>, Vec<PrototypeError>> {
+    load_monsters_with(&Toml, data)
+}
+
+/// Parses the given data and resolves each entry (and its prototypes) into a [`Monster`].
+///
+/// Monsters are returned in the order of their keys.
+///
+/// # Errors
+///
+/// Every problem found, if any, rather than stopping at the first.
+pub fn load_monsters_with(
+    format: &impl ConfigFormat,
+    data: &str,
+) -> Result<Vec<Monster>, Vec<PrototypeError>> {
+    let data: BTreeMap<String, MonsterConfig> = format
+        .parse(data)
+        .map_err(|error| vec![PrototypeError::Malformed(error)])?;
+
+    let registry = PrototypeRegistry::new(data);
+    let mut errors = Vec::new();
+
+    for (key, config) in registry.entries() {
+        errors.extend(
+            config
+                .validate()
+                .into_iter()
+                .map(|violation| PrototypeError::Invalid {
+                    key: key.to_string(),
+                    violation,
+                }),
+        );
+    }
+
+    // Monsters that only inherit a problem with their prototypes are skipped, since any missing
+    // fields are likely caused by it.
+    let (resolved, chain_errors) = registry.resolve_all();
+    errors.extend(chain_errors.into_iter().map(PrototypeError::Chain));
+
+    let mut monsters = Vec::new();
+    for (key, merged) in resolved {
+        let config = registry.get(&key).unwrap();
+        match Monster::try_from(merged) {
+            Ok(monster) => {
+                // A range split across prototypes can only be checked once merged; one defined
+                // entirely by this config was already checked above.
+                let checked = config.min_health.is_some() && config.max_health.is_some();
+                let defines = config.min_health.is_some() || config.max_health.is_some();
+                if !checked && defines && monster.min_health > monster.max_health {
+                    errors.push(PrototypeError::Invalid {
+                        key: key.clone(),
+                        violation: Violation::HealthRange {
+                            min_health: monster.min_health,
+                            max_health: monster.max_health,
+                        },
+                    });
+                }
+                monsters.push(monster);
+            }
+            Err(fields) => {
+                errors.extend(
+                    fields
+                        .into_iter()
+                        .map(|field| PrototypeError::MissingField {
+                            key: key.clone(),
+                            field,
+                        }),
+                )
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(monsters)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Writes fully resolved monsters as TOML, keyed by name, without any prototypes.
+///
+/// See [`dump_monsters_with`] for other formats.
+///
+/// # Errors
+///
+/// If the monsters can't be represented in TOML.
+pub fn dump_monsters(monsters: &[Monster]) -> Result<String, Box<dyn Error>> {
+    dump_monsters_with(&Toml, monsters)
+}
+
+/// Writes fully resolved monsters (see [`Monster::to_config`]), keyed by name.
+///
+/// Useful for debugging what a chain of prototypes actually produces; loading the output again
+/// results in the same monsters.
+///
+/// # Errors
+///
+/// If the monsters can't be represented in the format.
+pub fn dump_monsters_with(
+    format: &impl ConfigFormat,
+    monsters: &[Monster],
+) -> Result<String, Box<dyn Error>> {
+    let configs: BTreeMap<&str, MonsterConfig> = monsters
+        .iter()
+        .map(|monster| (monster.name.as_str(), monster.to_config()))
+        .collect();
+    format.write(&configs)
+}
+
+/// A data format that configs can be written in.
+///
+/// Merging prototypes only ever sees [`MonsterConfig`]s, so it doesn't depend on the format; only
+/// [`Toml`] is implemented so far.
+pub trait ConfigFormat {
+    /// Parses the given text as a `T`.
+    fn parse<T: DeserializeOwned>(&self, data: &str) -> Result<T, Box<dyn Error>>;
+
+    /// Writes the given value as text.
+    fn write<T: Serialize>(&self, value: &T) -> Result<String, Box<dyn Error>>;
+}
+
+/// [TOML](https://toml.io), the format of `prototype.toml`.
+pub struct Toml;
+
+impl ConfigFormat for Toml {
+    fn parse<T: DeserializeOwned>(&self, data: &str) -> Result<T, Box<dyn Error>> {
+        Ok(toml::from_str(data)?)
+    }
+
+    fn write<T: Serialize>(&self, value: &T) -> Result<String, Box<dyn Error>> {
+        Ok(toml::to_string(value)?)
+    }
+}
+
+/// A problem found while loading monsters.
+#[derive(Debug)]
+pub enum PrototypeError {
+    /// The data can't be parsed, or doesn't match the shape of a [`MonsterConfig`].
+    Malformed(Box<dyn Error>),
+
+    /// A monster refers to a prototype that doesn't exist, or prototypes form a cycle.
+    Chain(ChainError),
+
+    /// A monster, even after merging its prototypes, is missing a required field.
+    MissingField { key: String, field: &'static str },
+
+    /// A monster breaks one of the rules in [`MonsterConfig::validate`].
+    Invalid { key: String, violation: Violation },
+}
+
+impl Display for PrototypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrototypeError::Malformed(error) => write!(f, "Malformed data: {error}"),
+            PrototypeError::Chain(error) => write!(f, "{error}"),
+            PrototypeError::MissingField { key, field } => {
+                write!(f, "[{key}] is missing required field \"{field}\"")
+            }
+            PrototypeError::Invalid { key, violation } => write!(f, "[{key}] {violation}"),
+        }
+    }
+}
+
+/// The elements that monsters can resist, or be weak to.
+pub const ELEMENTS: &[&str] = &[
+    "cold", "fire", "light", "magic", "physical", "poison", "shadow", "water",
+];
+
+/// A rule that a [`MonsterConfig`] breaks.
+#[derive(Debug)]
+pub enum Violation {
+    /// The name is empty (or only whitespace).
+    EmptyName,
+
+    /// The minimum health is greater than the maximum health.
+    HealthRange { min_health: u8, max_health: u8 },
+
+    /// A `resist` or `weakness` entry is not one of the [`ELEMENTS`].
+    UnknownElement {
+        field: &'static str,
+        element: String,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::EmptyName => write!(f, "has an empty name"),
+            Violation::HealthRange {
+                min_health,
+                max_health,
+            } => write!(
+                f,
+                "has min_health ({min_health}) greater than max_health ({max_health})"
+            ),
+            Violation::UnknownElement { field, element } => {
+                write!(f, "has unknown element \"{element}\" in {field}")
+            }
+        }
     }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// A monster as written in data, where any field may be left to its prototypes.
+///
+/// Lists (`resist`, `weakness`) add to the prototypes' lists by default. An entry prefixed with `-`
+/// (`"-fire"`) removes it instead, and `resist_replace = true` discards the prototypes' list.
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
 pub struct MonsterConfig {
     pub name: Option<String>,
     pub min_health: Option<u8>,
     pub max_health: Option<u8>,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub prototype: Vec<String>,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub resist: Vec<String>,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub resist_replace: bool,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub weakness: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub weakness_replace: bool,
 }
 
-#[derive(Debug)]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl MonsterConfig {
+    /// Checks the fields this config defines, returning every rule it breaks.
+    ///
+    /// Fields left to prototypes aren't checked here; see [`load_monsters_with`].
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        if self
+            .name
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            violations.push(Violation::EmptyName);
+        }
+
+        if let (Some(min_health), Some(max_health)) = (self.min_health, self.max_health) {
+            if min_health > max_health {
+                violations.push(Violation::HealthRange {
+                    min_health,
+                    max_health,
+                });
+            }
+        }
+
+        for (field, list) in [("resist", &self.resist), ("weakness", &self.weakness)] {
+            for entry in list {
+                let element = entry.strip_prefix('-').unwrap_or(entry);
+                if !ELEMENTS.contains(&element) {
+                    violations.push(Violation::UnknownElement {
+                        field,
+                        element: element.to_string(),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Merges `list` into `into`, appending entries, removing `-` prefixed ones, or replacing it.
+fn merge_list(into: &mut Vec<String>, list: &[String], replace: bool) {
+    if replace {
+        into.clear();
+    }
+    for entry in list {
+        match entry.strip_prefix('-') {
+            Some(removed) => into.retain(|existing| existing != removed),
+            None => into.push(entry.clone()),
+        }
+    }
+}
+
+/// A fully resolved monster (all prototypes merged), which acts as the prototype for a [`Spawner`].
+#[derive(Clone, Debug, PartialEq)]
 #[allow(dead_code)]
 pub struct Monster {
-    name: String,
+    name: Symbol,
     min_health: u8,
     max_health: u8,
-    resist: HashSet<String>,
-    weakness: HashSet<String>,
+    resist: BTreeSet<String>,
+    weakness: BTreeSet<String>,
+}
+
+impl Monster {
+    /// Returns a config that (with no prototypes) loads as this monster.
+    pub fn to_config(&self) -> MonsterConfig {
+        MonsterConfig {
+            name: Some(self.name.as_str().to_string()),
+            min_health: Some(self.min_health),
+            max_health: Some(self.max_health),
+            resist: self.resist.iter().cloned().collect(),
+            weakness: self.weakness.iter().cloned().collect(),
+            ..MonsterConfig::default()
+        }
+    }
 }
 
 impl Display for Monster {
@@ -89,39 +553,111 @@ impl Display for Monster {
     }
 }
 
-impl From<&[MonsterConfig]> for Monster {
-    fn from(value: &[MonsterConfig]) -> Self {
-        let mut iter = value.iter();
-        let mut build = iter.next().unwrap().clone();
+/// Creates new monsters in the game world, each a copy of a prototype with its own state.
+///
+/// This is the book's twist on the pattern: rather than a spawner class per kind of monster, one
+/// spawner type is configured with a prototype.
+pub struct Spawner {
+    prototype: Rc<Monster>,
+}
 
-        // Iterate over the remaining and override/merge.
-        for merge in iter {
-            if let Some(name) = merge.name.clone() {
-                build.name = Some(name);
-            }
+impl Spawner {
+    /// Creates a spawner for the given prototype.
+    pub fn new(prototype: Rc<Monster>) -> Self {
+        Spawner { prototype }
+    }
 
-            if let Some(min_health) = merge.min_health {
-                build.min_health = Some(min_health);
-            }
+    /// Creates a new monster, with health rolled between the prototype's minimum and maximum.
+    pub fn spawn(&self) -> MonsterInstance {
+        self.spawn_with(&mut rand::thread_rng())
+    }
 
-            if let Some(max_health) = merge.max_health {
-                build.max_health = Some(max_health);
-            }
+    /// Like [`Spawner::spawn`], but rolls with the given random number generator.
+    ///
+    /// A seeded generator (i.e. [`StdRng::seed_from_u64`]) always spawns the same monsters, which
+    /// is what tests and replays want.
+    pub fn spawn_with(&self, rng: &mut impl Rng) -> MonsterInstance {
+        let (min, max) = (self.prototype.min_health, self.prototype.max_health);
+        MonsterInstance {
+            prototype: Rc::clone(&self.prototype),
+            health: rng.gen_range(min.min(max)..=max),
+        }
+    }
+}
+
+/// A monster in the game world, sharing its (unchanging) data with its prototype.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct MonsterInstance {
+    prototype: Rc<Monster>,
+    health: u8,
+}
+
+impl Merge for MonsterConfig {
+    fn prototypes(&self) -> &[String] {
+        &self.prototype
+    }
+
+    /// Overrides (or adds to) this config with `other`.
+    fn merge(&mut self, other: &Self) {
+        if let Some(name) = other.name.clone() {
+            self.name = Some(name);
+        }
 
-            build.resist.extend(merge.resist.clone());
-            build.weakness.extend(merge.weakness.clone());
+        if let Some(min_health) = other.min_health {
+            self.min_health = Some(min_health);
         }
 
-        // Sort the resist and weakness for consistency.
-        build.resist.sort();
-        build.weakness.sort();
+        if let Some(max_health) = other.max_health {
+            self.max_health = Some(max_health);
+        }
+
+        merge_list(&mut self.resist, &other.resist, other.resist_replace);
+        merge_list(&mut self.weakness, &other.weakness, other.weakness_replace);
+    }
+}
+
+impl TryFrom<MonsterConfig> for Monster {
+    /// The names of any required fields that are missing.
+    type Error = Vec<&'static str>;
 
-        Self {
-            name: build.name.expect("Name is required"),
-            min_health: build.min_health.expect("Min health is required"),
-            max_health: build.max_health.expect("Max health is required"),
-            resist: build.resist.into_iter().collect(),
-            weakness: build.weakness.into_iter().collect(),
+    /// Converts a config, with its prototypes already merged (see [`Merge`]).
+    fn try_from(build: MonsterConfig) -> Result<Self, Self::Error> {
+        match (build.name, build.min_health, build.max_health) {
+            (Some(name), Some(min_health), Some(max_health)) => Ok(Self {
+                name: Symbol::intern(&name),
+                min_health,
+                max_health,
+                resist: build.resist.into_iter().collect(),
+                weakness: build.weakness.into_iter().collect(),
+            }),
+            (name, min_health, max_health) => Err([
+                ("name", name.is_none()),
+                ("min_health", min_health.is_none()),
+                ("max_health", max_health.is_none()),
+            ]
+            .into_iter()
+            .filter_map(|(field, missing)| missing.then_some(field))
+            .collect()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolved_monsters_round_trip_through_toml() {
+        let by_name = |mut monsters: Vec<Monster>| {
+            monsters.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+            monsters
+        };
+        let monsters = by_name(load_monsters_with(&Toml, include_str!("prototype.toml")).unwrap());
+        let dumped = dump_monsters_with(&Toml, &monsters).unwrap();
+        assert_eq!(
+            by_name(load_monsters_with(&Toml, &dumped).unwrap()),
+            monsters
+        );
+    }
+}
@@ -101,6 +101,7 @@ mod concurrent_state_machines {
         holster: Option<Box<dyn BunnyState>>,
     }
 
+    #[allow(dead_code)]
     pub trait BunnyState: State {
         fn jump(self: Box<Self>, bunny: &mut BunnyHero) -> Box<dyn State>;
     }
@@ -114,6 +115,7 @@ mod hierarchical_state_machines {
     use super::*;
 
     /// Similar to a state, but for all states that occur on the ground.
+    #[allow(dead_code)]
     pub trait GroundedState: State {}
 }
 
@@ -10,83 +10,99 @@
 //!
 //! One suggested (free) tool is [CacheGrind](http://valgrind.org/docs/manual/cg-manual.html).
 //!
+//! The library's particle systems (see `src/particle.rs`) store the same particles as an array of
+//! structs and as a struct of arrays; only the latter keeps the update loop's data together, and
+//! `cargo bench --bench particles` shows what that is worth. Arrays of plain floats are also what
+//! vector instructions work on; with `--features simd`, the same bench compares the struct of
+//! arrays' update with one written a chunk of floats at a time.
+//!
+//! Splitting those arrays into chunks also spreads the update across cores: with `--features
+//! parallel`, `cargo bench --bench parallel` shows how it scales with particles and threads.
+//!
+//! Data that is rarely read can instead be split off from the data that is read every frame: an
+//! AI's loot (see `src/ai.rs`) is only needed when it dies, and `cargo bench --bench hot-cold`
+//! compares iterating with it inline and behind a pointer.
+//!
+//! The same goes for components: `cargo bench --bench components` compares game objects that each
+//! box their components with a `ComponentManager` (see `src/component.rs`) that keeps each kind of
+//! component in its own array.
+//!
 //! ```bash
 //! cargo run --example optimize-data-locality
 //! ```
 
-fn main() {
-    let mut system = ParticleSystem::new();
-
-    // Activate a bunch of particles.
-    for i in 0..100 {
-        system.activate(i);
-    }
-
-    // Update the system.
-    system.update();
-
-    // Deactivate a bunch of particles.
-    for i in (0..100).rev() {
-        system.deactivate(i);
-    }
-
-    // Update the system.
-    system.update();
-}
-
-#[derive(Clone, Copy)]
-pub struct Particle;
-
-impl Particle {
-    pub fn update(&self) {
-        println!("Updating particle");
-    }
-}
+use game_patterns_rs::{
+    ai::{AIComponent, LootDrop, LootType, SplitAIComponent},
+    particle::{Emitter, Particle, ParticleSystem, SoaParticleSystem},
+};
 
-pub struct ParticleSystem {
-    particles: [Particle; ParticleSystem::MAX_PARTICLES],
-    active_len: usize,
-}
-
-impl ParticleSystem {
-    const MAX_PARTICLES: usize = 100_000;
-
-    pub fn new() -> Self {
-        Self {
-            particles: [Particle; Self::MAX_PARTICLES],
-            active_len: 0,
-        }
-    }
-
-    pub fn update(&self) {
-        for i in 0..self.active_len {
-            self.particles[i].update();
+fn main() {
+    let mut aos = ParticleSystem::new();
+    let mut soa = SoaParticleSystem::new();
+
+    // A fountain: a burst to start, then a steady stream, lasting from half a second to a second.
+    let mut fountain = Emitter::new([0.0, 0.0])
+        .with_burst(100)
+        .with_rate(200.0)
+        .with_velocity([0.0, 10.0], 1.0)
+        .with_lifetime(0.5..1.0);
+
+    for frame in 0..8 {
+        for particle in fountain.emit(0.25) {
+            aos.spawn(particle);
+            soa.spawn(particle);
         }
-    }
-
-    pub fn activate(&mut self, index: usize) {
-        assert!(index >= self.active_len, "Already active!");
-
-        // Swap it with the first inactive particle right after the active ones.
-        self.particles.swap(index, self.active_len);
-        self.active_len += 1;
-    }
-
-    pub fn deactivate(&mut self, index: usize) {
-        assert!(
-            index <= self.active_len,
-            "Cannot deactivate inactive particle {} of {}",
-            index,
-            self.active_len
+        aos.update(0.25);
+        soa.update(0.25);
+        println!(
+            "After {:.2}s: {} particles (AoS), {} particles (SoA)",
+            (frame + 1) as f32 * 0.25,
+            aos.len(),
+            soa.len()
         );
-
-        self.active_len -= 1;
-        self.particles.swap(index, self.active_len);
+        assert_eq!(aos.len(), soa.len());
     }
-}
 
-impl Default for ParticleSystem {
-    fn default() -> Self {
-        Self::new()
+    // Handles still find their particle after others (packed in before it) are deactivated.
+    let handles: Vec<_> = (0..10)
+        .map(|i| {
+            let particle = Particle {
+                lifetime: 1.0,
+                size: i as f32,
+                ..Particle::default()
+            };
+            aos.spawn(particle).unwrap()
+        })
+        .collect();
+    for &handle in &handles[..5] {
+        assert!(aos.despawn(handle));
+    }
+    assert_eq!(aos.get(handles[7]).unwrap().size, 7.0);
+    assert_eq!(aos.get(handles[0]), None);
+    assert!(!aos.despawn(handles[0]));
+    println!(
+        "{} particles left after despawning 5 by handle; the rest are still found by theirs",
+        aos.len()
+    );
+
+    // Splitting off cold data changes how much of each component the update loop pulls in.
+    let loot = LootDrop {
+        drop: LootType::Sword,
+        min_drops: 1,
+        max_drops: 1,
+        chance_of_drop: 0.1,
+    };
+    let mut inline: [_; 3] = std::array::from_fn(|_| AIComponent::new([5.0, 5.0], loot));
+    let mut split: [_; 3] = std::array::from_fn(|_| SplitAIComponent::new([5.0, 5.0], loot));
+    for _ in 0..10 {
+        inline.iter_mut().for_each(|ai| ai.update(1.0));
+        split.iter_mut().for_each(|ai| ai.update(1.0));
     }
+    assert_eq!(inline[0].position, split[0].position);
+    assert_eq!(inline[0].loot(), split[0].loot());
+    println!(
+        "AI components are {} bytes with loot inline, {} with it split off",
+        size_of::<AIComponent>(),
+        size_of::<SplitAIComponent>()
+    );
 }
@@ -0,0 +1,250 @@
+//! Saving only the parts of the world that changed: each chunk of terrain has a dirty flag, set
+//! when one of its tiles is changed and cleared when it is saved.
+//!
+//! The world is saved as one file per chunk, so saving after a player has dug a single hole
+//! rewrites one small file rather than the whole world:
+//!
+//! ```toml
+//! x = 0
+//! y = 1
+//! tiles = ["Grass", "Stone", "Grass", ...]
+//! ```
+//!
+//! ```bash
+//! cargo run --example optimize-dirty-flag-chunks
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+fn main() {
+    let dir = std::env::temp_dir().join("game-patterns-rs-chunks");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    // A freshly generated world is entirely unsaved, so the first save writes every chunk.
+    let mut world = World::generate(4, 4);
+    let written = world.save(&dir).unwrap();
+    println!(
+        "First save: wrote {written} of {} chunks",
+        world.chunks.len()
+    );
+
+    // Nothing changed, so nothing is written.
+    let written = world.save(&dir).unwrap();
+    println!("Saved again without changes: wrote {written} chunks");
+
+    // Digging a trench across two chunks (and "changing" a tile to what it was) dirties two.
+    for x in 14..18 {
+        world.set(x, 20, Tile::Dirt);
+    }
+    world.set(40, 40, world.get(40, 40));
+    let written = world.save(&dir).unwrap();
+    println!("Dug a trench: wrote {written} chunks");
+
+    // Loading reads every chunk back, whenever it was written.
+    let loaded = World::load(&dir).unwrap();
+    println!(
+        "Loaded {} chunks, {} the world",
+        loaded.chunks.len(),
+        if loaded == world {
+            "matching"
+        } else {
+            "not matching"
+        }
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+const CHUNK_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum Tile {
+    #[default]
+    Grass,
+    Dirt,
+    Stone,
+    Water,
+}
+
+/// Chunks of tiles, by their position (in chunks).
+#[derive(Debug, Default)]
+struct World {
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+/// A square of tiles, and whether it has changed since it was last saved (or loaded).
+#[derive(Debug, Serialize, Deserialize)]
+struct Chunk {
+    x: i32,
+    y: i32,
+    tiles: Vec<Tile>,
+
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// Worlds are equal if their tiles are, whether or not they've been saved.
+impl PartialEq for World {
+    fn eq(&self, other: &Self) -> bool {
+        self.chunks.len() == other.chunks.len()
+            && self.chunks.iter().all(|(position, chunk)| {
+                other
+                    .chunks
+                    .get(position)
+                    .is_some_and(|other| other.tiles == chunk.tiles)
+            })
+    }
+}
+
+impl World {
+    /// Returns a world of `width` by `height` chunks, none of them saved yet.
+    fn generate(width: i32, height: i32) -> Self {
+        let mut chunks = HashMap::new();
+        for y in 0..height {
+            for x in 0..width {
+                let tiles = (0..CHUNK_SIZE * CHUNK_SIZE)
+                    .map(|i| match (i * 7 + (x * 3 + y) as usize) % 11 {
+                        0 => Tile::Water,
+                        1 | 2 => Tile::Stone,
+                        _ => Tile::Grass,
+                    })
+                    .collect();
+                let chunk = Chunk {
+                    x,
+                    y,
+                    tiles,
+                    dirty: true,
+                };
+                chunks.insert((x, y), chunk);
+            }
+        }
+        Self { chunks }
+    }
+
+    /// Returns which chunk a tile is in, and where in it.
+    fn locate(x: i32, y: i32) -> ((i32, i32), usize) {
+        let size = CHUNK_SIZE as i32;
+        let chunk = (x.div_euclid(size), y.div_euclid(size));
+        let index = y.rem_euclid(size) as usize * CHUNK_SIZE + x.rem_euclid(size) as usize;
+        (chunk, index)
+    }
+
+    fn get(&self, x: i32, y: i32) -> Tile {
+        let (chunk, index) = Self::locate(x, y);
+        self.chunks[&chunk].tiles[index]
+    }
+
+    /// Changes a tile, flagging its chunk as needing to be saved if the tile is different.
+    fn set(&mut self, x: i32, y: i32, tile: Tile) {
+        let (chunk, index) = Self::locate(x, y);
+        let chunk = self.chunks.get_mut(&chunk).expect("chunk is loaded");
+        if chunk.tiles[index] != tile {
+            chunk.tiles[index] = tile;
+            chunk.dirty = true;
+        }
+    }
+
+    /// Writes every dirty chunk to its own file in `dir`, returning how many were written.
+    fn save(&mut self, dir: &Path) -> io::Result<usize> {
+        let mut written = 0;
+        for chunk in self.chunks.values_mut().filter(|chunk| chunk.dirty) {
+            let toml = toml::to_string(chunk).map_err(io::Error::other)?;
+            fs::write(Self::path(dir, chunk.x, chunk.y), toml)?;
+            chunk.dirty = false;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Reads every chunk saved in `dir`; none of them are dirty, as they match what's on disk.
+    fn load(dir: &Path) -> io::Result<Self> {
+        let mut chunks = HashMap::new();
+        for entry in fs::read_dir(dir)? {
+            let chunk: Chunk =
+                toml::from_str(&fs::read_to_string(entry?.path())?).map_err(io::Error::other)?;
+            chunks.insert((chunk.x, chunk.y), chunk);
+        }
+        Ok(Self { chunks })
+    }
+
+    fn path(dir: &Path, x: i32, y: i32) -> PathBuf {
+        dir.join(format!("chunk_{x}_{y}.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory for one test's saves.
+    fn save_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("game-patterns-rs-chunks-{test}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn saved_files(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<_> = (fs::read_dir(dir).unwrap())
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn first_save_writes_every_chunk() {
+        let dir = save_dir("first");
+        let mut world = World::generate(4, 4);
+        assert_eq!(world.save(&dir).unwrap(), 16);
+        assert_eq!(saved_files(&dir).len(), 16);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unchanged_chunks_are_skipped() {
+        let dir = save_dir("skipped");
+        let mut world = World::generate(4, 4);
+        world.save(&dir).unwrap();
+        assert_eq!(world.save(&dir).unwrap(), 0);
+
+        // With the files gone, only what's saved next shows up.
+        fs::remove_dir_all(&dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        for x in 14..18 {
+            world.set(x, 20, Tile::Dirt);
+        }
+        world.set(40, 40, world.get(40, 40));
+        assert_eq!(world.save(&dir).unwrap(), 2);
+        assert_eq!(
+            saved_files(&dir),
+            [World::path(&dir, 0, 1), World::path(&dir, 1, 1)]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_chunks_round_trip() {
+        let dir = save_dir("round-trip");
+        let mut world = World::generate(4, 4);
+        world.save(&dir).unwrap();
+        for x in 14..18 {
+            world.set(x, 20, Tile::Dirt);
+        }
+        world.save(&dir).unwrap();
+
+        // Chunks saved at different times all load back, and aren't dirty.
+        let mut loaded = World::load(&dir).unwrap();
+        assert!(loaded == world);
+        assert_eq!(loaded.get(15, 20), Tile::Dirt);
+        assert_eq!(loaded.save(&dir).unwrap(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Avoid unnecessary work by deferring it until the result is needed.
+//!
+//! > A set of primary data changes over time. A set of derived data is determined from this using
+//! > some expensive process. A "dirty" flag tracks when the derived data is out of sync with the
+//! > primary data. It is set when the primary data changes. If the flag is set when the derived
+//! > data is needed, then it is reprocessed and the flag is cleared. Otherwise, the previously
+//! > cached derived data is used.
+//!
+//! Here, the primary data is each node's transform relative to its parent (see
+//! `src/transform.rs`), and the derived data is its transform in the world: a pirate in a crow's
+//! nest on a ship, with a parrot on their shoulder, sailing past a few hundred palm trees that
+//! never move.
+//!
+//! ```bash
+//! cargo run --example optimize-dirty-flag
+//! ```
+
+use std::hint::black_box;
+
+use game_patterns_rs::transform::Transform;
+
+fn main() {
+    let mut scene = SceneGraph::new();
+    let ship = scene.add(None, Transform::at(0.0, 0.0), "ship");
+    let nest = scene.add(Some(ship), Transform::at(0.0, 8.0), "crow's nest");
+    let pirate = scene.add(Some(nest), Transform::at(0.5, 1.0), "pirate");
+    let parrot = scene.add(Some(pirate), Transform::at(0.3, 1.5), "parrot");
+    let island = scene.add(None, Transform::at(100.0, 0.0), "island");
+    for i in 0..300 {
+        scene.add(Some(island), Transform::at(i as f32, 0.0), "palm tree");
+    }
+
+    const FRAMES: u32 = 60;
+    let mut drawn = 0;
+    for frame in 0..FRAMES {
+        // The ship sails every frame, and the parrot hops every tenth; nothing else moves.
+        let mut local = scene.local(ship);
+        local.x += 0.5;
+        scene.set_transform(ship, local);
+        if frame % 10 == 0 {
+            let mut local = scene.local(parrot);
+            local.x = -local.x;
+            scene.set_transform(parrot, local);
+        }
+
+        drawn += scene.render();
+    }
+
+    let stats = scene.stats;
+    println!(
+        "Drew {drawn} nodes over {FRAMES} frames; rather than recompute {drawn} world transforms, \
+         recomputed {} and reused {} from the cache",
+        stats.recomputed, stats.reused
+    );
+    assert_eq!(stats.recomputed + stats.reused, drawn);
+    assert!(stats.reused > stats.recomputed * 10);
+
+    // Asking for one node's world transform only works out what it depends on: the ship's move
+    // dirtied the parrot and everything it's on, but not the island.
+    let mut local = scene.local(ship);
+    local.x += 0.5;
+    scene.set_transform(ship, local);
+    let world = scene.world_transform(parrot);
+    println!(
+        "Then the parrot is at ({:.1}, {:.1}), after recomputing {} transforms",
+        world.x,
+        world.y,
+        scene.stats.recomputed - stats.recomputed
+    );
+    assert!((world.x - 31.3).abs() < 1e-3 && (world.y - 10.5).abs() < 1e-3);
+    assert_eq!(scene.stats.recomputed - stats.recomputed, 4);
+}
+
+/// Identifies a node in a [`SceneGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NodeId(usize);
+
+/// Nodes, each with a transform relative to its parent and a cached transform in the world.
+struct SceneGraph {
+    nodes: Vec<Node>,
+    stats: Stats,
+}
+
+struct Node {
+    local: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+
+    /// What this node would draw, if this were a real renderer.
+    #[allow(dead_code)]
+    mesh: &'static str,
+
+    /// Valid unless `dirty`: the node's own transform, combined with every ancestor's.
+    world: Transform,
+    dirty: bool,
+}
+
+/// How often a world transform was needed, and whether it had to be worked out again.
+#[derive(Clone, Copy, Debug, Default)]
+struct Stats {
+    recomputed: u32,
+    reused: u32,
+}
+
+impl SceneGraph {
+    fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    fn add(&mut self, parent: Option<NodeId>, local: Transform, mesh: &'static str) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            local,
+            parent,
+            children: Vec::new(),
+            mesh,
+            world: Transform::default(),
+            dirty: true,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+        id
+    }
+
+    fn local(&self, node: NodeId) -> Transform {
+        self.nodes[node.0].local
+    }
+
+    /// Changes a node's transform, and flags it and its descendants as needing recomputing; only
+    /// setting a flag, however often the node moves before it's next drawn.
+    fn set_transform(&mut self, node: NodeId, local: Transform) {
+        self.nodes[node.0].local = local;
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            let node = &mut self.nodes[node.0];
+            // A dirty node's descendants are already dirty, from when it was flagged.
+            if !node.dirty {
+                node.dirty = true;
+                stack.extend(&node.children);
+            }
+        }
+    }
+
+    /// Returns a node's transform in the world, recomputing it (and its dirty ancestors') first.
+    fn world_transform(&mut self, node: NodeId) -> Transform {
+        if self.nodes[node.0].dirty {
+            self.recompute(node)
+        } else {
+            self.stats.reused += 1;
+            self.nodes[node.0].world
+        }
+    }
+
+    fn recompute(&mut self, node: NodeId) -> Transform {
+        let parent = match self.nodes[node.0].parent {
+            Some(parent) if self.nodes[parent.0].dirty => self.recompute(parent),
+            Some(parent) => self.nodes[parent.0].world,
+            None => Transform::default(),
+        };
+        let node = &mut self.nodes[node.0];
+        node.world = parent.combine(node.local);
+        node.dirty = false;
+        self.stats.recomputed += 1;
+        node.world
+    }
+
+    /// "Draws" every node, returning how many were drawn.
+    fn render(&mut self) -> u32 {
+        let mut stack: Vec<_> = (0..self.nodes.len())
+            .filter(|&node| self.nodes[node].parent.is_none())
+            .map(NodeId)
+            .collect();
+        let mut drawn = 0;
+        while let Some(node) = stack.pop() {
+            // Parents are drawn before their children, so recomputing never has to look further
+            // up than the parent.
+            let world = self.world_transform(node);
+            black_box(world);
+            drawn += 1;
+            stack.extend(&self.nodes[node.0].children);
+        }
+        drawn
+    }
+}
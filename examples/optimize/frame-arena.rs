@@ -0,0 +1,134 @@
+//! Per-frame data (render commands, collision pairs) kept in a `FrameArena` (see `src/arena.rs`),
+//! which is reset each frame rather than reallocated; a cousin of the object pool.
+//!
+//! Every allocation the program makes is counted, so this shows the difference directly: building
+//! the same data into fresh `Vec`s allocates every frame, forever, while the arenas stop
+//! allocating as soon as they've seen a busy enough frame.
+//!
+//! ```bash
+//! cargo run --example optimize-frame-arena
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use game_patterns_rs::arena::FrameArena;
+
+/// Counts every allocation (and reallocation) made through it, then defers to the system's.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+// SAFETY: Every method defers to `System`, with the arguments it was given.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: The caller upholds `GlobalAlloc::alloc`'s contract.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: The caller upholds `GlobalAlloc::dealloc`'s contract.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: The caller upholds `GlobalAlloc::realloc`'s contract.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns how many allocations `f` made.
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+struct DrawSprite {
+    sprite: u32,
+    position: [f32; 2],
+}
+
+fn main() {
+    // A few hundred units, scattered across the map.
+    let units: Vec<[f32; 2]> = (0..300)
+        .map(|i| [(i * 37 % 200) as f32, (i * 91 % 200) as f32])
+        .collect();
+
+    let mut commands = FrameArena::new();
+    let mut pairs = FrameArena::new();
+
+    println!("{:<8} {:>12} {:>12}", "frame", "fresh Vecs", "arenas");
+    for frame in 0..10 {
+        // How many units are on screen changes from frame to frame.
+        let visible = &units[..200 + (frame * 47) % 100];
+
+        let fresh = count_allocations(|| {
+            let mut commands = Vec::new();
+            let mut pairs = Vec::new();
+            build_frame(
+                visible,
+                |command| commands.push(command),
+                |pair| pairs.push(pair),
+            );
+            std::hint::black_box((commands, pairs));
+        });
+
+        let reused = count_allocations(|| {
+            commands.reset();
+            pairs.reset();
+            build_frame(
+                visible,
+                |command| {
+                    commands.alloc(command);
+                },
+                |pair| {
+                    pairs.alloc(pair);
+                },
+            );
+            std::hint::black_box((commands.iter_mut().count(), pairs.iter_mut().count()));
+        });
+
+        println!("{frame:<8} {fresh:>12} {reused:>12}");
+        if frame >= 5 {
+            assert!(fresh > 0);
+            assert_eq!(reused, 0);
+        }
+    }
+
+    let stats = commands.stats();
+    println!(
+        "The command arena grew {} times in {} frames, to hold up to {} commands",
+        stats.grown, stats.frames, stats.high_water
+    );
+}
+
+/// Draws every unit, and finds the pairs of units close enough to collide.
+fn build_frame(
+    units: &[[f32; 2]],
+    mut draw: impl FnMut(DrawSprite),
+    mut collide: impl FnMut((usize, usize)),
+) {
+    for (i, &position) in units.iter().enumerate() {
+        draw(DrawSprite {
+            sprite: i as u32 % 4,
+            position,
+        });
+    }
+    for (i, a) in units.iter().enumerate() {
+        for (j, b) in units.iter().enumerate().skip(i + 1) {
+            if (a[0] - b[0]).abs() < 10.0 && (a[1] - b[1]).abs() < 10.0 {
+                collide((i, j));
+            }
+        }
+    }
+}
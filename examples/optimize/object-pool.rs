@@ -0,0 +1,154 @@
+//! Improve performance and memory use by reusing objects from a fixed pool instead of allocating
+//! and freeing them individually.
+//!
+//! > Use this pattern when:
+//! >
+//! > - You need to frequently create and destroy objects.
+//! > - Objects are similar in size.
+//! > - Allocating objects on the heap is slow or could lead to memory fragmentation.
+//! > - Each object encapsulates a resource such as a database or network connection that is
+//! >   expensive to acquire and could be reused.
+//!
+//! The pool itself is `game_patterns_rs::pool::Pool`; this example uses it for particles, which
+//! come and go every frame, and for playing sounds, whose sample buffers are worth keeping.
+//!
+//! ```bash
+//! cargo run --example optimize-object-pool
+//! ```
+
+use game_patterns_rs::{
+    particle::Particle,
+    pool::{Exhaustion, Pool},
+};
+
+fn main() {
+    particles();
+    println!();
+    sounds();
+}
+
+/// A fountain spawns a few particles every frame, sometimes more than the pool holds; what happens
+/// then depends on the pool's [`Exhaustion`], and its stats show how big it should have been.
+fn particles() {
+    for exhaustion in [
+        Exhaustion::Error,
+        Exhaustion::EvictOldest,
+        Exhaustion::Grow(8),
+    ] {
+        let mut particles: Pool<Particle> = Pool::new(32).with_exhaustion(exhaustion);
+        for frame in 0..20 {
+            for i in 0..4 {
+                // Failing to create a particle isn't worth handling; nobody misses one spark.
+                let _ = particles.create(|particle| {
+                    *particle = Particle {
+                        velocity: [i as f32 - 1.5, 5.0],
+                        lifetime: if frame < 10 { 0.5 } else { 0.2 },
+                        ..Particle::default()
+                    };
+                });
+            }
+            particles.retain(|particle| {
+                particle.position[0] += particle.velocity[0] * 0.05;
+                particle.position[1] += particle.velocity[1] * 0.05;
+                particle.lifetime -= 0.05;
+                particle.lifetime > 0.0
+            });
+        }
+
+        let stats = particles.stats();
+        println!(
+            "{exhaustion:?}: at most {} of {} particles in use; {} refused, {} evicted, {} grown",
+            stats.high_water,
+            particles.capacity(),
+            stats.refused,
+            stats.evicted,
+            stats.grown
+        );
+        match exhaustion {
+            Exhaustion::Error => assert!(stats.refused > 0),
+            Exhaustion::EvictOldest => assert!(stats.evicted > 0),
+            Exhaustion::Grow(_) => assert!(particles.capacity() > 32),
+        }
+    }
+}
+
+/// A playing sound: its samples, and how far through them it is.
+#[derive(Default)]
+struct Voice {
+    name: &'static str,
+    samples: Vec<f32>,
+    cursor: usize,
+}
+
+impl Voice {
+    /// Loads `length` samples of a tone, reusing the buffer left by the previous sound; returns
+    /// whether the buffer had to grow (and so was reallocated).
+    fn play(&mut self, name: &'static str, length: usize) -> bool {
+        let capacity = self.samples.capacity();
+        self.name = name;
+        self.samples.clear();
+        self.samples
+            .extend((0..length).map(|i| (i as f32 * 0.1).sin()));
+        self.cursor = 0;
+        self.samples.capacity() != capacity
+    }
+
+    /// Advances by `samples`, returning whether the sound is still playing.
+    fn advance(&mut self, samples: usize) -> bool {
+        self.cursor += samples;
+        self.cursor < self.samples.len()
+    }
+}
+
+/// Only a few sounds can play at once; the rest are dropped, and every voice's buffer is reused.
+fn sounds() {
+    let mut voices = Pool::from_fn(3, || Voice {
+        samples: Vec::with_capacity(1024),
+        ..Voice::default()
+    });
+    let mut reallocations = 0;
+    let mut playing = Vec::new();
+
+    for (name, length) in [
+        ("jump", 300),
+        ("coin", 200),
+        ("explosion", 900),
+        ("footstep", 100),
+    ] {
+        match voices.create(|voice| reallocations += voice.play(name, length) as usize) {
+            Ok(voice) => {
+                println!("Playing {name} on voice {}", voice.index());
+                playing.push((name, voice));
+            }
+            Err(error) => println!("Dropped {name}: {error}"),
+        }
+    }
+
+    // After a while, the short sounds finish and free up their voices.
+    voices.retain(|voice| {
+        let playing = voice.advance(400);
+        if !playing {
+            println!("Finished {}", voice.name);
+        }
+        playing
+    });
+    let footstep = voices
+        .create(|voice| reallocations += voice.play("footstep", 100) as usize)
+        .expect("a voice was reclaimed");
+    println!("Playing footstep on voice {}", footstep.index());
+
+    // A handle to a finished sound doesn't find the footstep now playing on its voice.
+    for (name, voice) in playing {
+        match voices.get(voice) {
+            Some(_) => println!("{name} is still playing"),
+            None => println!("{name} has finished"),
+        }
+        if voice.index() == footstep.index() {
+            assert!(voices.get(voice).is_none());
+        }
+    }
+
+    // No sound needed a bigger buffer than the voices started with, so none was reallocated.
+    assert_eq!(reallocations, 0);
+    println!("No voice's buffer was reallocated");
+}
@@ -8,10 +8,129 @@
 //! > kind of position and that you are doing enough queries to find objects by location that your
 //! > performance is suffering.
 //!
+//! The book's battlefield: a fixed grid of cells (see `src/grid.rs`), each holding the units
+//! standing in it as a linked list. Units only fight units in their own cell, or a neighboring
+//! one, so the grid makes far fewer comparisons than checking every pair;
+//! `cargo bench --bench spatial` times the difference.
+//!
+//! A fixed grid does badly once units crowd together, though, as a siege puts most of them in a
+//! few cells; the end of the example compares it with a quadtree (see `src/quadtree.rs`), which
+//! divides crowded areas more finely.
+//!
 //! ```bash
-//! cargo run --example spatial-partition
+//! cargo run --example optimize-spatial-partition
 //! ```
 
+use game_patterns_rs::{
+    grid::Grid,
+    quadtree::{QuadTree, Rect},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const CELLS: usize = 10;
+const CELL_SIZE: f32 = 20.0;
+const SIZE: f32 = CELLS as f32 * CELL_SIZE;
+const ATTACK_DISTANCE: f32 = 4.0;
+
 fn main() {
-    todo!()
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut grid = Grid::new(CELLS, CELL_SIZE);
+    for _ in 0..500 {
+        grid.add([rng.gen_range(0.0..SIZE), rng.gen_range(0.0..SIZE)]);
+    }
+
+    println!("{} units on a {CELLS}x{CELLS} grid:", grid.len());
+    for round in 0..3 {
+        let grid_melee = grid_melee(&grid);
+        let naive_melee = naive_melee(&positions(&grid));
+        println!(
+            "Round {round}: {} fights; the grid checked {} pairs, checking every pair takes {}",
+            grid_melee.fights, grid_melee.checks, naive_melee.checks
+        );
+        assert_eq!(grid_melee.fights, naive_melee.fights);
+        assert!(grid_melee.checks * 10 < naive_melee.checks);
+
+        // Everyone shuffles about a bit, some of them into other cells.
+        for (unit, [x, y]) in grid.units().collect::<Vec<_>>() {
+            let x = (x + rng.gen_range(-5.0..5.0)).clamp(0.0, SIZE - 1.0);
+            let y = (y + rng.gen_range(-5.0..5.0)).clamp(0.0, SIZE - 1.0);
+            grid.move_unit(unit, [x, y]);
+        }
+    }
+
+    // A siege: most of the army crowds around the gate, which is all in one cell.
+    let mut siege = Grid::new(CELLS, CELL_SIZE);
+    for unit in 0..500 {
+        let (center, spread) = if unit % 5 == 0 {
+            (SIZE / 2.0, SIZE / 2.0)
+        } else {
+            (50.0, CELL_SIZE / 2.0)
+        };
+        let x = center + rng.gen_range(-spread..spread);
+        let y = center + rng.gen_range(-spread..spread);
+        siege.add([x.min(SIZE - 1.0), y.min(SIZE - 1.0)]);
+    }
+    let grid_melee = grid_melee(&siege);
+    let tree_melee = quadtree_melee(&positions(&siege));
+    println!(
+        "A siege: {} fights; the grid checked {} pairs, a quadtree {}",
+        grid_melee.fights, grid_melee.checks, tree_melee.checks
+    );
+    assert_eq!(grid_melee.fights, tree_melee.fights);
+    assert!(tree_melee.checks * 4 < grid_melee.checks);
+}
+
+/// How many units are within attack range of one another, and how many pairs were checked to
+/// find out.
+#[derive(Clone, Copy, Debug, Default)]
+struct Melee {
+    fights: usize,
+    checks: usize,
+}
+
+fn in_range(a: [f32; 2], b: [f32; 2]) -> bool {
+    let (dx, dy) = (a[0] - b[0], a[1] - b[1]);
+    dx * dx + dy * dy < ATTACK_DISTANCE * ATTACK_DISTANCE
+}
+
+fn positions(grid: &Grid) -> Vec<[f32; 2]> {
+    grid.units().map(|(_, position)| position).collect()
+}
+
+/// Only checks pairs of units in the same or neighboring cells.
+fn grid_melee(grid: &Grid) -> Melee {
+    let mut fights = 0;
+    let checks = grid.handle_melee(ATTACK_DISTANCE, |_, _| fights += 1);
+    Melee { fights, checks }
+}
+
+/// Checks every pair of units; the O(n²) approach the grid replaces.
+fn naive_melee(units: &[[f32; 2]]) -> Melee {
+    let mut melee = Melee::default();
+    for (i, &a) in units.iter().enumerate() {
+        for &b in &units[i + 1..] {
+            melee.checks += 1;
+            melee.fights += in_range(a, b) as usize;
+        }
+    }
+    melee
+}
+
+/// Only checks pairs of units within a square around each other, found with a quadtree.
+fn quadtree_melee(units: &[[f32; 2]]) -> Melee {
+    let mut tree = QuadTree::new(Rect::new(0.0, 0.0, SIZE, SIZE));
+    for (unit, &position) in units.iter().enumerate() {
+        tree.insert(position, unit).unwrap();
+    }
+
+    let mut melee = Melee::default();
+    for (unit, &position) in units.iter().enumerate() {
+        let area = Rect::around(position, ATTACK_DISTANCE);
+        // Each pair is found from both ends, but only checked from one.
+        for (other, _) in tree.query(area).filter(|&(_, &other)| other > unit) {
+            melee.checks += 1;
+            melee.fights += in_range(position, other) as usize;
+        }
+    }
+    melee
 }
@@ -0,0 +1,116 @@
+//! A fixed-timestep game loop (see `src/game_loop.rs`) rendering to the terminal.
+//!
+//! The ball only updates 10 times a second, but is rendered (into a double-buffered `Scene`, see
+//! `src/double_buffer.rs`) at up to 30 frames a second. Each frame interpolates between the last
+//! two updates by the loop's `alpha`, the fraction of the way to the next update, so the ball
+//! still moves smoothly; the status line shows `alpha` for each frame.
+//!
+//! ```bash
+//! cargo run --example sequence-game-loop-render
+//! ```
+
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use game_patterns_rs::{
+    double_buffer::Scene,
+    game_loop::{Game, GameLoop, LoopControl},
+};
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 10;
+
+fn main() {
+    let mut game = Bouncing::new(Duration::from_secs(4));
+    GameLoop::new(Duration::from_millis(100)).run(&mut game);
+}
+
+/// A ball bouncing around the screen.
+struct Bouncing {
+    previous: (f64, f64),
+    position: (f64, f64),
+    velocity: (f64, f64),
+    scene: Scene<char>,
+    started: Instant,
+    duration: Duration,
+    last_render: Option<Instant>,
+}
+
+impl Bouncing {
+    /// The fastest the terminal is redrawn.
+    const FRAME_TIME: Duration = Duration::from_millis(33);
+
+    fn new(duration: Duration) -> Self {
+        Bouncing {
+            previous: (1.0, 1.0),
+            position: (1.0, 1.0),
+            velocity: (2.0, 1.0),
+            scene: Scene::new(WIDTH, HEIGHT),
+            started: Instant::now(),
+            duration,
+            last_render: None,
+        }
+    }
+}
+
+impl Game for Bouncing {
+    fn process_input(&mut self, _: &mut LoopControl) {}
+
+    fn update(&mut self, _: Duration) {
+        // Velocity is per update (not per second), so the ball moves in visible steps.
+        self.previous = self.position;
+        let (mut x, mut y) = (
+            self.position.0 + self.velocity.0,
+            self.position.1 + self.velocity.1,
+        );
+        let (max_x, max_y) = ((WIDTH - 1) as f64, (HEIGHT - 1) as f64);
+        if !(0.0..=max_x).contains(&x) {
+            self.velocity.0 = -self.velocity.0;
+            x = x.clamp(0.0, max_x);
+        }
+        if !(0.0..=max_y).contains(&y) {
+            self.velocity.1 = -self.velocity.1;
+            y = y.clamp(0.0, max_y);
+        }
+        self.position = (x, y);
+    }
+
+    fn render(&mut self, alpha: f64) {
+        if self
+            .last_render
+            .is_some_and(|last| last.elapsed() < Self::FRAME_TIME)
+        {
+            return;
+        }
+        self.last_render = Some(Instant::now());
+
+        // Draw into the back buffer, then show the whole frame at once.
+        let x = self.previous.0 + (self.position.0 - self.previous.0) * alpha;
+        let y = self.previous.1 + (self.position.1 - self.previous.1) * alpha;
+        self.scene.clear();
+        self.scene.draw(x.round() as usize, y.round() as usize, 'O');
+        self.scene.swap();
+
+        // Move the cursor home and clear the screen, then print the frame.
+        let mut frame = String::from("\x1b[H\x1b[2J");
+        frame.push_str(&format!("+{}+\n", "-".repeat(WIDTH)));
+        for row in self.scene.pixels() {
+            let row: String = row
+                .iter()
+                .map(|&pixel| if pixel == '\0' { ' ' } else { pixel })
+                .collect();
+            frame.push_str(&format!("|{row}|\n"));
+        }
+        frame.push_str(&format!("+{}+\n", "-".repeat(WIDTH)));
+        frame.push_str(&format!("alpha = {alpha:.2}\n"));
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(frame.as_bytes()).unwrap();
+        stdout.flush().unwrap();
+    }
+
+    fn is_running(&self) -> bool {
+        self.started.elapsed() < self.duration
+    }
+}
@@ -8,102 +8,249 @@
 //!
 //! It tracks the passage of time to control the rate of gameplay.
 //!
+//! The library's `GameLoop` implements each of the book's loops, from the simplest to a fixed
+//! timestep (see `LoopStrategy`); this runs each of them on a ball that is thrown up and falls
+//! back down.
+//!
 //! ```bash
 //! cargo run --example sequence-game-loop
 //! ```
 
 use std::{
+    sync::mpsc::{self, Receiver},
     thread,
     time::{Duration, Instant},
 };
 
-fn main() {}
+use game_patterns_rs::game_loop::{Game, GameLoop, LoopControl, LoopStrategy};
 
-/// Simple, but the problem with it is you have no control over how fast the game runs.
-#[allow(dead_code)]
-fn simple_game_loop() {
-    fn process_input() {}
-    fn update() {}
-    fn render() {}
+fn main() {
+    let timestep = Duration::from_secs(1) / 60;
+    let mut ball = Ball::new(Duration::from_secs(2));
+    let stats = GameLoop::new(timestep)
+        .on_report(|second| print!("{second}"))
+        .run(&mut ball);
+    println!(
+        "Landed after {} updates and {} renders",
+        ball.updates, ball.renders
+    );
+    print!("Overall: {stats}");
+    println!();
 
-    loop {
-        process_input();
-        update();
-        render();
+    // The same game, with each of the book's loops. Simple runs far too fast; variable updates
+    // make the flight slightly different each time; capped and fixed agree with each other.
+    for strategy in [
+        LoopStrategy::Simple,
+        LoopStrategy::Capped,
+        LoopStrategy::Variable,
+        LoopStrategy::Fixed,
+    ] {
+        let mut ball = Ball::new(Duration::from_secs(2));
+        ball.quiet = true;
+        let start = Instant::now();
+        let stats = GameLoop::new(timestep).strategy(strategy).run(&mut ball);
+        println!(
+            "{:<9} landed after {:>7.2?} ({:.2?} simulated), peaking at {:.3}m; {} updates, {:.0} fps",
+            format!("{strategy:?}:"),
+            start.elapsed(),
+            ball.simulated,
+            ball.peak,
+            ball.updates,
+            stats.average_fps(),
+        );
     }
+    println!();
+
+    // Debugging: pause mid-flight, step through two updates, then carry on.
+    let mut debugger = Debugger {
+        ball: Ball::new(Duration::from_secs(2)),
+        started: Instant::now(),
+        stage: 0,
+    };
+    GameLoop::new(timestep).run(&mut debugger);
+    println!();
+
+    // A turn-based game has nothing to do between moves, so the loop sleeps until the player
+    // (here, a thread sending scripted moves) does something.
+    let (player, moves) = mpsc::channel();
+    thread::spawn(move || {
+        for step in [1, 1, -1, 2] {
+            thread::sleep(Duration::from_millis(100));
+            player.send(step).unwrap();
+        }
+    });
+    let mut crawl = Crawl {
+        moves,
+        pending: None,
+        position: 0,
+        turns: 0,
+        running: true,
+    };
+    let stats = GameLoop::new(timestep)
+        .strategy(LoopStrategy::EventDriven)
+        .run(&mut crawl);
+    println!("{} turns, {} frames rendered", crawl.turns, stats.frames());
+}
+
+/// A turn-based crawl along a corridor, one move per turn.
+struct Crawl {
+    moves: Receiver<i32>,
+    pending: Option<i32>,
+    position: i32,
+    turns: u32,
+    running: bool,
 }
 
-/// Maximum speed of 60FPS.
-#[allow(dead_code)]
-fn timed_game_loop() {
-    const MS_PER_FRAME: u128 = 1000 / 60;
+impl Game for Crawl {
+    fn process_input(&mut self, _: &mut LoopControl) {
+        self.pending = self.moves.try_recv().ok();
+    }
 
-    fn process_input() {}
-    fn update() {}
-    fn render() {}
+    fn wait_for_input(&mut self, _: &mut LoopControl) {
+        // The player leaving (the channel closing) ends the game.
+        self.pending = self.moves.recv().ok();
+        self.running = self.pending.is_some();
+    }
 
-    loop {
-        let start = Instant::now();
-        process_input();
-        update();
-        render();
-
-        // Sleep to ensure the game doesn't run too quickly, i.e. not more than 60 FPS.
-        let elapsed = start.elapsed().as_millis();
-        if elapsed < MS_PER_FRAME {
-            let delta = MS_PER_FRAME - elapsed;
-            thread::sleep(Duration::from_millis(delta as u64));
+    fn update(&mut self, _: Duration) {
+        if let Some(step) = self.pending.take() {
+            self.position += step;
+            self.turns += 1;
         }
     }
+
+    fn render(&mut self, _: f64) {
+        let mut corridor = ['.'; 6];
+        corridor[self.position as usize] = '@';
+        println!(
+            "Turn {}: {}",
+            self.turns,
+            corridor.iter().collect::<String>()
+        );
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
 }
 
-/// Pick a dynamic maximum based on how much time the frame really takes.
-#[allow(dead_code)]
-fn scaled_game_loop() {
-    fn process_input() {}
-    fn update(_elapsed: Duration) {}
-    fn render() {}
+/// A scripted debugging session over a [`Ball`], pausing and stepping it as a developer might.
+struct Debugger {
+    ball: Ball,
+    started: Instant,
+    stage: u32,
+}
 
-    let mut last_time = Instant::now();
+impl Debugger {
+    fn print(&self, action: &str) {
+        println!(
+            "{action} at {:.2?} simulated ({:.2?} real): {:.2}m high",
+            self.ball.simulated,
+            self.started.elapsed(),
+            self.ball.height
+        );
+    }
+}
 
-    loop {
-        let current = Instant::now();
-        let elapsed = current - last_time;
+impl Game for Debugger {
+    fn process_input(&mut self, control: &mut LoopControl) {
+        self.ball.process_input(control);
+        let elapsed = self.started.elapsed();
+        match self.stage {
+            0 if elapsed >= Duration::from_millis(500) => {
+                control.pause();
+                self.print("Paused");
+            }
+            1 if elapsed >= Duration::from_millis(800) => {
+                self.print("Stepping twice");
+                control.step();
+                control.step();
+            }
+            2 if elapsed >= Duration::from_millis(1000) => {
+                self.print("Resumed");
+                control.resume();
+            }
+            _ => return,
+        }
+        self.stage += 1;
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.ball.update(dt);
+    }
 
-        process_input();
-        update(elapsed);
-        render();
+    fn render(&mut self, alpha: f64) {
+        self.ball.render(alpha);
+    }
 
-        last_time = current;
+    fn is_running(&self) -> bool {
+        self.ball.is_running()
     }
 }
 
-/// Update is always done at 60FPS, but reduce rendering as-needed.
-#[allow(dead_code)]
-fn fixed_update_scaled_render_game_loop() {
-    const MS_PER_FRAME: u128 = 1000 / 60;
+/// A ball thrown straight up, which the game follows until it lands (or time runs out).
+struct Ball {
+    height: f64,
+    velocity: f64,
+    previous_height: f64,
+    peak: f64,
+    simulated: Duration,
+    duration: Duration,
+    updates: u32,
+    renders: u32,
+    next_print: Duration,
+    quiet: bool,
+}
 
-    fn process_input() {}
-    fn update() {}
-    fn render(_next_frame: f64) {}
+impl Ball {
+    const GRAVITY: f64 = -9.8;
 
-    let mut previous = Instant::now();
-    let mut lag = 0.0;
+    fn new(duration: Duration) -> Self {
+        Ball {
+            height: 0.0,
+            velocity: 9.0,
+            previous_height: 0.0,
+            peak: 0.0,
+            simulated: Duration::ZERO,
+            duration,
+            updates: 0,
+            renders: 0,
+            next_print: Duration::ZERO,
+            quiet: false,
+        }
+    }
+}
 
-    loop {
-        let current = Instant::now();
-        let elapsed = current - previous;
+impl Game for Ball {
+    fn process_input(&mut self, _: &mut LoopControl) {}
 
-        previous = current;
-        lag += elapsed.as_millis() as f64;
+    fn update(&mut self, dt: Duration) {
+        self.previous_height = self.height;
+        self.velocity += Self::GRAVITY * dt.as_secs_f64();
+        self.height = (self.height + self.velocity * dt.as_secs_f64()).max(0.0);
+        self.peak = self.peak.max(self.height);
+        self.simulated += dt;
+        self.updates += 1;
+    }
 
-        process_input();
+    fn render(&mut self, alpha: f64) {
+        self.renders += 1;
 
-        while lag >= MS_PER_FRAME as f64 {
-            update();
-            lag -= MS_PER_FRAME as f64;
+        // Printing every frame would flood the terminal; print a few times a second instead.
+        if self.quiet || self.simulated < self.next_print {
+            return;
         }
+        self.next_print += Duration::from_millis(250);
+        let height = self.previous_height + (self.height - self.previous_height) * alpha;
+        println!(
+            "{:>5.2}s {:<30} {height:.2}m",
+            self.simulated.as_secs_f64(),
+            "*".repeat((height * 6.0) as usize + 1)
+        );
+    }
 
-        render(lag / MS_PER_FRAME as f64);
+    fn is_running(&self) -> bool {
+        let landed = self.updates > 0 && self.height == 0.0;
+        !landed && self.simulated < self.duration
     }
 }
@@ -0,0 +1,112 @@
+//! Why fixed updates matter: the same patrols, simulated with variable and fixed timesteps.
+//!
+//! Frame times are never exactly equal; here, they're jittered randomly (from a seed), and the
+//! loop's time comes from a `ManualClock` (see `src/game_loop.rs`), so each run is repeatable.
+//! With a variable timestep, where each patrol turns around depends on how the frames happened
+//! to fall, so two runs of the same game end up in different places. With a fixed timestep, they
+//! never differ, which replays, networked lockstep, and tests all depend on.
+//!
+//! ```bash
+//! cargo run --example sequence-update-determinism
+//! ```
+
+use std::time::Duration;
+
+use game_patterns_rs::game_loop::{Game, GameLoop, LoopControl, LoopStrategy, ManualClock};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn main() {
+    for strategy in [LoopStrategy::Variable, LoopStrategy::Fixed] {
+        let runs: Vec<Vec<f64>> = [1, 2, 3]
+            .into_iter()
+            .map(|seed| simulate(strategy, seed))
+            .collect();
+        for (seed, positions) in runs.iter().enumerate() {
+            let positions: Vec<String> = positions.iter().map(|x| format!("{x:7.3}")).collect();
+            println!(
+                "{strategy:?}, jitter seed {}: {}",
+                seed + 1,
+                positions.join(" ")
+            );
+        }
+        let spread = (0..runs[0].len())
+            .map(|i| {
+                let (min, max) = runs.iter().fold((f64::MAX, f64::MIN), |(min, max), run| {
+                    (min.min(run[i]), max.max(run[i]))
+                });
+                max - min
+            })
+            .fold(0.0, f64::max);
+        println!("{strategy:?}: runs differ by up to {spread:.3} units");
+        println!();
+
+        if strategy == LoopStrategy::Fixed {
+            assert!(runs.windows(2).all(|pair| pair[0] == pair[1]));
+        }
+    }
+}
+
+/// Runs 10 seconds of patrols, with frames that take 5-30ms, returning where each patrol ends.
+fn simulate(strategy: LoopStrategy, seed: u64) -> Vec<f64> {
+    let clock = ManualClock::new();
+    let mut game = Patrols {
+        patrols: [37.0, 60.0, 91.0].into_iter().map(Patrol::new).collect(),
+        simulated: Duration::ZERO,
+        clock: clock.clone(),
+        jitter: StdRng::seed_from_u64(seed),
+    };
+    GameLoop::new(Duration::from_secs(1) / 60)
+        .strategy(strategy)
+        .clock(clock)
+        .run(&mut game);
+    game.patrols.iter().map(|patrol| patrol.x).collect()
+}
+
+/// Walks back and forth between 0 and 100.
+struct Patrol {
+    x: f64,
+    speed: f64,
+}
+
+impl Patrol {
+    fn new(speed: f64) -> Self {
+        Patrol { x: 0.0, speed }
+    }
+
+    fn update(&mut self, elapsed: Duration) {
+        // Clamping at either end loses the rest of that frame's movement, which is exactly where
+        // the frame times leak into the result.
+        self.x += self.speed * elapsed.as_secs_f64();
+        if !(0.0..=100.0).contains(&self.x) {
+            self.x = self.x.clamp(0.0, 100.0);
+            self.speed = -self.speed;
+        }
+    }
+}
+
+struct Patrols {
+    patrols: Vec<Patrol>,
+    simulated: Duration,
+    clock: ManualClock,
+    jitter: StdRng,
+}
+
+impl Game for Patrols {
+    fn process_input(&mut self, _: &mut LoopControl) {}
+
+    fn update(&mut self, dt: Duration) {
+        for patrol in &mut self.patrols {
+            patrol.update(dt);
+        }
+        self.simulated += dt;
+    }
+
+    fn render(&mut self, _: f64) {
+        let frame = Duration::from_millis(self.jitter.gen_range(5..=30));
+        self.clock.advance(frame);
+    }
+
+    fn is_running(&self) -> bool {
+        self.simulated < Duration::from_secs(10)
+    }
+}
@@ -0,0 +1,149 @@
+//! Updating on one thread, and rendering on another.
+//!
+//! The simulation thread updates the world at a fixed rate, no matter how long rendering takes,
+//! and after each update publishes a snapshot of it. The main thread renders whichever snapshot
+//! is newest, at its own (slower) rate, and never sees a world that is half-updated.
+//!
+//! Snapshots are double buffered: two of them move back and forth over a pair of channels. The
+//! simulation fills a free one and sends it; the renderer sends back the one it replaces. If
+//! neither is free yet, the renderer is behind, so the simulation skips publishing that update
+//! rather than waiting for it.
+//!
+//! ```bash
+//! cargo run --example sequence-update-threaded
+//! ```
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use game_patterns_rs::double_buffer::Scene;
+
+const WIDTH: usize = 40;
+const TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+fn main() {
+    let (publish, published) = mpsc::channel();
+    let (recycle, free) = mpsc::channel();
+    for _ in 0..2 {
+        recycle.send(Snapshot::default()).unwrap();
+    }
+
+    let simulation = thread::spawn(move || simulate(Duration::from_secs(2), publish, free));
+    let frames = render(Duration::from_millis(100), published, recycle);
+    let (updates, snapshots) = simulation.join().unwrap();
+
+    println!();
+    println!(
+        "Simulated {updates} updates, published {snapshots} snapshots, rendered {frames} frames"
+    );
+    assert_eq!(updates, 120);
+}
+
+/// The state of the world after an update, as sent to the renderer.
+#[derive(Default)]
+struct Snapshot {
+    update: u64,
+    balls: Vec<f64>,
+}
+
+/// A ball rolling back and forth.
+struct Ball {
+    x: f64,
+    speed: f64,
+}
+
+impl Ball {
+    fn update(&mut self, elapsed: Duration) {
+        self.x += self.speed * elapsed.as_secs_f64();
+        let max = (WIDTH - 1) as f64;
+        if !(0.0..=max).contains(&self.x) {
+            self.x = self.x.clamp(0.0, max);
+            self.speed = -self.speed;
+        }
+    }
+}
+
+/// Updates the world every [`TIMESTEP`] for `duration`; returns how many updates and snapshots.
+fn simulate(duration: Duration, publish: Sender<Snapshot>, free: Receiver<Snapshot>) -> (u64, u64) {
+    let mut balls = vec![
+        Ball {
+            x: 0.0,
+            speed: 20.0,
+        },
+        Ball {
+            x: 10.0,
+            speed: -35.0,
+        },
+        Ball {
+            x: 30.0,
+            speed: 50.0,
+        },
+    ];
+    let updates = (duration.as_secs_f64() / TIMESTEP.as_secs_f64()).round() as u64;
+    let mut published = 0;
+    let start = Instant::now();
+    for update in 1..=updates {
+        for ball in &mut balls {
+            ball.update(TIMESTEP);
+        }
+
+        if let Ok(mut snapshot) = free.try_recv() {
+            snapshot.update = update;
+            snapshot.balls.clear();
+            snapshot.balls.extend(balls.iter().map(|ball| ball.x));
+            if publish.send(snapshot).is_err() {
+                break;
+            }
+            published += 1;
+        }
+
+        // Sleeps until the next update is due; the simulation never waits on the renderer.
+        let next = start + TIMESTEP * update as u32;
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+    (updates, published)
+}
+
+/// Draws the newest snapshot every `interval`, until the simulation stops; returns the frames.
+fn render(interval: Duration, published: Receiver<Snapshot>, recycle: Sender<Snapshot>) -> u64 {
+    let mut scene = Scene::<char>::new(WIDTH, 1);
+    let mut latest: Option<Snapshot> = None;
+    let mut frames = 0;
+    loop {
+        thread::sleep(interval);
+
+        // Takes the newest snapshot, and hands the one it replaces back to the simulation.
+        let running = loop {
+            match published.try_recv() {
+                Ok(snapshot) => {
+                    if let Some(previous) = latest.replace(snapshot) {
+                        let _ = recycle.send(previous);
+                    }
+                }
+                Err(TryRecvError::Empty) => break true,
+                Err(TryRecvError::Disconnected) => break false,
+            }
+        };
+
+        if let Some(snapshot) = &latest {
+            scene.clear();
+            for x in &snapshot.balls {
+                scene.draw(x.round() as usize, 0, 'o');
+            }
+            scene.swap();
+            frames += 1;
+
+            let row: String = scene.pixels()[0]
+                .iter()
+                .map(|&pixel| if pixel == '\0' { '.' } else { pixel })
+                .collect();
+            println!("update {:3}: {row}", snapshot.update);
+        }
+        if !running {
+            return frames;
+        }
+    }
+}
@@ -8,78 +8,701 @@
 //! - Each object’s behavior is mostly independent of the others.
 //! - The objects need to be simulated over time.
 //!
+//! An [`EntityManager`] owns the entities and updates each in turn. Entities often spawn or
+//! destroy others while updating (a wizard casting a fireball, the fireball burning out), so those
+//! changes are deferred until every entity has been updated, rather than changing the list while
+//! it is being iterated over.
+//!
+//! Insertion order is a fragile way to say "input before physics before the camera", so each
+//! entity also picks a [`Phase`], and a priority within it; the manager keeps entities sorted by
+//! both, and only falls back to spawn order for ties.
+//!
+//! In a large world, most entities are far from the player, and updating them is wasted work;
+//! [`EntityManager::update_near`] buckets entities into a grid of cells (a simple spatial
+//! partition), and only updates those in the cells around one entity, leaving the rest dormant.
+//!
+//! Some entities never need updating at all, such as a statue that only fires lightning when it is
+//! touched. They opt out with [`Entity::wants_update`], and react to [`Event`]s sent to them
+//! instead, so the same manager holds both kinds.
+//!
+//! With thousands of low-priority agents, updating all of them every frame may not fit at all;
+//! [`EntityManager::update_sliced`] updates as many as fit in a time budget, and picks up where it
+//! left off next frame, passing each entity all of the time since it was last updated.
+//!
 //! ```bash
 //! cargo run --example sequence-update
 //! ```
 
-use std::time::Duration;
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+    hint::black_box,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 fn main() {
     let mut skeleton = Skeleton {
         patrol_left: false,
-        x: 0,
-        y: 0,
+        x: 0.0,
+        y: 0.0,
     };
 
-    skeleton.update(Duration::from_millis(500));
-    println!("The skeleton's x-coordinate after 500ms: {}", skeleton.x());
+    let mut commands = Commands::new(EntityId(0));
+    for _ in 0..3 {
+        skeleton.update(Duration::from_millis(500), &mut commands);
+        println!("The skeleton's x-coordinate after 500ms: {}", skeleton.x());
+    }
+
+    // At a high frame rate, each frame moves the skeleton less than a unit; it still gets there.
+    for _ in 0..250 {
+        skeleton.update(Duration::from_millis(1), &mut commands);
+    }
+    println!(
+        "The skeleton's x-coordinate after 250 frames of 1ms: {}",
+        skeleton.x()
+    );
+
+    // A wizard who casts fireballs, which burn out after a while.
+    let mut manager = EntityManager::default();
+    manager.spawn(Skeleton {
+        patrol_left: false,
+        x: 0.0,
+        y: 0.0,
+    });
+    manager.spawn(Wizard {
+        cooldown: Duration::ZERO,
+        x: 50.0,
+        y: 0.0,
+    });
+    for frame in 0..6 {
+        manager.update(Duration::from_millis(250));
+        let positions: Vec<i64> = manager.entities().map(|entity| entity.x()).collect();
+        println!(
+            "Frame {frame}: {} entities, at x = {positions:?}",
+            manager.len()
+        );
+    }
+
+    // Spawned in the "wrong" order; phases and priorities sort that out.
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut manager = EntityManager::default();
+    for (name, phase, priority) in [
+        ("camera", Phase::PostUpdate, 0),
+        ("physics", Phase::Update, 0),
+        ("animation", Phase::Update, -1),
+        ("input", Phase::PreUpdate, 0),
+        ("ai", Phase::Update, 1),
+    ] {
+        manager.spawn(Traced {
+            name,
+            phase,
+            priority,
+            log: Rc::clone(&log),
+        });
+    }
+    manager.update(Duration::from_millis(16));
+    println!("Update order: {}", log.borrow().join(" -> "));
+    assert_eq!(
+        *log.borrow(),
+        ["input", "ai", "physics", "animation", "camera"]
+    );
+
+    // A long road of wizards; only those near the player (walking down it) are awake.
+    let mut manager = EntityManager::default();
+    let player = manager.spawn(Player {
+        speed: 400.0,
+        x: 0.0,
+        y: 0.0,
+    });
+    for i in 0..200 {
+        manager.spawn(Wizard {
+            cooldown: Duration::ZERO,
+            x: f64::from(i) * 50.0,
+            y: 0.0,
+        });
+    }
+    let mut total = UpdateStats::default();
+    for frame in 0..60 {
+        let stats = manager.update_near(Duration::from_millis(100), player, 2);
+        if frame % 15 == 0 {
+            println!(
+                "Frame {frame}: updated {}, skipped {} dormant entities",
+                stats.updated, stats.skipped
+            );
+        }
+        total.updated += stats.updated;
+        total.skipped += stats.skipped;
+    }
+    println!(
+        "In total: updated {}, skipped {} ({:.0}% of the work)",
+        total.updated,
+        total.skipped,
+        100.0 * total.skipped as f64 / (total.updated + total.skipped) as f64
+    );
+
+    // A statue is never updated; a pilgrim walking past touches it, and lightning strikes.
+    let mut manager = EntityManager::default();
+    let statue = manager.spawn(Statue { x: 30.0, y: 0.0 });
+    manager.spawn(Pilgrim {
+        statue,
+        touched: false,
+        x: 0.0,
+        y: 0.0,
+    });
+    for frame in 0..8 {
+        let stats = manager.update_near(Duration::from_millis(100), statue, 1);
+        let entities: Vec<&str> = manager.entities().map(|entity| entity.name()).collect();
+        println!(
+            "Frame {frame}: updated {} of {}: {entities:?}",
+            stats.updated,
+            manager.len()
+        );
+    }
+
+    // Far too many agents to update every frame; each frame updates as many as fit in 1ms.
+    let mut manager = EntityManager::default();
+    for i in 0..2_000 {
+        manager.spawn(Agent {
+            heading: f64::from(i),
+            x: 0.0,
+            y: 0.0,
+        });
+    }
+    let mut updated = 0;
+    for frame in 0..10 {
+        let stats = manager.update_sliced(Duration::from_millis(16), Duration::from_millis(1));
+        updated += stats.updated;
+        println!(
+            "Frame {frame}: updated {} agents in 1ms, {} will wait",
+            stats.updated, stats.skipped
+        );
+    }
+    println!(
+        "Each agent was updated {:.1} times in 10 frames",
+        updated as f64 / manager.len() as f64
+    );
+}
 
-    skeleton.update(Duration::from_millis(500));
-    println!("The skeleton's x-coordinate after 500ms: {}", skeleton.x());
+/// Something that happened to an entity, delivered by [`Entity::on_event`].
+#[derive(Clone, Copy, Debug)]
+pub enum Event {
+    Touched { by: EntityId },
+}
 
-    skeleton.update(Duration::from_millis(500));
-    println!("The skeleton's x-coordinate after 500ms: {}", skeleton.x());
+/// How many entities an update called [`Entity::update`] on, and how many it left dormant.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UpdateStats {
+    pub updated: usize,
+    pub skipped: usize,
 }
 
-trait Entity {
-    fn x(&self) -> u64;
-    fn y(&self) -> u64;
+/// When, during a frame, an entity is updated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Before everything else, i.e. reading input.
+    PreUpdate,
 
-    fn set_x(&mut self, x: u64);
-    fn set_y(&mut self, y: u64);
+    /// Most gameplay, i.e. AI and physics.
+    #[default]
+    Update,
 
-    fn update(&mut self, elapsed: Duration);
+    /// After everything else, i.e. a camera following whatever moved.
+    PostUpdate,
 }
 
-struct Skeleton {
-    patrol_left: bool,
-    x: u64,
-    y: u64,
+/// Identifies an entity in an [`EntityManager`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EntityId(u64);
+
+/// Entities to spawn and despawn once the current update is over; given to [`Entity::update`].
+pub struct Commands {
+    current: EntityId,
+    spawned: Vec<Box<dyn Entity>>,
+    despawned: Vec<EntityId>,
+    sent: Vec<(EntityId, Event)>,
 }
 
-impl Entity for Skeleton {
-    fn x(&self) -> u64 {
-        self.x
+impl Commands {
+    fn new(current: EntityId) -> Self {
+        Commands {
+            current,
+            spawned: Vec::new(),
+            despawned: Vec::new(),
+            sent: Vec::new(),
+        }
+    }
+
+    /// Returns the id of the entity being updated.
+    pub fn id(&self) -> EntityId {
+        self.current
     }
 
-    fn y(&self) -> u64 {
-        self.y
+    /// Adds an entity, which is first updated next frame.
+    pub fn spawn(&mut self, entity: impl Entity + 'static) {
+        self.spawned.push(Box::new(entity));
     }
 
-    fn set_x(&mut self, x: u64) {
-        self.x = x;
+    /// Removes an entity; it isn't updated again, even later this frame.
+    pub fn despawn(&mut self, entity: EntityId) {
+        self.despawned.push(entity);
     }
 
-    fn set_y(&mut self, y: u64) {
-        self.y = y;
+    /// Sends an event to an entity, which receives it once the current update is over.
+    pub fn send(&mut self, entity: EntityId, event: Event) {
+        self.sent.push((entity, event));
     }
+}
+
+/// Owns every entity, and updates them once per frame.
+#[derive(Default)]
+pub struct EntityManager {
+    entities: Vec<(EntityId, Box<dyn Entity>)>,
+    next_id: u64,
 
-    fn update(&mut self, elapsed: Duration) {
-        let mut x = self.x as i64;
+    /// Where [`EntityManager::update_sliced`] resumes, and when each entity was last updated (by
+    /// any of the update methods).
+    cursor: usize,
+    time: Duration,
+    updated_at: HashMap<EntityId, Duration>,
+}
+
+impl EntityManager {
+    pub fn spawn(&mut self, entity: impl Entity + 'static) -> EntityId {
+        self.add(Box::new(entity))
+    }
+
+    fn add(&mut self, entity: Box<dyn Entity>) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+
+        // Insert after every entity that goes first, or ties, so spawn order breaks ties.
+        let key = Self::order(entity.as_ref());
+        let index = self
+            .entities
+            .partition_point(|(_, other)| Self::order(other.as_ref()) <= key);
+        self.entities.insert(index, (id, entity));
+        self.updated_at.insert(id, self.time);
+        id
+    }
+
+    fn order(entity: &dyn Entity) -> (Phase, Reverse<i32>) {
+        (entity.phase(), Reverse(entity.priority()))
+    }
+
+    /// Returns the number of entities.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns whether there are no entities.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = &dyn Entity> {
+        self.entities.iter().map(|(_, entity)| entity.as_ref())
+    }
+
+    /// Updates every entity, by phase, then priority, then spawn order, then applies their commands.
+    pub fn update(&mut self, elapsed: Duration) {
+        self.update_where(elapsed, |_| true);
+    }
+
+    /// Like [`EntityManager::update`], but only updates entities within `radius` cells of `focus`.
+    ///
+    /// Entities elsewhere are dormant: they aren't updated, and don't move, until `focus` is near;
+    /// the time they spend dormant is skipped, not caught up on later.
+    pub fn update_near(&mut self, elapsed: Duration, focus: EntityId, radius: i64) -> UpdateStats {
+        let Some((_, focus)) = self.entities.iter().find(|(id, _)| *id == focus) else {
+            return self.update_where(elapsed, |_| true);
+        };
+        let (x, y) = Grid::cell(focus.position());
+
+        let grid = Grid::new(&self.entities);
+        let mut active = BTreeSet::new();
+        for cell_x in x - radius..=x + radius {
+            for cell_y in y - radius..=y + radius {
+                active.extend(grid.get((cell_x, cell_y)));
+            }
+        }
+        self.update_where(elapsed, |index| active.contains(&index))
+    }
+
+    fn update_where(&mut self, elapsed: Duration, active: impl Fn(usize) -> bool) -> UpdateStats {
+        self.time += elapsed;
+        let mut stats = UpdateStats::default();
+        let mut commands = Commands::new(EntityId(0));
+        for (index, (id, entity)) in self.entities.iter_mut().enumerate() {
+            if commands.despawned.contains(id) {
+                continue;
+            }
+
+            // Even entities left alone are up to date, so `update_sliced` won't catch them up.
+            self.updated_at.insert(*id, self.time);
+            if !entity.wants_update() || !active(index) {
+                stats.skipped += 1;
+                continue;
+            }
+            stats.updated += 1;
+            commands.current = *id;
+            entity.update(elapsed, &mut commands);
+        }
+        self.apply(commands);
+        stats
+    }
+
+    /// Updates as many entities as fit in `budget`, resuming from where the last call stopped.
+    ///
+    /// Each entity is passed the time since it was last updated, which may span several frames.
+    /// Entities are visited in order, but a pass may span frames, so this is best suited to
+    /// low-priority entities whose updates don't depend on each other.
+    pub fn update_sliced(&mut self, elapsed: Duration, budget: Duration) -> UpdateStats {
+        self.time += elapsed;
+        let start = Instant::now();
+        let mut stats = UpdateStats::default();
+        let mut commands = Commands::new(EntityId(0));
+        let len = self.entities.len();
+        let mut visited = 0;
+        while visited < len && start.elapsed() < budget {
+            let index = self.cursor % len;
+            self.cursor = index + 1;
+            visited += 1;
+
+            let (id, entity) = &mut self.entities[index];
+            if commands.despawned.contains(id) || !entity.wants_update() {
+                continue;
+            }
+            stats.updated += 1;
+            let since = self.updated_at.insert(*id, self.time).unwrap_or_default();
+            commands.current = *id;
+            entity.update(self.time - since, &mut commands);
+        }
+        stats.skipped = len - stats.updated;
+        self.apply(commands);
+        stats
+    }
+
+    /// Delivers an event to an entity (if it still exists), then applies its commands.
+    pub fn send(&mut self, target: EntityId, event: Event) {
+        let mut commands = Commands::new(target);
+        if let Some((_, entity)) = self.entities.iter_mut().find(|(id, _)| *id == target) {
+            entity.on_event(event, &mut commands);
+        }
+        self.apply(commands);
+    }
+
+    fn apply(&mut self, commands: Commands) {
+        self.entities
+            .retain(|(id, _)| !commands.despawned.contains(id));
+        for id in &commands.despawned {
+            self.updated_at.remove(id);
+        }
+        for entity in commands.spawned {
+            self.add(entity);
+        }
+        for (target, event) in commands.sent {
+            self.send(target, event);
+        }
+    }
+}
+
+/// Indices of entities, bucketed by which square cell of the world they are in.
+struct Grid {
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    const CELL_SIZE: f64 = 100.0;
+
+    fn new(entities: &[(EntityId, Box<dyn Entity>)]) -> Self {
+        let mut cells = HashMap::<_, Vec<_>>::new();
+        for (index, (_, entity)) in entities.iter().enumerate() {
+            cells
+                .entry(Self::cell(entity.position()))
+                .or_default()
+                .push(index);
+        }
+        Grid { cells }
+    }
+
+    fn cell((x, y): (f64, f64)) -> (i64, i64) {
+        (
+            (x / Self::CELL_SIZE).floor() as i64,
+            (y / Self::CELL_SIZE).floor() as i64,
+        )
+    }
+
+    fn get(&self, cell: (i64, i64)) -> impl Iterator<Item = usize> + '_ {
+        self.cells.get(&cell).into_iter().flatten().copied()
+    }
+}
+
+pub trait Entity {
+    /// Returns exactly where the entity is, so that movement accumulates however small each
+    /// frame's share of it is.
+    fn position(&self) -> (f64, f64);
+
+    /// Returns the x-coordinate, rounded to a whole unit (i.e. a pixel) for drawing.
+    fn x(&self) -> i64 {
+        self.position().0.round() as i64
+    }
+
+    /// Returns the y-coordinate, rounded to a whole unit (i.e. a pixel) for drawing.
+    fn y(&self) -> i64 {
+        self.position().1.round() as i64
+    }
+
+    fn update(&mut self, elapsed: Duration, commands: &mut Commands);
+
+    /// Returns which phase of the frame the entity is updated in; read once, when it's added.
+    fn phase(&self) -> Phase {
+        Phase::default()
+    }
+
+    /// Returns the entity's priority within its phase; higher priorities are updated first.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Returns whether to update the entity every frame; if not, it only reacts to events.
+    fn wants_update(&self) -> bool {
+        true
+    }
+
+    /// Reacts to an event sent to the entity; by default, ignores it.
+    fn on_event(&mut self, _event: Event, _commands: &mut Commands) {}
+
+    /// Returns what to call the entity when printing it.
+    fn name(&self) -> &'static str {
+        "entity"
+    }
+}
+
+/// Records when it was updated, to show the order entities are updated in.
+struct Traced {
+    name: &'static str,
+    phase: Phase,
+    priority: i32,
+    log: Rc<RefCell<Vec<&'static str>>>,
+}
+
+impl Entity for Traced {
+    fn position(&self) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    fn update(&mut self, _: Duration, _: &mut Commands) {
+        self.log.borrow_mut().push(self.name);
+    }
+
+    fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+struct Skeleton {
+    patrol_left: bool,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Skeleton {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, _: &mut Commands) {
         let elapsed = elapsed.as_secs_f64();
         if self.patrol_left {
-            x -= (elapsed * 100.0) as i64;
-            if x <= 0 {
-                x = 0;
+            self.x -= elapsed * 100.0;
+            if self.x <= 0.0 {
+                self.x = 0.0;
                 self.patrol_left = false;
             }
         } else {
-            x += (elapsed * 100.0) as i64;
-            if x >= 100 {
-                x = 100;
+            self.x += elapsed * 100.0;
+            if self.x >= 100.0 {
+                self.x = 100.0;
                 self.patrol_left = true;
             }
         }
-        self.x = x as u64;
+    }
+}
+
+/// Casts a fireball every second.
+struct Wizard {
+    cooldown: Duration,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Wizard {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, commands: &mut Commands) {
+        self.cooldown = self.cooldown.saturating_sub(elapsed);
+        if self.cooldown.is_zero() {
+            commands.spawn(Fireball {
+                remaining: Duration::from_millis(500),
+                x: self.x,
+                y: self.y,
+            });
+            self.cooldown = Duration::from_secs(1);
+        }
+    }
+}
+
+/// Walks right, forever.
+struct Player {
+    speed: f64,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Player {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, _: &mut Commands) {
+        self.x += elapsed.as_secs_f64() * self.speed;
+    }
+
+    fn phase(&self) -> Phase {
+        Phase::PreUpdate
+    }
+}
+
+/// Stands still, and is never updated; when touched, calls down lightning.
+struct Statue {
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Statue {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, _: Duration, _: &mut Commands) {}
+
+    fn wants_update(&self) -> bool {
+        false
+    }
+
+    fn on_event(&mut self, event: Event, commands: &mut Commands) {
+        let Event::Touched { by } = event;
+        println!("The statue was touched by {by:?}; lightning strikes!");
+        commands.spawn(Lightning {
+            remaining: Duration::from_millis(200),
+            x: self.x,
+            y: self.y,
+        });
+    }
+
+    fn name(&self) -> &'static str {
+        "statue"
+    }
+}
+
+/// Walks right, touching the statue on the way past.
+struct Pilgrim {
+    statue: EntityId,
+    touched: bool,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Pilgrim {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, commands: &mut Commands) {
+        self.x += elapsed.as_secs_f64() * 100.0;
+        if self.x >= 30.0 && !self.touched {
+            self.touched = true;
+            commands.send(self.statue, Event::Touched { by: commands.id() });
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "pilgrim"
+    }
+}
+
+/// Strikes for a moment, then is gone.
+struct Lightning {
+    remaining: Duration,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Lightning {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, commands: &mut Commands) {
+        self.remaining = self.remaining.saturating_sub(elapsed);
+        if self.remaining.is_zero() {
+            commands.despawn(commands.id());
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "lightning"
+    }
+}
+
+/// Wanders aimlessly, after thinking about it for a while.
+struct Agent {
+    heading: f64,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Agent {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, _: &mut Commands) {
+        // Stands in for pathfinding, or some other expensive decision.
+        for _ in 0..100 {
+            self.heading = black_box(self.heading.sin() * 1.1 + 0.3);
+        }
+        let distance = elapsed.as_secs_f64() * 10.0;
+        self.x += self.heading.cos() * distance;
+        self.y += self.heading.sin() * distance;
+    }
+}
+
+/// Flies right, then burns out.
+struct Fireball {
+    remaining: Duration,
+    x: f64,
+    y: f64,
+}
+
+impl Entity for Fireball {
+    fn position(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    fn update(&mut self, elapsed: Duration, commands: &mut Commands) {
+        self.x += elapsed.as_secs_f64() * 40.0;
+        self.remaining = self.remaining.saturating_sub(elapsed);
+        if self.remaining.is_zero() {
+            commands.despawn(commands.id());
+        }
     }
 }
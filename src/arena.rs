@@ -0,0 +1,235 @@
+//! Memory for data that only lives for one frame, reused every frame instead of reallocated.
+//!
+//! Render commands, collision pairs, and the like are built up during a frame and thrown away at
+//! its end. Collecting them into a fresh `Vec` each frame allocates (and frees) every frame; a
+//! [`FrameArena`] is [`reset`](FrameArena::reset) instead, which forgets what it holds but keeps
+//! its memory, so once it has grown to fit a busy frame it stops allocating altogether.
+//!
+//! Values are stored in chunks that are never resized or moved, so allocating only needs a shared
+//! reference to the arena, and everything allocated during a frame stays usable together until
+//! the arena is reset:
+//!
+//! ```
+//! use game_patterns_rs::arena::FrameArena;
+//!
+//! let mut pairs = FrameArena::new();
+//! for frame in 0..3 {
+//!     pairs.reset();
+//!     let first = pairs.alloc((1, 2));
+//!     let rest = pairs.alloc_iter([(3, 4), (5, 6)]);
+//!     first.0 += rest[1].0;
+//!     assert_eq!(*first, (6, 2));
+//!     assert_eq!(pairs.len(), 3);
+//! }
+//!
+//! // Only the first frame had to make room.
+//! assert_eq!(pairs.stats().grown, 1);
+//! assert_eq!(pairs.stats().high_water, 3);
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt, slice,
+};
+
+/// The fewest values a chunk makes room for.
+const MIN_CHUNK: usize = 8;
+
+/// A frame's worth of values of type `T`, whose memory is kept from one frame to the next.
+pub struct FrameArena<T> {
+    chunks: RefCell<Chunks<T>>,
+    stats: Cell<ArenaStats>,
+}
+
+/// Where a [`FrameArena`]'s values are.
+struct Chunks<T> {
+    /// The chunk being filled; never pushed past its capacity, so its values never move.
+    current: Vec<T>,
+
+    /// Chunks filled earlier this frame.
+    full: Vec<Vec<T>>,
+
+    /// Values added this frame, across every chunk.
+    len: usize,
+}
+
+/// How a [`FrameArena`] has been used so far; useful for choosing its starting capacity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ArenaStats {
+    /// The most values held in one frame.
+    pub high_water: usize,
+
+    /// Times the arena had to allocate more memory to fit a frame's values.
+    pub grown: u64,
+
+    /// Frames (calls to [`FrameArena::reset`]) so far.
+    pub frames: u64,
+}
+
+impl<T> FrameArena<T> {
+    /// Creates an empty arena, which allocates on first use.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty arena with room for `capacity` values, i.e. a typical frame's worth.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            chunks: RefCell::new(Chunks {
+                current: Vec::with_capacity(capacity),
+                full: Vec::new(),
+                len: 0,
+            }),
+            stats: Cell::new(ArenaStats::default()),
+        }
+    }
+
+    /// Adds a value for this frame, returning it.
+    ///
+    /// The value stays where it is until the arena is reset, so it can be used alongside
+    /// anything else allocated this frame.
+    #[allow(
+        clippy::mut_from_ref,
+        reason = "each value is handed out once, see below"
+    )]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let mut chunks = self.chunks.borrow_mut();
+        if chunks.current.len() == chunks.current.capacity() {
+            self.grow(&mut chunks, 1);
+        }
+        let index = chunks.current.len();
+        chunks.current.push(value);
+        self.note_value(&mut chunks);
+
+        // SAFETY: The value was just pushed within the chunk's capacity, so the chunk didn't
+        // reallocate, and it never will (see `grow`), so the value isn't moved until `reset` or
+        // drop, which need `&mut self`. It's handed out once, and `as_mut_ptr` doesn't create a
+        // reference to the chunk's other values, which may already have been handed out.
+        unsafe { &mut *chunks.current.as_mut_ptr().add(index) }
+    }
+
+    /// Adds several values for this frame, returning them.
+    ///
+    /// # Panics
+    ///
+    /// If `values` allocates from this arena as it's iterated.
+    #[allow(
+        clippy::mut_from_ref,
+        reason = "each value is handed out once, see below"
+    )]
+    pub fn alloc_iter(&self, values: impl IntoIterator<Item = T>) -> &mut [T] {
+        let values = values.into_iter();
+        let mut chunks = self.chunks.borrow_mut();
+        let additional = values.size_hint().0;
+        if chunks.current.capacity() - chunks.current.len() < additional {
+            self.grow(&mut chunks, additional);
+        }
+
+        let mut start = chunks.current.len();
+        for value in values {
+            if chunks.current.len() == chunks.current.capacity() {
+                // Out of room: move the values added so far into a bigger chunk, to keep them
+                // together. None of them have been handed out yet.
+                let added = chunks.current.len() - start;
+                self.grow(&mut chunks, added + 1);
+                if added > 0 {
+                    let Chunks { current, full, .. } = &mut *chunks;
+                    current.extend(full.last_mut().unwrap().drain(start..));
+                }
+                start = 0;
+            }
+            chunks.current.push(value);
+            self.note_value(&mut chunks);
+        }
+
+        let added = chunks.current.len() - start;
+        // SAFETY: As in `alloc`, the values stay where they are until the arena is reset, are
+        // handed out once, and no reference to the chunk's earlier values is created.
+        unsafe { slice::from_raw_parts_mut(chunks.current.as_mut_ptr().add(start), added) }
+    }
+
+    /// Returns the number of values added this frame.
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().len
+    }
+
+    /// Returns whether no values have been added this frame.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the values added this frame, in the order they were added.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let Chunks { current, full, .. } = self.chunks.get_mut();
+        full.iter_mut()
+            .chain(std::iter::once(current))
+            .flat_map(|chunk| chunk.iter_mut())
+    }
+
+    /// Drops every value, keeping the memory they used for the next frame.
+    ///
+    /// If the frame needed more than one chunk, they're replaced with a single chunk as big as
+    /// all of them, so the next frame as busy as this one fits without growing.
+    pub fn reset(&mut self) {
+        let Chunks { current, full, len } = self.chunks.get_mut();
+        let merge = !full.is_empty();
+        if merge {
+            let capacity = current.capacity() + full.iter().map(Vec::capacity).sum::<usize>();
+            full.clear();
+            *current = Vec::with_capacity(capacity);
+        } else {
+            current.clear();
+        }
+        *len = 0;
+        self.update_stats(|stats| {
+            stats.grown += merge as u64;
+            stats.frames += 1;
+        });
+    }
+
+    /// Returns how the arena has been used so far.
+    pub fn stats(&self) -> ArenaStats {
+        self.stats.get()
+    }
+
+    /// Starts a new chunk with room for at least `additional` values, keeping the current one
+    /// (and the values in it) as it is.
+    fn grow(&self, chunks: &mut Chunks<T>, additional: usize) {
+        let capacity = (chunks.current.capacity() * 2)
+            .max(additional)
+            .max(MIN_CHUNK);
+        let full = std::mem::replace(&mut chunks.current, Vec::with_capacity(capacity));
+        if full.capacity() > 0 {
+            chunks.full.push(full);
+        }
+        self.update_stats(|stats| stats.grown += 1);
+    }
+
+    fn note_value(&self, chunks: &mut Chunks<T>) {
+        chunks.len += 1;
+        let len = chunks.len;
+        self.update_stats(|stats| stats.high_water = stats.high_water.max(len));
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut ArenaStats)) {
+        let mut stats = self.stats.get();
+        update(&mut stats);
+        self.stats.set(stats);
+    }
+}
+
+impl<T> Default for FrameArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for FrameArena<T> {
+    // Values may be borrowed mutably through `alloc`, so only the counts are shown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameArena")
+            .field("len", &self.len())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
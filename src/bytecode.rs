@@ -0,0 +1,228 @@
+//! The virtual machine from the bytecode chapter, shared so that data (i.e. a breed) can refer to a
+//! script and have it run.
+//!
+//! Scripts are written as text, one instruction per line, and assembled into bytecode:
+//!
+//! ```
+//! use game_patterns_rs::bytecode::{assemble, VM};
+//!
+//! let bytes = assemble("LITERAL 3\nPLAY_SOUND").unwrap();
+//! VM::new(bytes).run().unwrap();
+//! ```
+//!
+//! Scripts come from data files, so a broken one is reported rather than crashing the game: the
+//! assembler rejects scripts that would take a value from an empty stack, and the VM returns a
+//! [`RunError`] for bytecode that didn't come from the assembler.
+//!
+//! ```
+//! use game_patterns_rs::bytecode::{assemble, RunError, VM};
+//!
+//! let error = assemble("LITERAL 7\nPLAY_SOUND\nSPAWN_PARTICLES").unwrap_err();
+//! assert_eq!(error.line, 3);
+//!
+//! let result = VM::new(vec![VM::INST_PLAY_SOUND]).run();
+//! assert_eq!(result, Err(RunError::StackUnderflow(VM::INST_PLAY_SOUND)));
+//! ```
+
+use std::{collections::VecDeque, error::Error, fmt, vec::IntoIter};
+
+pub struct VM {
+    stack: VecDeque<u64>,
+    bytes: IntoIter<u64>,
+}
+
+impl VM {
+    pub const INST_LITERAL: u64 = 100_000_000;
+    pub const INST_SET_HEALTH: u64 = 100_000_001;
+    pub const INST_SET_WISDOM: u64 = 100_000_002;
+    pub const INST_SET_AGILITY: u64 = 100_000_003;
+    pub const INST_PLAY_SOUND: u64 = 100_000_004;
+    pub const INST_SPAWN_PARTICLES: u64 = 100_000_005;
+
+    /// Every instruction, by the name it is written as in a script.
+    pub const INSTRUCTIONS: [(&'static str, u64); 6] = [
+        ("LITERAL", VM::INST_LITERAL),
+        ("SET_HEALTH", VM::INST_SET_HEALTH),
+        ("SET_WISDOM", VM::INST_SET_WISDOM),
+        ("SET_AGILITY", VM::INST_SET_AGILITY),
+        ("PLAY_SOUND", VM::INST_PLAY_SOUND),
+        ("SPAWN_PARTICLES", VM::INST_SPAWN_PARTICLES),
+    ];
+
+    pub fn new(bytes: Vec<u64>) -> Self {
+        VM {
+            stack: Default::default(),
+            bytes: bytes.into_iter(),
+        }
+    }
+
+    pub fn push(&mut self, value: u64) {
+        self.stack.push_front(value)
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.stack.pop_front()
+    }
+
+    /// Executes every remaining instruction.
+    ///
+    /// # Errors
+    ///
+    /// If an instruction is unknown, or needs more values than are on the stack; the instructions
+    /// before it have already run.
+    pub fn run(&mut self) -> Result<(), RunError> {
+        while let Some(value) = self.bytes.next() {
+            self.execute(value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns how many values an instruction takes from the stack, if it is one.
+    fn pops(instruction: u64) -> Option<usize> {
+        match instruction {
+            VM::INST_LITERAL => Some(0),
+            VM::INST_SET_HEALTH | VM::INST_SET_WISDOM | VM::INST_SET_AGILITY => Some(2),
+            VM::INST_PLAY_SOUND | VM::INST_SPAWN_PARTICLES => Some(1),
+            _ => None,
+        }
+    }
+
+    fn pop_for(&mut self, instruction: u64) -> Result<u64, RunError> {
+        self.pop().ok_or(RunError::StackUnderflow(instruction))
+    }
+
+    fn execute(&mut self, value: u64) -> Result<(), RunError> {
+        match value {
+            VM::INST_LITERAL => {
+                let next = self.bytes.next().ok_or(RunError::MissingLiteral)?;
+                self.push(next);
+            }
+
+            VM::INST_SET_HEALTH | VM::INST_SET_WISDOM | VM::INST_SET_AGILITY => {
+                let (amount, wizard) = (self.pop_for(value)?, self.pop_for(value)?);
+                let name = match value {
+                    VM::INST_SET_HEALTH => "Health",
+                    VM::INST_SET_WISDOM => "Wisdom",
+                    VM::INST_SET_AGILITY => "Agility",
+                    _ => unreachable!(),
+                };
+                println!("set{}({}. {})", name, amount, wizard);
+            }
+
+            VM::INST_PLAY_SOUND => {
+                let sound = self.pop_for(value)?;
+                println!("playSound({})", sound);
+            }
+
+            VM::INST_SPAWN_PARTICLES => {
+                let texture = self.pop_for(value)?;
+                println!("spawnParticles({})", texture);
+            }
+
+            _ => return Err(RunError::UnknownInstruction(value)),
+        }
+        Ok(())
+    }
+}
+
+/// Why the [`VM`] stopped before the end of its bytecode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunError {
+    /// A value that isn't an instruction, where one was expected.
+    UnknownInstruction(u64),
+
+    /// The instruction needed more values than were on the stack.
+    StackUnderflow(u64),
+
+    /// The bytecode ended straight after a `LITERAL`.
+    MissingLiteral,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |instruction| {
+            VM::INSTRUCTIONS
+                .into_iter()
+                .find(|&(_, known)| known == instruction)
+                .map_or("?", |(name, _)| name)
+        };
+        match *self {
+            RunError::UnknownInstruction(value) => write!(f, "unknown instruction {value}"),
+            RunError::StackUnderflow(instruction) => {
+                write!(f, "{} needs more values on the stack", name(instruction))
+            }
+            RunError::MissingLiteral => f.write_str("LITERAL has no value"),
+        }
+    }
+}
+
+impl Error for RunError {}
+
+/// Assembles a script into bytecode for the [`VM`].
+///
+/// Each line is an instruction (see [`VM::INSTRUCTIONS`]), and `LITERAL` is followed by its value.
+/// Blank lines, and anything after a `#`, are ignored.
+///
+/// # Errors
+///
+/// If a line isn't a known instruction, a `LITERAL` has no (numeric) value, or an instruction
+/// would take more values than the lines before it leave on the stack.
+pub fn assemble(source: &str) -> Result<Vec<u64>, AssembleError> {
+    let mut bytes = Vec::new();
+    let mut depth = 0;
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| AssembleError {
+            line: index + 1,
+            message,
+        };
+        let mut words = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let Some(name) = words.next() else {
+            continue;
+        };
+        let (_, instruction) = VM::INSTRUCTIONS
+            .into_iter()
+            .find(|(known, _)| *known == name)
+            .ok_or_else(|| error(format!("unknown instruction {name}")))?;
+        bytes.push(instruction);
+
+        let pops = VM::pops(instruction).unwrap_or_default();
+        if pops > depth {
+            return Err(error(format!(
+                "{name} needs {pops} values on the stack, but there are {depth}"
+            )));
+        }
+        depth -= pops;
+
+        if instruction == VM::INST_LITERAL {
+            let value = words
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| error("LITERAL needs a numeric value".to_string()))?;
+            bytes.push(value);
+            depth += 1;
+        }
+        if let Some(extra) = words.next() {
+            return Err(error(format!("unexpected {extra}")));
+        }
+    }
+    Ok(bytes)
+}
+
+/// A line of a script that could not be assembled.
+#[derive(Debug)]
+pub struct AssembleError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AssembleError {}
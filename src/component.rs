@@ -0,0 +1,250 @@
+//! The component pattern's `GameObject`: an entity that delegates its input, physics, and graphics
+//! to components, so each domain can be swapped without touching the others.
+//!
+//! A game supplies the object's state (`S`, i.e. a position), the context that components read
+//! (`C`, i.e. the world and renderer), and a component for each domain:
+//!
+//! ```
+//! use game_patterns_rs::component::{
+//!     GameObject, GraphicsComponent, InputComponent, PhysicsComponent,
+//! };
+//!
+//! struct Walk;
+//! impl InputComponent<f32, ()> for Walk {
+//!     fn update(&mut self, velocity: &mut f32, _: &()) {
+//!         *velocity += 1.0;
+//!     }
+//! }
+//!
+//! struct Still;
+//! impl PhysicsComponent<f32, ()> for Still {
+//!     fn update(&mut self, _: &mut f32, _: &()) {}
+//! }
+//! impl GraphicsComponent<f32, ()> for Still {
+//!     fn update(&mut self, _: &mut f32, _: &()) {}
+//! }
+//!
+//! let mut object = GameObject::new(0.0, Walk, Still, Still);
+//! object.update(&());
+//! assert_eq!(object.state, 1.0);
+//! ```
+//!
+//! Each object's components live in their own boxes, wherever the allocator put them; a
+//! [`ComponentManager`] instead stores each kind of component in its own contiguous array, and
+//! updates one kind at a time. `cargo bench --bench components` compares the two.
+
+/// Turns input (a controller, or an AI) into changes to the object's state.
+pub trait InputComponent<S, C> {
+    fn update(&mut self, state: &mut S, context: &C);
+}
+
+/// Moves the object through the world, and resolves collisions.
+pub trait PhysicsComponent<S, C> {
+    fn update(&mut self, state: &mut S, context: &C);
+}
+
+/// Draws the object.
+pub trait GraphicsComponent<S, C> {
+    fn update(&mut self, state: &mut S, context: &C);
+}
+
+/// Input that does nothing: the null object, for objects simulated without a player or an AI.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullInput;
+
+impl<S, C> InputComponent<S, C> for NullInput {
+    fn update(&mut self, _: &mut S, _: &C) {}
+}
+
+/// Graphics that draw nothing, for simulating objects without a display (i.e. in tests or CI).
+///
+/// ```
+/// use game_patterns_rs::component::{GameObject, NullGraphics, NullInput, PhysicsComponent};
+///
+/// struct Gravity;
+/// impl PhysicsComponent<f32, f32> for Gravity {
+///     fn update(&mut self, height: &mut f32, gravity: &f32) {
+///         *height = (*height - gravity).max(0.0);
+///     }
+/// }
+///
+/// let mut rock = GameObject::new(3.0, NullInput, Gravity, NullGraphics);
+/// for _ in 0..5 {
+///     rock.update(&1.0);
+/// }
+/// assert_eq!(rock.state, 0.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullGraphics;
+
+impl<S, C> GraphicsComponent<S, C> for NullGraphics {
+    fn update(&mut self, _: &mut S, _: &C) {}
+}
+
+/// An object whose behavior is entirely defined by its components.
+pub struct GameObject<S, C> {
+    pub state: S,
+    input: Box<dyn InputComponent<S, C>>,
+    physics: Box<dyn PhysicsComponent<S, C>>,
+    graphics: Box<dyn GraphicsComponent<S, C>>,
+}
+
+impl<S, C> GameObject<S, C> {
+    pub fn new(
+        state: S,
+        input: impl InputComponent<S, C> + 'static,
+        physics: impl PhysicsComponent<S, C> + 'static,
+        graphics: impl GraphicsComponent<S, C> + 'static,
+    ) -> Self {
+        GameObject {
+            state,
+            input: Box::new(input),
+            physics: Box::new(physics),
+            graphics: Box::new(graphics),
+        }
+    }
+
+    /// Replaces the input component (i.e. a player with an AI), returning the previous one.
+    pub fn set_input(
+        &mut self,
+        input: Box<dyn InputComponent<S, C>>,
+    ) -> Box<dyn InputComponent<S, C>> {
+        std::mem::replace(&mut self.input, input)
+    }
+
+    /// Updates the input, physics, and graphics components, in that order.
+    pub fn update(&mut self, context: &C) {
+        self.update_input(context);
+        self.update_physics(context);
+        self.update_graphics(context);
+    }
+
+    /// Updates only the input component; useful for updating one domain across many objects.
+    pub fn update_input(&mut self, context: &C) {
+        self.input.update(&mut self.state, context);
+    }
+
+    /// Updates only the physics component.
+    pub fn update_physics(&mut self, context: &C) {
+        self.physics.update(&mut self.state, context);
+    }
+
+    /// Updates only the graphics component.
+    pub fn update_graphics(&mut self, context: &C) {
+        self.graphics.update(&mut self.state, context);
+    }
+}
+
+impl<S, C, T: InputComponent<S, C> + ?Sized> InputComponent<S, C> for Box<T> {
+    fn update(&mut self, state: &mut S, context: &C) {
+        T::update(self, state, context);
+    }
+}
+
+impl<S, C, T: PhysicsComponent<S, C> + ?Sized> PhysicsComponent<S, C> for Box<T> {
+    fn update(&mut self, state: &mut S, context: &C) {
+        T::update(self, state, context);
+    }
+}
+
+impl<S, C, T: GraphicsComponent<S, C> + ?Sized> GraphicsComponent<S, C> for Box<T> {
+    fn update(&mut self, state: &mut S, context: &C) {
+        T::update(self, state, context);
+    }
+}
+
+/// Objects whose state and components are each stored in their own array, indexed by object.
+///
+/// Unlike a [`GameObject`], components are concrete types rather than boxed trait objects, and
+/// [`ComponentManager::update`] runs every input component, then every physics component, then
+/// every graphics component, walking each array in order.
+///
+/// ```
+/// use game_patterns_rs::component::{
+///     ComponentManager, NullGraphics, NullInput, PhysicsComponent,
+/// };
+///
+/// struct Gravity;
+/// impl PhysicsComponent<f32, f32> for Gravity {
+///     fn update(&mut self, height: &mut f32, gravity: &f32) {
+///         *height = (*height - gravity).max(0.0);
+///     }
+/// }
+///
+/// let mut rocks = ComponentManager::new();
+/// let high = rocks.spawn(5.0, NullInput, Gravity, NullGraphics);
+/// let low = rocks.spawn(1.0, NullInput, Gravity, NullGraphics);
+/// rocks.update(&2.0);
+/// assert_eq!(rocks.state(high), &3.0);
+/// assert_eq!(rocks.state(low), &0.0);
+/// ```
+pub struct ComponentManager<S, I, P, G> {
+    states: Vec<S>,
+    inputs: Vec<I>,
+    physics: Vec<P>,
+    graphics: Vec<G>,
+}
+
+impl<S, I, P, G> ComponentManager<S, I, P, G> {
+    pub fn new() -> Self {
+        Self {
+            states: Vec::new(),
+            inputs: Vec::new(),
+            physics: Vec::new(),
+            graphics: Vec::new(),
+        }
+    }
+
+    /// Adds an object, returning its index.
+    pub fn spawn(&mut self, state: S, input: I, physics: P, graphics: G) -> usize {
+        self.states.push(state);
+        self.inputs.push(input);
+        self.physics.push(physics);
+        self.graphics.push(graphics);
+        self.states.len() - 1
+    }
+
+    /// Returns the number of objects.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns whether there are no objects.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Returns the state of the object at `index`.
+    pub fn state(&self, index: usize) -> &S {
+        &self.states[index]
+    }
+
+    /// Returns the state of every object.
+    pub fn states(&self) -> &[S] {
+        &self.states
+    }
+
+    /// Updates every object's input, then physics, then graphics components.
+    pub fn update<C>(&mut self, context: &C)
+    where
+        I: InputComponent<S, C>,
+        P: PhysicsComponent<S, C>,
+        G: GraphicsComponent<S, C>,
+    {
+        for (input, state) in self.inputs.iter_mut().zip(&mut self.states) {
+            input.update(state, context);
+        }
+        for (physics, state) in self.physics.iter_mut().zip(&mut self.states) {
+            physics.update(state, context);
+        }
+        for (graphics, state) in self.graphics.iter_mut().zip(&mut self.states) {
+            graphics.update(state, context);
+        }
+    }
+}
+
+impl<S, I, P, G> Default for ComponentManager<S, I, P, G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,803 @@
+//! A minimal entity component system (ECS), the natural end-state of the component pattern.
+//!
+//! Rather than an object that owns its components (see [`crate::component`]), an [`Entity`] is
+//! only an id, and each type of component is stored, densely, in its own [`SparseSet`]. Systems
+//! are then plain loops over the components they need:
+//!
+//! ```
+//! use game_patterns_rs::ecs::World;
+//!
+//! struct Position(f32);
+//! struct Velocity(f32);
+//!
+//! let mut world = World::new();
+//! let moving = world.spawn();
+//! world.insert(moving, Position(0.0));
+//! world.insert(moving, Velocity(2.0));
+//! let still = world.spawn();
+//! world.insert(still, Position(5.0));
+//!
+//! // A movement system: every entity with both a position and a velocity.
+//! let mut positions = world.storage_mut::<Position>();
+//! let velocities = world.storage::<Velocity>();
+//! for (_, position, velocity) in positions.join_mut(&velocities) {
+//!     position.0 += velocity.0;
+//! }
+//! drop((positions, velocities));
+//!
+//! assert_eq!(world.get::<Position>(moving).unwrap().0, 2.0);
+//! assert_eq!(world.get::<Position>(still).unwrap().0, 5.0);
+//! ```
+//!
+//! The [`query!`](crate::query) macro writes that loop from the components it needs instead:
+//!
+//! ```
+//! # use game_patterns_rs::{ecs::World, query};
+//! # struct Position(f32);
+//! # struct Velocity(f32);
+//! # let mut world = World::new();
+//! # world.register::<Position>();
+//! # world.register::<Velocity>();
+//! query!(world, |_, position: &mut Position, velocity: &Velocity| {
+//!     position.0 += velocity.0;
+//! });
+//! ```
+//!
+//! Other systems can react to components being attached or detached with [`World::observe`].
+//!
+//! [`archetype`] stores components differently (grouped by which components an entity has); see
+//! `benches/ecs.rs` for how the two compare.
+
+pub mod archetype;
+
+use std::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+};
+
+/// Identifies an entity in a [`World`].
+///
+/// Ids are reused once an entity is despawned, so each also has a generation; an id kept around
+/// after its entity was despawned never refers to the entity that reuses its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Returns the index of the entity, which is unique among living entities.
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+
+    /// Returns how many entities used this index before this one.
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+/// Components of one type, stored contiguously, with constant time lookup by [`Entity`].
+///
+/// ```
+/// use game_patterns_rs::ecs::{SparseSet, World};
+///
+/// let mut world = World::new();
+/// let (a, b) = (world.spawn(), world.spawn());
+///
+/// let mut names = SparseSet::new();
+/// names.insert(a, "a");
+/// names.insert(b, "b");
+/// assert_eq!(names.remove(a), Some("a"));
+/// assert_eq!(names.iter().collect::<Vec<_>>(), [(b, &"b")]);
+///
+/// // An entity reusing a despawned one's index takes the place of any component left behind.
+/// world.despawn(b);
+/// let c = world.spawn();
+/// assert_eq!(names.insert(c, "c"), None);
+/// assert_eq!(names.iter().collect::<Vec<_>>(), [(c, &"c")]);
+/// ```
+pub struct SparseSet<T> {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<T>,
+    entities: Vec<Entity>,
+}
+
+impl<T> SparseSet<T> {
+    pub fn new() -> Self {
+        SparseSet {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    /// Returns the number of components.
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Returns whether there are no components.
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Returns the entities that have a component, in the same order as [`SparseSet::iter`].
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns whether the entity has a component.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.position(entity).is_some()
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.position(entity).map(|index| &self.dense[index])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.position(entity).map(|index| &mut self.dense[index])
+    }
+
+    /// Sets the entity's component, returning the previous one, if any.
+    ///
+    /// If an older generation of the entity (one with the same index) has a component, it's
+    /// dropped, and this one takes its place. If a newer generation does, `entity` is stale (it was
+    /// despawned, and its index reused), so the insert is ignored.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(Some(index)) = self.sparse.get(entity.index()).copied() {
+            let occupant = self.entities[index];
+            if occupant.generation > entity.generation {
+                return None;
+            }
+            let previous = std::mem::replace(&mut self.dense[index], component);
+            if occupant == entity {
+                return Some(previous);
+            }
+            self.entities[index] = entity;
+            return None;
+        }
+        if self.sparse.len() <= entity.index() {
+            self.sparse.resize(entity.index() + 1, None);
+        }
+        self.sparse[entity.index()] = Some(self.dense.len());
+        self.dense.push(component);
+        self.entities.push(entity);
+        None
+    }
+
+    /// Removes and returns the entity's component, if any.
+    ///
+    /// The last component is moved into its place, so removal is constant time (but changes the
+    /// order of iteration).
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.position(entity)?;
+        self.sparse[entity.index()] = None;
+        self.entities.swap_remove(index);
+        if let Some(&moved) = self.entities.get(index) {
+            self.sparse[moved.index()] = Some(index);
+        }
+        Some(self.dense.swap_remove(index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.dense)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entities.iter().copied().zip(&mut self.dense)
+    }
+
+    /// Iterates over the entities that have both this component and one in `other`.
+    pub fn join<'a, U>(
+        &'a self,
+        other: &'a SparseSet<U>,
+    ) -> impl Iterator<Item = (Entity, &'a T, &'a U)> {
+        self.iter()
+            .filter_map(|(entity, component)| Some((entity, component, other.get(entity)?)))
+    }
+
+    /// Like [`SparseSet::join`], but with mutable access to this set's components.
+    pub fn join_mut<'a, U>(
+        &'a mut self,
+        other: &'a SparseSet<U>,
+    ) -> impl Iterator<Item = (Entity, &'a mut T, &'a U)> {
+        self.iter_mut()
+            .filter_map(|(entity, component)| Some((entity, component, other.get(entity)?)))
+    }
+
+    /// Returns where in `dense` the entity's component is.
+    fn position(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.index())?)?;
+        (self.entities[index] == entity).then_some(index)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Send> SparseSet<T> {
+    /// Like [`SparseSet::iter_mut`], but calls `f` from several threads, each with a chunk of the
+    /// components.
+    ///
+    /// Starting threads isn't free, so this only pays off for many components (thousands), or
+    /// expensive work per component.
+    ///
+    /// ```
+    /// use game_patterns_rs::ecs::World;
+    ///
+    /// let mut world = World::new();
+    /// for i in 0..1000 {
+    ///     let entity = world.spawn();
+    ///     world.insert(entity, i);
+    /// }
+    /// world.storage_mut::<i32>().par_for_each_mut(|_, i| *i *= 2);
+    /// assert_eq!(world.storage::<i32>().iter().map(|(_, i)| i).sum::<i32>(), 999_000);
+    /// ```
+    pub fn par_for_each_mut(&mut self, f: impl Fn(Entity, &mut T) + Sync) {
+        self.par_chunks_mut(|entities, components| {
+            for (entity, component) in entities.iter().zip(components) {
+                f(*entity, component);
+            }
+        });
+    }
+
+    /// Like [`SparseSet::join_mut`], but calls `f` from several threads; see
+    /// [`SparseSet::par_for_each_mut`].
+    pub fn par_join_mut<U: Sync>(
+        &mut self,
+        other: &SparseSet<U>,
+        f: impl Fn(Entity, &mut T, &U) + Sync,
+    ) {
+        self.par_chunks_mut(|entities, components| {
+            for (entity, component) in entities.iter().zip(components) {
+                if let Some(other) = other.get(*entity) {
+                    f(*entity, component, other);
+                }
+            }
+        });
+    }
+
+    /// Splits the components into a chunk per available thread, and calls `f` with each chunk.
+    fn par_chunks_mut(&mut self, f: impl Fn(&[Entity], &mut [T]) + Sync) {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunk = self.dense.len().div_ceil(threads).max(1);
+        let f = &f;
+        std::thread::scope(|scope| {
+            for (entities, components) in self
+                .entities
+                .chunks(chunk)
+                .zip(self.dense.chunks_mut(chunk))
+            {
+                scope.spawn(move || f(entities, components));
+            }
+        });
+    }
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs a block for every entity that has all of the given components.
+///
+/// Each component is named, and borrowed as `&T` or `&mut T`; the first one listed decides the
+/// order of iteration (so list the rarest first). Like a closure, except that `continue` moves to
+/// the next entity:
+///
+/// ```
+/// use game_patterns_rs::{ecs::World, query};
+///
+/// struct Position(f32);
+/// struct Velocity(f32);
+///
+/// let mut world = World::new();
+/// let moving = world.spawn();
+/// world.insert(moving, Position(0.0));
+/// world.insert(moving, Velocity(2.0));
+/// let still = world.spawn();
+/// world.insert(still, Position(5.0));
+///
+/// query!(world, |entity, velocity: &Velocity, position: &mut Position| {
+///     assert_eq!(entity, moving);
+///     position.0 += velocity.0;
+/// });
+/// assert_eq!(world.get::<Position>(moving).unwrap().0, 2.0);
+/// ```
+///
+/// # Panics
+///
+/// If a component type isn't registered (see [`World::register`]), is listed twice, or (as with
+/// [`World::storage`]) its storage is already borrowed.
+#[macro_export]
+macro_rules! query {
+    ($world:expr, |$entity:pat_param, $first:ident : $first_ty:ty $(, $name:ident : $ty:ty)* $(,)?| $body:block) => {{
+        use $crate::ecs::Fetch as _;
+        let world: &$crate::ecs::World = &$world;
+        let mut $first = <$first_ty>::borrow(world);
+        $(let mut $name = <$ty>::borrow(world);)*
+        for index in 0..<$first_ty>::entities(&$first).len() {
+            let entity = <$first_ty>::entities(&$first)[index];
+            let Some($first) = <$first_ty>::fetch(&mut $first, entity) else {
+                continue;
+            };
+            $(let Some($name) = <$ty>::fetch(&mut $name, entity) else {
+                continue;
+            };)*
+            let $entity = entity;
+            $body
+        }
+    }};
+}
+
+/// How [`query!`](crate::query) borrows a component type: `&T` to read it, `&mut T` to write it.
+#[doc(hidden)]
+pub trait Fetch {
+    type Storage<'w>;
+    type Item<'s>;
+
+    fn borrow(world: &World) -> Self::Storage<'_>;
+    fn entities<'s>(storage: &'s Self::Storage<'_>) -> &'s [Entity];
+    fn fetch<'s>(storage: &'s mut Self::Storage<'_>, entity: Entity) -> Option<Self::Item<'s>>;
+}
+
+impl<T: 'static> Fetch for &T {
+    type Storage<'w> = Ref<'w, SparseSet<T>>;
+    type Item<'s> = &'s T;
+
+    fn borrow(world: &World) -> Self::Storage<'_> {
+        world.storage()
+    }
+
+    fn entities<'s>(storage: &'s Self::Storage<'_>) -> &'s [Entity] {
+        storage.entities()
+    }
+
+    fn fetch<'s>(storage: &'s mut Self::Storage<'_>, entity: Entity) -> Option<Self::Item<'s>> {
+        storage.get(entity)
+    }
+}
+
+impl<T: 'static> Fetch for &mut T {
+    type Storage<'w> = RefMut<'w, SparseSet<T>>;
+    type Item<'s> = &'s mut T;
+
+    fn borrow(world: &World) -> Self::Storage<'_> {
+        world.storage_mut()
+    }
+
+    fn entities<'s>(storage: &'s Self::Storage<'_>) -> &'s [Entity] {
+        storage.entities()
+    }
+
+    fn fetch<'s>(storage: &'s mut Self::Storage<'_>, entity: Entity) -> Option<Self::Item<'s>> {
+        storage.get_mut(entity)
+    }
+}
+
+/// A [`SparseSet`] of any component type, so a [`World`] can hold one of each.
+trait Storage: Any {
+    /// Removes the entity's component, returning `false` if it didn't have one.
+    fn remove_entity(&mut self, entity: Entity) -> bool;
+}
+
+impl<T: 'static> Storage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) -> bool {
+        self.remove(entity).is_some()
+    }
+}
+
+/// A component being attached to, or detached from, an entity; see [`World::observe`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentEvent {
+    /// The entity gained a component of a type it didn't have (replacing one doesn't count).
+    Attached(Entity, ComponentType),
+
+    /// The entity lost a component, because it was removed or the entity was despawned.
+    Detached(Entity, ComponentType),
+}
+
+impl ComponentEvent {
+    /// Returns the entity the component was attached to, or detached from.
+    pub fn entity(self) -> Entity {
+        match self {
+            ComponentEvent::Attached(entity, _) | ComponentEvent::Detached(entity, _) => entity,
+        }
+    }
+
+    /// Returns the type of the component.
+    pub fn component(self) -> ComponentType {
+        match self {
+            ComponentEvent::Attached(_, component) | ComponentEvent::Detached(_, component) => {
+                component
+            }
+        }
+    }
+}
+
+/// The type of a component, as reported by a [`ComponentEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ComponentType {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl ComponentType {
+    pub fn of<T: 'static>() -> Self {
+        ComponentType {
+            id: TypeId::of::<T>(),
+            name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// Returns whether this is the type `T`.
+    pub fn is<T: 'static>(self) -> bool {
+        self.id == TypeId::of::<T>()
+    }
+
+    /// Returns the name of the type, for debugging.
+    pub fn name(self) -> &'static str {
+        self.name
+    }
+}
+
+type Observer = dyn FnMut(ComponentEvent);
+
+/// Identifies an observer added with [`World::observe`], so it can be removed again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObserverId(usize);
+
+/// Entities, and a storage for each type of component they have.
+///
+/// Each storage is borrowed separately (and checked at runtime, like a [`RefCell`]), so a system
+/// can read some component types while writing others.
+///
+/// ```
+/// use game_patterns_rs::ecs::World;
+///
+/// let mut world = World::new();
+/// let goblin = world.spawn();
+/// world.insert(goblin, "goblin");
+/// assert!(world.despawn(goblin));
+///
+/// // The index is reused, but the old id doesn't refer to the new entity.
+/// let troll = world.spawn();
+/// assert_eq!(troll.index(), goblin.index());
+/// assert!(!world.is_alive(goblin));
+/// assert!(world.get::<&str>(goblin).is_none());
+/// ```
+#[derive(Default)]
+pub struct World {
+    entities: Allocator,
+    storages: HashMap<TypeId, (ComponentType, RefCell<Box<dyn Storage>>)>,
+
+    /// The keys of `storages`, in the order they were registered.
+    registered: Vec<TypeId>,
+    observers: Vec<(ObserverId, Box<Observer>)>,
+    next_observer: usize,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an entity, with no components.
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.allocate()
+    }
+
+    /// Removes an entity and all of its components, returning `false` if it was not alive.
+    ///
+    /// Observers see the components detached in the order their types were registered.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        let mut detached = Vec::new();
+        for id in &self.registered {
+            let (component, storage) = self.storages.get_mut(id).unwrap();
+            if storage.get_mut().remove_entity(entity) {
+                detached.push(ComponentEvent::Detached(entity, *component));
+            }
+        }
+        for event in detached {
+            self.notify(event);
+        }
+        true
+    }
+
+    /// Returns whether the entity has been spawned, and not despawned since.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    /// Returns the number of living entities.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns whether there are no living entities.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns every living entity, in order of their index.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter()
+    }
+
+    /// Creates an (empty) storage for components of type `T`, if there isn't one already.
+    ///
+    /// Inserting a component registers its type, so this is only needed to query a type before
+    /// any entity has it.
+    pub fn register<T: 'static>(&mut self) {
+        self.storages.entry(TypeId::of::<T>()).or_insert_with(|| {
+            self.registered.push(TypeId::of::<T>());
+            (
+                ComponentType::of::<T>(),
+                RefCell::new(Box::new(SparseSet::<T>::new())),
+            )
+        });
+    }
+
+    /// Calls `observer` whenever a component is attached to, or detached from, any entity.
+    ///
+    /// Observers are called after the change, so they can't borrow the world; instead, they keep
+    /// their own state in sync (a render batch, a spatial index, ...).
+    ///
+    /// ```
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// use game_patterns_rs::ecs::{ComponentEvent, World};
+    ///
+    /// struct Sprite;
+    ///
+    /// let mut world = World::new();
+    /// let sprites = Rc::new(RefCell::new(Vec::new()));
+    /// let batch = sprites.clone();
+    /// world.observe(move |event| {
+    ///     if event.component().is::<Sprite>() {
+    ///         match event {
+    ///             ComponentEvent::Attached(entity, _) => batch.borrow_mut().push(entity),
+    ///             ComponentEvent::Detached(entity, _) => batch.borrow_mut().retain(|e| *e != entity),
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// let tree = world.spawn();
+    /// world.insert(tree, Sprite);
+    /// assert_eq!(*sprites.borrow(), [tree]);
+    ///
+    /// world.despawn(tree);
+    /// assert!(sprites.borrow().is_empty());
+    /// ```
+    pub fn observe(&mut self, observer: impl FnMut(ComponentEvent) + 'static) -> ObserverId {
+        let id = ObserverId(self.next_observer);
+        self.next_observer += 1;
+        self.observers.push((id, Box::new(observer)));
+        id
+    }
+
+    /// Removes an observer, returning `false` if it was already removed.
+    pub fn unobserve(&mut self, observer: ObserverId) -> bool {
+        let len = self.observers.len();
+        self.observers.retain(|(id, _)| *id != observer);
+        self.observers.len() != len
+    }
+
+    /// Sets the entity's component of type `T`, returning the previous one, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the entity is not alive, or the storage for `T` is borrowed.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        assert!(self.is_alive(entity), "{entity:?} is not alive");
+        self.register::<T>();
+        let previous = self.storage_mut::<T>().insert(entity, component);
+        if previous.is_none() {
+            self.notify(ComponentEvent::Attached(entity, ComponentType::of::<T>()));
+        }
+        previous
+    }
+
+    /// Removes and returns the entity's component of type `T`, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the storage for `T` is borrowed.
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        let removed = self.try_storage_mut::<T>()?.remove(entity)?;
+        self.notify(ComponentEvent::Detached(entity, ComponentType::of::<T>()));
+        Some(removed)
+    }
+
+    /// Returns the entity's component of type `T`, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the storage for `T` is mutably borrowed.
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.try_storage::<T>()?, |storage| storage.get(entity)).ok()
+    }
+
+    /// Returns the entity's component of type `T` mutably, if any.
+    ///
+    /// # Panics
+    ///
+    /// If the storage for `T` is borrowed.
+    pub fn get_mut<T: 'static>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.try_storage_mut::<T>()?, |storage| {
+            storage.get_mut(entity)
+        })
+        .ok()
+    }
+
+    /// Borrows every component of type `T`.
+    ///
+    /// # Panics
+    ///
+    /// If `T` was never registered (see [`World::register`]), or its storage is mutably borrowed.
+    pub fn storage<T: 'static>(&self) -> Ref<'_, SparseSet<T>> {
+        self.try_storage()
+            .unwrap_or_else(|| panic!("{} is not registered", std::any::type_name::<T>()))
+    }
+
+    /// Mutably borrows every component of type `T`.
+    ///
+    /// # Panics
+    ///
+    /// If `T` was never registered (see [`World::register`]), or its storage is borrowed.
+    pub fn storage_mut<T: 'static>(&self) -> RefMut<'_, SparseSet<T>> {
+        self.try_storage_mut()
+            .unwrap_or_else(|| panic!("{} is not registered", std::any::type_name::<T>()))
+    }
+
+    fn try_storage<T: 'static>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.1.borrow();
+        Some(Ref::map(storage, |storage| {
+            (storage.as_ref() as &dyn Any).downcast_ref().unwrap()
+        }))
+    }
+
+    fn try_storage_mut<T: 'static>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.1.borrow_mut();
+        Some(RefMut::map(storage, |storage| {
+            (storage.as_mut() as &mut dyn Any).downcast_mut().unwrap()
+        }))
+    }
+
+    fn notify(&mut self, event: ComponentEvent) {
+        for (_, observer) in &mut self.observers {
+            observer(event);
+        }
+    }
+}
+
+/// Hands out entity ids, reusing the indices of despawned entities with a new generation.
+#[derive(Default)]
+struct Allocator {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+}
+
+impl Allocator {
+    fn allocate(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                let index = u32::try_from(self.generations.len()).expect("Too many entities");
+                self.generations.push(0);
+                self.alive.push(false);
+                index
+            }
+        };
+        self.alive[index as usize] = true;
+        Entity {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+
+    /// Frees the entity's index for reuse, returning `false` if it was not alive.
+    fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        self.alive[entity.index()] = false;
+        self.generations[entity.index()] = entity.generation.wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.get(entity.index()).copied().unwrap_or(false)
+            && self.generations[entity.index()] == entity.generation
+    }
+
+    fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(index, _)| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn insert_replaces_an_older_generation() {
+        let mut world = World::new();
+        let old = world.spawn();
+        let mut names = SparseSet::new();
+        names.insert(old, "old");
+
+        world.despawn(old);
+        let new = world.spawn();
+        assert_eq!(new.index(), old.index());
+        assert_eq!(names.insert(new, "new"), None);
+        assert_eq!(names.iter().collect::<Vec<_>>(), [(new, &"new")]);
+        assert_eq!(names.get(old), None);
+    }
+
+    #[test]
+    fn insert_with_a_stale_entity_is_ignored() {
+        let mut world = World::new();
+        let stale = world.spawn();
+        world.despawn(stale);
+        let live = world.spawn();
+        assert_eq!(live.index(), stale.index());
+
+        let mut names = SparseSet::new();
+        names.insert(live, "live");
+        assert_eq!(names.insert(stale, "stale"), None);
+        assert_eq!(names.iter().collect::<Vec<_>>(), [(live, &"live")]);
+        assert_eq!(names.get(stale), None);
+    }
+
+    #[test]
+    fn despawn_detaches_in_registration_order() {
+        struct A;
+        struct B;
+        struct C;
+        struct D;
+
+        let mut world = World::new();
+        let detached = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&detached);
+        world.observe(move |event| {
+            if let ComponentEvent::Detached(_, component) = event {
+                log.borrow_mut().push(component);
+            }
+        });
+        let entity = world.spawn();
+        world.insert(entity, C);
+        world.insert(entity, A);
+        world.insert(entity, D);
+        world.insert(entity, B);
+        world.despawn(entity);
+
+        let order = [
+            ComponentType::of::<C>(),
+            ComponentType::of::<A>(),
+            ComponentType::of::<D>(),
+            ComponentType::of::<B>(),
+        ];
+        assert_eq!(*detached.borrow(), order);
+    }
+}
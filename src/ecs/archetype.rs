@@ -0,0 +1,316 @@
+//! Archetype storage: entities are grouped by the exact set of component types they have.
+//!
+//! Each group (an archetype) stores a column per component type, so iterating over entities with
+//! some components is a walk down a few arrays, with no lookups at all. The cost moves to adding
+//! and removing components, which moves the entity (every one of its components) to another
+//! archetype. [`World`](super::World)'s sparse sets make the opposite trade.
+//!
+//! ```
+//! use game_patterns_rs::ecs::archetype::ArchetypeWorld;
+//!
+//! struct Position(f32);
+//! struct Velocity(f32);
+//!
+//! let mut world = ArchetypeWorld::new();
+//! let moving = world.spawn();
+//! world.insert(moving, Position(0.0));
+//! world.insert(moving, Velocity(2.0));
+//!
+//! world.for_each_mut(|_, position: &mut Position, velocity: &Velocity| {
+//!     position.0 += velocity.0;
+//! });
+//! assert_eq!(world.get::<Position>(moving).unwrap().0, 2.0);
+//!
+//! // Removing a component moves the entity back to an archetype without it.
+//! assert!(world.remove::<Velocity>(moving).is_some());
+//! assert_eq!(world.get::<Position>(moving).unwrap().0, 2.0);
+//! ```
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use super::{Allocator, Entity};
+
+/// Components of one type within an archetype, type-erased so an archetype can hold any types.
+trait Column: Any {
+    /// Returns an empty column of the same type.
+    fn empty(&self) -> Box<dyn Column>;
+
+    /// Moves the component at `row` to the end of `other` (a column of the same type).
+    fn move_to(&mut self, row: usize, other: &mut dyn Column);
+
+    /// Drops the component at `row`.
+    fn remove(&mut self, row: usize);
+}
+
+impl<T: 'static> Column for Vec<T> {
+    fn empty(&self) -> Box<dyn Column> {
+        Box::new(Vec::<T>::new())
+    }
+
+    fn move_to(&mut self, row: usize, other: &mut dyn Column) {
+        let other: &mut Vec<T> = (other as &mut dyn Any).downcast_mut().unwrap();
+        other.push(self.swap_remove(row));
+    }
+
+    fn remove(&mut self, row: usize) {
+        self.swap_remove(row);
+    }
+}
+
+/// Every entity with exactly the same component types, and their components.
+struct Archetype {
+    /// Sorted, so that the same set of types is always found in the same archetype.
+    types: Vec<TypeId>,
+    columns: Vec<Box<dyn Column>>,
+    entities: Vec<Entity>,
+}
+
+impl Archetype {
+    fn column(&self, id: TypeId) -> Option<usize> {
+        self.types.binary_search(&id).ok()
+    }
+}
+
+/// Where an entity's components are.
+#[derive(Clone, Copy)]
+struct Location {
+    archetype: usize,
+    row: usize,
+}
+
+/// Entities, with their components stored by [`Archetype`].
+pub struct ArchetypeWorld {
+    entities: Allocator,
+    locations: Vec<Location>,
+    archetypes: Vec<Archetype>,
+    by_types: HashMap<Vec<TypeId>, usize>,
+}
+
+impl ArchetypeWorld {
+    pub fn new() -> Self {
+        // Entities without components live in the first archetype.
+        ArchetypeWorld {
+            entities: Allocator::default(),
+            locations: Vec::new(),
+            archetypes: vec![Archetype {
+                types: Vec::new(),
+                columns: Vec::new(),
+                entities: Vec::new(),
+            }],
+            by_types: HashMap::from([(Vec::new(), 0)]),
+        }
+    }
+
+    /// Creates an entity, with no components.
+    pub fn spawn(&mut self) -> Entity {
+        let entity = self.entities.allocate();
+        let location = Location {
+            archetype: 0,
+            row: self.archetypes[0].entities.len(),
+        };
+        self.archetypes[0].entities.push(entity);
+        if self.locations.len() <= entity.index() {
+            self.locations.resize(entity.index() + 1, location);
+        }
+        self.locations[entity.index()] = location;
+        entity
+    }
+
+    /// Removes an entity and all of its components, returning `false` if it was not alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        let Location { archetype, row } = self.locations[entity.index()];
+        for column in &mut self.archetypes[archetype].columns {
+            column.remove(row);
+        }
+        self.remove_row(archetype, row);
+        true
+    }
+
+    /// Returns whether the entity has been spawned, and not despawned since.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    /// Returns the number of living entities.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns whether there are no living entities.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entity's component of type `T`, if any.
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        let Location { archetype, row } = self.locations[entity.index()];
+        let archetype = &self.archetypes[archetype];
+        let column = archetype.column(TypeId::of::<T>())?;
+        let column: &Vec<T> = (archetype.columns[column].as_ref() as &dyn Any)
+            .downcast_ref()
+            .unwrap();
+        Some(&column[row])
+    }
+
+    /// Sets the entity's component of type `T`, returning the previous one, if any.
+    ///
+    /// If the entity didn't have a `T`, it (and all of its components) moves to another archetype.
+    ///
+    /// # Panics
+    ///
+    /// If the entity is not alive.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        assert!(self.is_alive(entity), "{entity:?} is not alive");
+        let id = TypeId::of::<T>();
+        let Location { archetype, row } = self.locations[entity.index()];
+        if let Some(column) = self.archetypes[archetype].column(id) {
+            let column = column_mut::<T>(&mut self.archetypes[archetype], column);
+            return Some(std::mem::replace(&mut column[row], component));
+        }
+
+        let mut types = self.archetypes[archetype].types.clone();
+        let index = types.binary_search(&id).unwrap_err();
+        types.insert(index, id);
+        let target = self.find_or_create(types, archetype, |columns| {
+            columns.insert(index, Box::new(Vec::<T>::new()));
+        });
+
+        self.move_entity(entity, target);
+        let target = &mut self.archetypes[target];
+        column_mut::<T>(target, index).push(component);
+        None
+    }
+
+    /// Removes and returns the entity's component of type `T`, if any.
+    ///
+    /// If it had one, the entity (and all of its other components) moves to another archetype.
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        let Location { archetype, row } = self.locations[entity.index()];
+        let index = self.archetypes[archetype].column(TypeId::of::<T>())?;
+        let component = column_mut::<T>(&mut self.archetypes[archetype], index).swap_remove(row);
+
+        let mut types = self.archetypes[archetype].types.clone();
+        types.remove(index);
+        let target = self.find_or_create(types, archetype, |columns| {
+            columns.remove(index);
+        });
+
+        self.move_entity(entity, target);
+        Some(component)
+    }
+
+    /// Calls `f` with every entity that has both an `A` and a `B`.
+    ///
+    /// # Panics
+    ///
+    /// If `A` and `B` are the same type.
+    pub fn for_each_mut<A: 'static, B: 'static>(&mut self, mut f: impl FnMut(Entity, &mut A, &B)) {
+        let (a, b) = (TypeId::of::<A>(), TypeId::of::<B>());
+        assert_ne!(a, b, "Cannot borrow a component type twice");
+        for archetype in &mut self.archetypes {
+            let (Some(a), Some(b)) = (archetype.column(a), archetype.column(b)) else {
+                continue;
+            };
+            let [a, b] = archetype.columns.get_disjoint_mut([a, b]).unwrap();
+            let a: &mut Vec<A> = (a.as_mut() as &mut dyn Any).downcast_mut().unwrap();
+            let b: &Vec<B> = (b.as_ref() as &dyn Any).downcast_ref().unwrap();
+            for ((entity, a), b) in archetype.entities.iter().zip(a).zip(b) {
+                f(*entity, a, b);
+            }
+        }
+    }
+
+    /// Returns the number of archetypes, including the one for entities without components.
+    pub fn archetypes(&self) -> usize {
+        self.archetypes.len()
+    }
+
+    /// Returns the archetype with exactly the given (sorted) types, creating it if necessary.
+    ///
+    /// A new archetype has the same column types as `source`, which differs by one type; `adjust`
+    /// adds or removes that column.
+    fn find_or_create(
+        &mut self,
+        types: Vec<TypeId>,
+        source: usize,
+        adjust: impl FnOnce(&mut Vec<Box<dyn Column>>),
+    ) -> usize {
+        if let Some(&index) = self.by_types.get(&types) {
+            return index;
+        }
+        let mut columns: Vec<Box<dyn Column>> = self.archetypes[source]
+            .columns
+            .iter()
+            .map(|column| column.empty())
+            .collect();
+        adjust(&mut columns);
+
+        let index = self.archetypes.len();
+        self.archetypes.push(Archetype {
+            types: types.clone(),
+            columns,
+            entities: Vec::new(),
+        });
+        self.by_types.insert(types, index);
+        index
+    }
+
+    /// Moves an entity's components from its archetype to `target`.
+    ///
+    /// Components of types that `target` doesn't have must already have been removed, and any
+    /// that only `target` has must be pushed by the caller afterwards.
+    fn move_entity(&mut self, entity: Entity, target: usize) {
+        let Location { archetype, row } = self.locations[entity.index()];
+        let [source, destination] = self
+            .archetypes
+            .get_disjoint_mut([archetype, target])
+            .unwrap();
+
+        for (id, column) in source.types.iter().zip(&mut source.columns) {
+            if let Some(other) = destination.column(*id) {
+                column.move_to(row, destination.columns[other].as_mut());
+            }
+        }
+
+        destination.entities.push(entity);
+        self.locations[entity.index()] = Location {
+            archetype: target,
+            row: destination.entities.len() - 1,
+        };
+        self.remove_row(archetype, row);
+    }
+
+    /// Removes `row` from an archetype's entities (its components are already gone), updating
+    /// the location of the entity moved into its place.
+    fn remove_row(&mut self, archetype: usize, row: usize) {
+        let entities = &mut self.archetypes[archetype].entities;
+        entities.swap_remove(row);
+        if let Some(&moved) = entities.get(row) {
+            self.locations[moved.index()].row = row;
+        }
+    }
+}
+
+impl Default for ArchetypeWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn column_mut<T: 'static>(archetype: &mut Archetype, column: usize) -> &mut Vec<T> {
+    (archetype.columns[column].as_mut() as &mut dyn Any)
+        .downcast_mut()
+        .unwrap()
+}
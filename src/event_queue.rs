@@ -0,0 +1,571 @@
+//! A bounded queue of events (of any type), stored in a ring buffer.
+//!
+//! Events are pushed at the tail and popped from the head; both wrap around the end of the
+//! buffer, so it never shifts (or, unless allowed to [grow](Overflow::Grow), allocates), and
+//! can be drained a few events at a time while more are pushed behind them:
+//!
+//! ```
+//! use game_patterns_rs::event_queue::EventQueue;
+//!
+//! let mut queue = EventQueue::<&str, 2>::new();
+//! queue.push("spawn").unwrap();
+//! queue.push("damage").unwrap();
+//! assert!(queue.is_full());
+//!
+//! assert_eq!(queue.pop(), Some("spawn"));
+//! queue.push("despawn").unwrap();
+//! assert_eq!(queue.pop(), Some("damage"));
+//! assert_eq!(queue.pop(), Some("despawn"));
+//! assert_eq!(queue.pop(), None);
+//! ```
+//!
+//! [`Timers`] hold events that aren't due yet ("explode in 3 seconds", "after 120 frames"), and
+//! push each to a queue on the update that it becomes due:
+//!
+//! ```
+//! use std::time::Duration;
+//! use game_patterns_rs::event_queue::{Delay, EventQueue, Timers};
+//!
+//! let mut timers = Timers::new();
+//! timers.schedule(Delay::Time(Duration::from_secs(1)), "explode");
+//! timers.schedule(Delay::Frames(2), "beep");
+//!
+//! let mut queue = EventQueue::<&str, 4>::new();
+//! timers.update(Duration::from_millis(600), &mut queue);
+//! assert!(queue.is_empty());
+//! timers.update(Duration::from_millis(600), &mut queue);
+//! assert_eq!(queue.pop(), Some("explode"));
+//! assert_eq!(queue.pop(), Some("beep"));
+//! ```
+//!
+//! A [`BroadcastQueue`] is read by several systems instead, each with its own [`Cursor`], so every
+//! one of them sees every event:
+//!
+//! ```
+//! use game_patterns_rs::event_queue::BroadcastQueue;
+//!
+//! let mut events = BroadcastQueue::<&str, 4>::new();
+//! let mut audio = events.subscribe();
+//! let mut achievements = events.subscribe();
+//!
+//! events.push("jump");
+//! assert_eq!(events.read(&mut audio).collect::<Vec<_>>(), [&"jump"]);
+//!
+//! events.push("land");
+//! assert_eq!(events.read(&mut audio).collect::<Vec<_>>(), [&"land"]);
+//! assert_eq!(events.read(&mut achievements).collect::<Vec<_>>(), [&"jump", &"land"]);
+//! ```
+
+use std::{
+    array,
+    cmp::Ordering,
+    collections::BinaryHeap,
+    error::Error,
+    fmt, iter,
+    time::{Duration, Instant},
+};
+
+/// A queue of `MAX` events, or more if it [grows](Overflow::Grow) when full.
+///
+/// A head and tail alone can't tell a full buffer from an empty one (both have `head == tail`),
+/// so the length is tracked instead of the tail.
+pub struct EventQueue<T, const MAX: usize> {
+    buffer: Vec<Option<(Instant, T)>>,
+    head: usize,
+    len: usize,
+    overflow: Overflow,
+    stats: QueueStats,
+}
+
+/// How an [`EventQueue`] has been used, to choose its `MAX` with data rather than guesses.
+///
+/// ```
+/// use game_patterns_rs::event_queue::{EventQueue, Overflow};
+///
+/// let mut queue = EventQueue::<u8, 2>::with_overflow(Overflow::DropNewest);
+/// for event in 1..=3 {
+///     queue.push(event).unwrap();
+/// }
+/// queue.pop();
+///
+/// let stats = queue.stats();
+/// assert_eq!(stats.high_water, 2);
+/// assert_eq!(stats.dropped, 1);
+/// assert_eq!(stats.delivered, 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueueStats {
+    /// The most events that have been pending at once.
+    pub high_water: usize,
+
+    /// Events discarded because the queue was full (see [`Overflow`]).
+    pub dropped: u64,
+
+    /// Events popped from the queue.
+    pub delivered: u64,
+
+    /// The time every delivered event spent in the queue, added together.
+    pub waited: Duration,
+}
+
+impl QueueStats {
+    /// Returns the average time a delivered event spent in the queue.
+    pub fn average_latency(&self) -> Duration {
+        if self.delivered == 0 {
+            return Duration::ZERO;
+        }
+        self.waited.div_f64(self.delivered as f64)
+    }
+}
+
+/// What [`EventQueue::push`] does when the queue is full.
+///
+/// ```
+/// use game_patterns_rs::event_queue::{EventQueue, Overflow, QueueFull};
+///
+/// let mut queue = EventQueue::<u8, 2>::with_overflow(Overflow::Error);
+/// assert_eq!(queue.push(1), Ok(()));
+/// assert_eq!(queue.push(2), Ok(()));
+/// assert_eq!(queue.push(3), Err(QueueFull(3)));
+/// assert_eq!(queue.iter().collect::<Vec<_>>(), [&1, &2]);
+///
+/// let mut queue = EventQueue::<u8, 2>::with_overflow(Overflow::DropNewest);
+/// for event in 1..=3 {
+///     queue.push(event).unwrap();
+/// }
+/// assert_eq!(queue.iter().collect::<Vec<_>>(), [&1, &2]);
+///
+/// let mut queue = EventQueue::<u8, 2>::with_overflow(Overflow::DropOldest);
+/// for event in 1..=3 {
+///     queue.push(event).unwrap();
+/// }
+/// assert_eq!(queue.iter().collect::<Vec<_>>(), [&2, &3]);
+///
+/// let mut queue = EventQueue::<u8, 2>::with_overflow(Overflow::Grow);
+/// queue.push(1).unwrap();
+/// assert_eq!(queue.pop(), Some(1));
+/// for event in 2..=5 {
+///     queue.push(event).unwrap();
+/// }
+/// assert_eq!(queue.iter().collect::<Vec<_>>(), [&2, &3, &4, &5]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Returns the event back, as an error.
+    #[default]
+    Error,
+
+    /// Discards the event being pushed.
+    DropNewest,
+
+    /// Discards the oldest pending event, to make room.
+    DropOldest,
+
+    /// Doubles the size of the buffer (which, unlike the others, allocates).
+    Grow,
+}
+
+/// Returned by [`EventQueue::push`] when the queue is full, with the event that didn't fit.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull<T>(pub T);
+
+impl<T> fmt::Debug for QueueFull<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("QueueFull(..)")
+    }
+}
+
+impl<T> fmt::Display for QueueFull<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("event queue is full")
+    }
+}
+
+impl<T> Error for QueueFull<T> {}
+
+impl<T, const MAX: usize> EventQueue<T, MAX> {
+    /// Creates an empty queue, which returns an error when pushed to while full.
+    pub fn new() -> Self {
+        Self::with_overflow(Overflow::default())
+    }
+
+    /// Creates an empty queue, which does as `overflow` says when pushed to while full.
+    ///
+    /// # Panics
+    ///
+    /// If `MAX` is zero.
+    pub fn with_overflow(overflow: Overflow) -> Self {
+        assert!(MAX > 0, "event queue must hold at least one event");
+        Self {
+            buffer: iter::repeat_with(|| None).take(MAX).collect(),
+            head: 0,
+            len: 0,
+            overflow,
+            stats: QueueStats::default(),
+        }
+    }
+
+    /// Returns the number of pending events.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether there are no pending events.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns how the queue has been used so far.
+    pub fn stats(&self) -> &QueueStats {
+        &self.stats
+    }
+
+    /// Returns whether another event would overflow.
+    pub fn is_full(&self) -> bool {
+        self.len == self.buffer.len()
+    }
+
+    /// Adds an event after every pending event, or, if full, does as the [`Overflow`] says.
+    ///
+    /// Only [`Overflow::Error`] ever returns an error.
+    pub fn push(&mut self, event: T) -> Result<(), QueueFull<T>> {
+        if self.is_full() {
+            match self.overflow {
+                Overflow::Error => return Err(QueueFull(event)),
+                Overflow::DropNewest => {
+                    self.stats.dropped += 1;
+                    return Ok(());
+                }
+                Overflow::DropOldest => {
+                    self.stats.dropped += 1;
+                    self.take();
+                }
+                Overflow::Grow => self.grow(),
+            }
+        }
+        let tail = (self.head + self.len) % self.buffer.len();
+        self.buffer[tail] = Some((Instant::now(), event));
+        self.len += 1;
+        self.stats.high_water = self.stats.high_water.max(self.len);
+        Ok(())
+    }
+
+    /// Removes and returns the oldest pending event.
+    pub fn pop(&mut self) -> Option<T> {
+        let (pushed, event) = self.take()?;
+        self.stats.delivered += 1;
+        self.stats.waited += pushed.elapsed();
+        Some(event)
+    }
+
+    /// Removes the oldest pending event, and when it was pushed, without counting it delivered.
+    fn take(&mut self) -> Option<(Instant, T)> {
+        if self.is_empty() {
+            return None;
+        }
+        let entry = self.buffer[self.head].take();
+        self.head = (self.head + 1) % self.buffer.len();
+        self.len -= 1;
+        entry
+    }
+
+    /// Returns the pending events, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..self.len).map(|i| {
+            let (_, event) = self.buffer[(self.head + i) % self.buffer.len()]
+                .as_ref()
+                .unwrap();
+            event
+        })
+    }
+
+    /// Returns the pending events, oldest first, so they can be changed in place.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let (wrapped, head) = self.buffer.split_at_mut(self.head);
+        let tail_len = self.len.saturating_sub(head.len());
+        head.iter_mut()
+            .chain(&mut wrapped[..tail_len])
+            .take(self.len)
+            .map(|entry| &mut entry.as_mut().unwrap().1)
+    }
+
+    /// Doubles the buffer, moving the pending events to its start.
+    fn grow(&mut self) {
+        let capacity = self.buffer.len() * 2;
+        let mut buffer = Vec::with_capacity(capacity);
+        buffer.extend(iter::from_fn(|| self.take()).map(Some));
+        self.len = buffer.len();
+        self.head = 0;
+        buffer.resize_with(capacity, || None);
+        self.buffer = buffer;
+    }
+}
+
+impl<T, const MAX: usize> Default for EventQueue<T, MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How long until a scheduled event is due.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delay {
+    /// Due once this much time has passed.
+    Time(Duration),
+
+    /// Due on this many updates from now.
+    Frames(u64),
+}
+
+/// Events scheduled for later, delivered to an [`EventQueue`] once due.
+pub struct Timers<T> {
+    now: Duration,
+    frame: u64,
+    next: u64,
+    by_time: BinaryHeap<Timer<Duration, T>>,
+    by_frame: BinaryHeap<Timer<u64, T>>,
+}
+
+impl<T> Timers<T> {
+    /// Creates an empty set of timers.
+    pub fn new() -> Self {
+        Self {
+            now: Duration::ZERO,
+            frame: 0,
+            next: 0,
+            by_time: BinaryHeap::new(),
+            by_frame: BinaryHeap::new(),
+        }
+    }
+
+    /// Returns the number of events that aren't due yet.
+    pub fn len(&self) -> usize {
+        self.by_time.len() + self.by_frame.len()
+    }
+
+    /// Returns whether no events are scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Schedules an event to be delivered after a delay.
+    pub fn schedule(&mut self, delay: Delay, event: T) {
+        let order = self.next;
+        self.next += 1;
+        match delay {
+            Delay::Time(delay) => self.by_time.push(Timer {
+                due: self.now + delay,
+                order,
+                event,
+            }),
+            Delay::Frames(frames) => self.by_frame.push(Timer {
+                due: self.frame + frames,
+                order,
+                event,
+            }),
+        }
+    }
+
+    /// Advances by one update of `elapsed`, pushing every event now due to `queue`.
+    ///
+    /// Events are pushed in the order they're due (ties in the order they were scheduled); events
+    /// delayed by time are pushed before those delayed by frames. An event that `queue` returns
+    /// as [`QueueFull`] stays scheduled, and is pushed again next update.
+    pub fn update<const MAX: usize>(&mut self, elapsed: Duration, queue: &mut EventQueue<T, MAX>) {
+        self.now += elapsed;
+        self.frame += 1;
+        Self::deliver(&mut self.by_time, self.now, queue);
+        Self::deliver(&mut self.by_frame, self.frame, queue);
+    }
+
+    fn deliver<K: Ord, const MAX: usize>(
+        timers: &mut BinaryHeap<Timer<K, T>>,
+        now: K,
+        queue: &mut EventQueue<T, MAX>,
+    ) {
+        while timers.peek().is_some_and(|timer| timer.due <= now) {
+            let Timer { due, order, event } = timers.pop().unwrap();
+            if let Err(QueueFull(event)) = queue.push(event) {
+                timers.push(Timer { due, order, event });
+                return;
+            }
+        }
+    }
+}
+
+impl<T> Default for Timers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An event, ordered so that the earliest due (then earliest scheduled) is the greatest.
+struct Timer<K, T> {
+    due: K,
+    order: u64,
+    event: T,
+}
+
+impl<K: Ord, T> Ord for Timer<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&other.due, other.order).cmp(&(&self.due, self.order))
+    }
+}
+
+impl<K: Ord, T> PartialOrd for Timer<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> PartialEq for Timer<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, T> Eq for Timer<K, T> {}
+
+/// A ring of the last `MAX` events, read by any number of [`Cursor`]s.
+///
+/// Pushing never waits for readers; once the ring is full, the oldest event is overwritten, and a
+/// reader that hasn't read it yet misses it (see [`BroadcastQueue::missed`]).
+pub struct BroadcastQueue<T, const MAX: usize> {
+    buffer: [Option<T>; MAX],
+    written: u64,
+}
+
+/// Where one reader of a [`BroadcastQueue`] is up to.
+#[derive(Clone, Copy, Debug)]
+pub struct Cursor {
+    next: u64,
+}
+
+impl<T, const MAX: usize> BroadcastQueue<T, MAX> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self {
+            buffer: array::from_fn(|_| None),
+            written: 0,
+        }
+    }
+
+    /// Returns a cursor that reads every event pushed from now on.
+    pub fn subscribe(&self) -> Cursor {
+        Cursor { next: self.written }
+    }
+
+    /// Adds an event, overwriting the oldest if the ring is full.
+    pub fn push(&mut self, event: T) {
+        self.buffer[(self.written % MAX as u64) as usize] = Some(event);
+        self.written += 1;
+    }
+
+    /// Returns how many events were overwritten before `cursor` read them.
+    pub fn missed(&self, cursor: &Cursor) -> u64 {
+        self.oldest().saturating_sub(cursor.next)
+    }
+
+    /// Returns the events `cursor` hasn't read yet (and can still be read), oldest first.
+    pub fn read<'a>(&'a self, cursor: &mut Cursor) -> impl Iterator<Item = &'a T> {
+        let start = cursor.next.max(self.oldest());
+        cursor.next = self.written;
+        (start..self.written).map(|i| self.buffer[(i % MAX as u64) as usize].as_ref().unwrap())
+    }
+
+    fn oldest(&self) -> u64 {
+        self.written.saturating_sub(MAX as u64)
+    }
+}
+
+impl<T, const MAX: usize> Default for BroadcastQueue<T, MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_wraps_past_max() {
+        let mut queue = EventQueue::<u32, 3>::new();
+        queue.push(0).unwrap();
+        for event in 1..10 {
+            queue.push(event).unwrap();
+            assert_eq!(queue.pop(), Some(event - 1));
+            assert!(queue.head < 3);
+            assert_eq!(queue.len(), 1);
+        }
+        assert_eq!(queue.pop(), Some(9));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn partial_drain_then_refill() {
+        let mut queue = EventQueue::<u32, 3>::new();
+        for event in 1..=3 {
+            queue.push(event).unwrap();
+        }
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+
+        // The tail wraps to the start of the buffer, behind the event still pending.
+        queue.push(4).unwrap();
+        queue.push(5).unwrap();
+        assert!(queue.is_full());
+        assert_eq!(queue.iter().collect::<Vec<_>>(), [&3, &4, &5]);
+        for event in queue.iter_mut() {
+            *event *= 10;
+        }
+        assert_eq!(queue.pop(), Some(30));
+        assert_eq!(queue.pop(), Some(40));
+        assert_eq!(queue.pop(), Some(50));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn full_and_empty_both_have_head_at_tail() {
+        let mut queue = EventQueue::<u32, 2>::new();
+        queue.push(1).unwrap();
+        queue.pop();
+
+        // Empty, with the head (and tail) past the start of the buffer.
+        assert_eq!(queue.head, 1);
+        assert!(queue.is_empty());
+        assert!(!queue.is_full());
+        assert_eq!(queue.iter().count(), 0);
+
+        // Full, with the tail back around at the head.
+        queue.push(2).unwrap();
+        queue.push(3).unwrap();
+        assert_eq!((queue.head + queue.len()) % 2, queue.head);
+        assert!(queue.is_full());
+        assert!(!queue.is_empty());
+        assert_eq!(queue.push(4), Err(QueueFull(4)));
+        assert_eq!(queue.iter().collect::<Vec<_>>(), [&2, &3]);
+    }
+
+    #[test]
+    fn drop_oldest_at_the_wrap_point() {
+        let mut queue = EventQueue::<u32, 3>::with_overflow(Overflow::DropOldest);
+        for event in 1..=3 {
+            queue.push(event).unwrap();
+        }
+        queue.pop();
+        queue.push(4).unwrap();
+
+        // Full, with the head at the last slot of the buffer.
+        queue.push(5).unwrap();
+        assert_eq!(queue.head, 2);
+        assert_eq!(queue.iter().collect::<Vec<_>>(), [&3, &4, &5]);
+
+        // Dropping the oldest wraps the head back to the start.
+        queue.push(6).unwrap();
+        assert_eq!(queue.head, 0);
+        assert_eq!(queue.iter().collect::<Vec<_>>(), [&4, &5, &6]);
+        assert_eq!(queue.stats().dropped, 2);
+        assert_eq!(queue.len(), 3);
+    }
+}
@@ -0,0 +1,486 @@
+//! A game loop that updates at a fixed rate, and renders as often as it can.
+//!
+//! Each turn of the loop processes input, catches the game up on as many fixed-size updates as
+//! real time has passed, and renders; rendering is told how far it is between two updates, so it
+//! can interpolate:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use game_patterns_rs::game_loop::{Game, GameLoop, LoopControl};
+//!
+//! #[derive(Default)]
+//! struct Countdown {
+//!     updates: u32,
+//! }
+//!
+//! impl Game for Countdown {
+//!     fn process_input(&mut self, _: &mut LoopControl) {}
+//!
+//!     fn update(&mut self, _: Duration) {
+//!         self.updates += 1;
+//!     }
+//!
+//!     fn render(&mut self, _: f64) {}
+//!
+//!     fn is_running(&self) -> bool {
+//!         self.updates < 3
+//!     }
+//! }
+//!
+//! let mut game = Countdown::default();
+//! GameLoop::new(Duration::from_millis(1)).run(&mut game);
+//! assert_eq!(game.updates, 3);
+//! ```
+//!
+//! The book's simpler loops are available too (see [`LoopStrategy`]), to compare against.
+//!
+//! How a loop actually performs is easier to measure than to guess, so the loop also collects
+//! [`FrameStats`].
+
+use std::{
+    cell::Cell,
+    fmt,
+    rc::Rc,
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What a [`GameLoop`] runs.
+pub trait Game {
+    /// Handles user input since the last call, without blocking; input can also pause the loop.
+    fn process_input(&mut self, control: &mut LoopControl);
+
+    /// Blocks until there is input, then handles it; used instead of [`Game::process_input`] by
+    /// [`LoopStrategy::EventDriven`].
+    ///
+    /// By default, handles whatever input there is without blocking.
+    fn wait_for_input(&mut self, control: &mut LoopControl) {
+        self.process_input(control);
+    }
+
+    /// Advances the game by `dt`: the loop's timestep, unless the loop's strategy is
+    /// [`LoopStrategy::Variable`].
+    fn update(&mut self, dt: Duration);
+
+    /// Draws the game, `alpha` (from `0.0` to `1.0`) of the way from the last update to the next.
+    fn render(&mut self, alpha: f64);
+
+    /// Returns whether the loop should keep running; checked before each turn and after each
+    /// update.
+    fn is_running(&self) -> bool;
+}
+
+/// How a [`GameLoop`] paces updates and renders, from the book's simplest loop to its last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopStrategy {
+    /// One update per frame, as fast as possible: the game runs faster on a faster machine.
+    Simple,
+
+    /// One update per frame, sleeping so that a frame takes at least the timestep: the game runs
+    /// at the right speed, unless frames take longer than the timestep.
+    Capped,
+
+    /// One update per frame, of however long the last frame took: the right speed on any machine,
+    /// but the simulation is no longer deterministic (and can become unstable when frames are slow).
+    Variable,
+
+    /// As many updates of the timestep as real time has passed, rendering as often as possible
+    /// in between: deterministic, and at the right speed.
+    #[default]
+    Fixed,
+
+    /// Waits for input (see [`Game::wait_for_input`]), then updates once and renders: for
+    /// turn-based games, where nothing happens until the player acts.
+    EventDriven,
+}
+
+/// Pauses, resumes, and single-steps a [`GameLoop`]; given to [`Game::process_input`].
+///
+/// While paused, the game keeps rendering (so a debugger or pause menu can still be drawn), but
+/// only updates when stepped.
+#[derive(Clone, Debug, Default)]
+pub struct LoopControl {
+    paused: bool,
+    steps: u32,
+}
+
+impl LoopControl {
+    /// Stops updating the game, from this frame on.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Updates the game again, continuing from the moment it was paused.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.steps = 0;
+    }
+
+    /// Updates the game once (by the loop's timestep) this frame; only while paused.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.steps += 1;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+/// Where a [`GameLoop`] gets the time from, so that tests (or platforms without [`Instant`], like
+/// browsers) can provide their own.
+pub trait Clock {
+    /// Returns the time since some fixed moment, which is up to the clock; only the differences
+    /// between times are used.
+    fn now(&self) -> Duration;
+
+    /// Waits for `duration`; used by [`LoopStrategy::Capped`].
+    fn sleep(&self, duration: Duration);
+}
+
+/// The real time, since the first time any `SystemClock` was asked for it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        EPOCH.get_or_init(Instant::now).elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// A clock that only moves when told to (or when slept on), for deterministic tests.
+///
+/// Clones share the same time, so a test (or the game itself) can keep one to advance the time
+/// while the loop has another:
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use game_patterns_rs::game_loop::{Game, GameLoop, LoopControl, ManualClock};
+///
+/// struct Counter {
+///     clock: ManualClock,
+///     frames: u32,
+///     updates: u32,
+/// }
+///
+/// impl Game for Counter {
+///     fn process_input(&mut self, _: &mut LoopControl) {}
+///
+///     fn update(&mut self, _: Duration) {
+///         self.updates += 1;
+///     }
+///
+///     // Every frame takes exactly 20ms.
+///     fn render(&mut self, _: f64) {
+///         self.frames += 1;
+///         self.clock.advance(Duration::from_millis(20));
+///     }
+///
+///     fn is_running(&self) -> bool {
+///         self.frames < 4
+///     }
+/// }
+///
+/// let clock = ManualClock::new();
+/// let mut game = Counter { clock: clock.clone(), frames: 0, updates: 0 };
+/// GameLoop::new(Duration::from_millis(10)).clock(clock).run(&mut game);
+///
+/// // The first frame starts at time zero; each one after catches up on 20ms.
+/// assert_eq!(game.updates, 6);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    /// Creates a clock at time zero.
+    pub fn new() -> Self {
+        ManualClock {
+            now: Rc::new(Cell::new(Duration::ZERO)),
+        }
+    }
+
+    /// Moves the time forward.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+type Report = dyn FnMut(&FrameStats);
+
+/// Runs a [`Game`], with a fixed timestep unless another [`LoopStrategy`] is chosen.
+pub struct GameLoop {
+    timestep: Duration,
+    strategy: LoopStrategy,
+    control: LoopControl,
+    report: Option<Box<Report>>,
+    clock: Box<dyn Clock>,
+    previous: Option<Duration>,
+    lag: Duration,
+    total: FrameStats,
+    second: FrameStats,
+}
+
+impl GameLoop {
+    /// Creates a loop that updates once per `timestep` of real time.
+    ///
+    /// # Panics
+    ///
+    /// If `timestep` is zero.
+    pub fn new(timestep: Duration) -> Self {
+        assert!(!timestep.is_zero(), "Timestep must not be zero");
+        GameLoop {
+            timestep,
+            strategy: LoopStrategy::default(),
+            control: LoopControl::default(),
+            report: None,
+            clock: Box::new(SystemClock),
+            previous: None,
+            lag: Duration::ZERO,
+            total: FrameStats::default(),
+            second: FrameStats::default(),
+        }
+    }
+
+    /// Uses the given strategy instead of [`LoopStrategy::Fixed`].
+    pub fn strategy(mut self, strategy: LoopStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Takes the time from `clock` instead of the [`SystemClock`].
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Calls `report` once a second (of real time) with the stats for that second.
+    pub fn on_report(mut self, report: impl FnMut(&FrameStats) + 'static) -> Self {
+        self.report = Some(Box::new(report));
+        self
+    }
+
+    /// Returns how much time each update simulates.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Runs the game until [`Game::is_running`] returns `false`, returning stats for the whole run.
+    pub fn run(&mut self, game: &mut impl Game) -> FrameStats {
+        self.reset();
+        while game.is_running() && self.frame(game) {}
+        std::mem::take(&mut self.total)
+    }
+
+    /// Runs a single turn of the loop (one frame), returning whether the game is still running.
+    fn frame(&mut self, game: &mut impl Game) -> bool {
+        let current = self.clock.now();
+
+        let elapsed = self
+            .previous
+            .map_or(Duration::ZERO, |previous| current.saturating_sub(previous));
+        self.previous = Some(current);
+
+        if self.strategy == LoopStrategy::EventDriven {
+            game.wait_for_input(&mut self.control);
+        } else {
+            game.process_input(&mut self.control);
+        }
+        let alpha = if self.control.paused {
+            // Time spent paused is never caught up on; the game carries on from where it was.
+            for _ in 0..std::mem::take(&mut self.control.steps) {
+                if game.is_running() {
+                    game.update(self.timestep);
+                }
+            }
+            match self.strategy {
+                LoopStrategy::Fixed => self.lag.as_secs_f64() / self.timestep.as_secs_f64(),
+                _ => 0.0,
+            }
+        } else {
+            match self.strategy {
+                LoopStrategy::Simple | LoopStrategy::Capped | LoopStrategy::EventDriven => {
+                    game.update(self.timestep);
+                    0.0
+                }
+                LoopStrategy::Variable => {
+                    game.update(elapsed);
+                    0.0
+                }
+                LoopStrategy::Fixed => {
+                    self.lag += elapsed;
+                    while self.lag >= self.timestep && game.is_running() {
+                        game.update(self.timestep);
+                        self.lag -= self.timestep;
+                    }
+                    self.lag.as_secs_f64() / self.timestep.as_secs_f64()
+                }
+            }
+        };
+        if !game.is_running() {
+            return false;
+        }
+        game.render(alpha);
+        if self.strategy == LoopStrategy::Capped {
+            let spent = self.clock.now().saturating_sub(current);
+            if let Some(remaining) = self.timestep.checked_sub(spent) {
+                self.clock.sleep(remaining);
+            }
+        }
+
+        // Each turn of the loop is a frame, measured from the start of the previous one.
+        self.total.record(elapsed);
+        self.second.record(elapsed);
+        if self.second.total >= Duration::from_secs(1) {
+            if let Some(report) = &mut self.report {
+                report(&self.second);
+            }
+            self.second = FrameStats::default();
+        }
+        game.is_running()
+    }
+
+    /// Returns the stats for every frame so far (of this run, if it was started with
+    /// [`GameLoop::run`]).
+    pub fn stats(&self) -> &FrameStats {
+        &self.total
+    }
+
+    fn reset(&mut self) {
+        self.previous = Some(self.clock.now());
+        self.lag = Duration::ZERO;
+        self.total = FrameStats::default();
+        self.second = FrameStats::default();
+    }
+}
+
+/// How long frames took: the current and average frame rate, the worst frame, and a histogram.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use game_patterns_rs::game_loop::FrameStats;
+///
+/// let mut stats = FrameStats::default();
+/// stats.record(Duration::from_millis(10));
+/// stats.record(Duration::from_millis(30));
+/// assert_eq!(stats.frames(), 2);
+/// assert_eq!(stats.average_fps(), 50.0);
+/// assert_eq!(stats.worst(), Duration::from_millis(30));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    frames: u32,
+    total: Duration,
+    last: Duration,
+    worst: Duration,
+    histogram: [u32; FrameStats::BUCKETS.len()],
+}
+
+impl FrameStats {
+    /// The upper bound of each histogram bucket (besides the last, which has none).
+    const BUCKETS: [Duration; 6] = [
+        Duration::from_millis(1),
+        Duration::from_millis(4),
+        Duration::from_millis(8),
+        Duration::from_micros(16_667),
+        Duration::from_micros(33_333),
+        Duration::MAX,
+    ];
+
+    /// Adds a frame that took `frame_time`.
+    pub fn record(&mut self, frame_time: Duration) {
+        self.frames += 1;
+        self.total += frame_time;
+        self.last = frame_time;
+        self.worst = self.worst.max(frame_time);
+        let bucket = Self::BUCKETS
+            .iter()
+            .position(|&bound| frame_time < bound)
+            .unwrap_or(Self::BUCKETS.len() - 1);
+        self.histogram[bucket] += 1;
+    }
+
+    /// Returns the number of frames recorded.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Returns the frame rate implied by the last frame alone.
+    pub fn fps(&self) -> f64 {
+        rate(self.frames.min(1), self.last)
+    }
+
+    /// Returns the frame rate over every recorded frame.
+    pub fn average_fps(&self) -> f64 {
+        rate(self.frames, self.total)
+    }
+
+    /// Returns how long the slowest frame took.
+    pub fn worst(&self) -> Duration {
+        self.worst
+    }
+
+    /// Returns how many frames took less than each duration (and more than the one before it);
+    /// the last bucket, `Duration::MAX`, counts the rest.
+    pub fn histogram(&self) -> impl Iterator<Item = (Duration, u32)> + '_ {
+        Self::BUCKETS.into_iter().zip(self.histogram)
+    }
+}
+
+fn rate(frames: u32, time: Duration) -> f64 {
+    if time.is_zero() {
+        0.0
+    } else {
+        f64::from(frames) / time.as_secs_f64()
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:.1} fps (average {:.1}), worst frame {:.2?}",
+            self.fps(),
+            self.average_fps(),
+            self.worst
+        )?;
+        let most = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (bound, count) in self.histogram() {
+            let label = if bound == Duration::MAX {
+                "slower".to_string()
+            } else {
+                format!("< {bound:.1?}")
+            };
+            let bar = "#".repeat((count as usize * 20).div_ceil(most as usize));
+            writeln!(f, "{label:>10} | {bar:<20} {count}")?;
+        }
+        Ok(())
+    }
+}
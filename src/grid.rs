@@ -0,0 +1,214 @@
+//! The spatial partition chapter's fixed grid: space is divided into equal square cells, each
+//! holding the units standing in it.
+//!
+//! Each cell's units are a doubly linked list threaded through the units themselves (by index), so
+//! moving a unit between cells never allocates. Units only fight units in their own cell or a
+//! neighboring one, so [`Grid::handle_melee`] makes far fewer comparisons than checking every pair:
+//!
+//! ```
+//! use game_patterns_rs::grid::Grid;
+//!
+//! let mut grid = Grid::new(10, 20.0);
+//! grid.add([10.0, 10.0]);
+//! grid.add([12.0, 11.0]);
+//! let dragon = grid.add([190.0, 190.0]);
+//!
+//! let mut fights = Vec::new();
+//! grid.handle_melee(5.0, |a, b| fights.push((a, b)));
+//! assert_eq!(fights.len(), 1);
+//!
+//! // Units are moved through the grid, so it can keep them in the right cell.
+//! grid.move_unit(dragon, [11.0, 12.0]);
+//! let mut fights = 0;
+//! grid.handle_melee(5.0, |_, _| fights += 1);
+//! assert_eq!(fights, 3);
+//! ```
+//!
+//! A fixed grid does badly once units crowd into a few cells; see [`crate::quadtree`] for a
+//! partition that adapts.
+
+use crate::quadtree::Rect;
+
+/// Identifies a unit on a [`Grid`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnitId(usize);
+
+impl UnitId {
+    /// Returns the unit's index, in the order units were added; useful for keeping other data
+    /// about units alongside the grid.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A unit's position, and its neighbors in its cell's list.
+#[derive(Clone, Copy, Debug)]
+struct Unit {
+    position: [f32; 2],
+    prev: Option<UnitId>,
+    next: Option<UnitId>,
+}
+
+/// Square space, split into a fixed number of equal cells.
+pub struct Grid {
+    cells: usize,
+    cell_size: f32,
+    units: Vec<Unit>,
+
+    /// The first unit in each cell's list, by row.
+    heads: Vec<Option<UnitId>>,
+}
+
+impl Grid {
+    /// Creates an empty grid of `cells` by `cells` cells, each `cell_size` across.
+    ///
+    /// Positions outside the grid are clamped into its edge cells.
+    ///
+    /// # Panics
+    ///
+    /// If there are no cells, or they have no size.
+    pub fn new(cells: usize, cell_size: f32) -> Self {
+        assert!(cells > 0 && cell_size > 0.0, "grid must have cells");
+        Self {
+            cells,
+            cell_size,
+            units: Vec::new(),
+            heads: vec![None; cells * cells],
+        }
+    }
+
+    /// Adds a unit, returning its id.
+    pub fn add(&mut self, position: [f32; 2]) -> UnitId {
+        let unit = UnitId(self.units.len());
+        self.units.push(Unit {
+            position,
+            prev: None,
+            next: None,
+        });
+        self.link(unit);
+        unit
+    }
+
+    /// Returns where a unit is.
+    pub fn position(&self, unit: UnitId) -> [f32; 2] {
+        self.units[unit.0].position
+    }
+
+    /// Returns every unit and where it is, in the order they were added.
+    pub fn units(&self) -> impl Iterator<Item = (UnitId, [f32; 2])> + '_ {
+        (self.units.iter().enumerate()).map(|(index, unit)| (UnitId(index), unit.position))
+    }
+
+    /// Returns the number of units.
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    /// Returns whether there are no units.
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// Moves a unit, switching it to the list of its new cell if it changed cells.
+    pub fn move_unit(&mut self, unit: UnitId, position: [f32; 2]) {
+        let old_cell = self.cell(self.units[unit.0].position);
+        self.units[unit.0].position = position;
+        if self.cell(position) != old_cell {
+            self.unlink(unit, old_cell);
+            self.link(unit);
+        }
+    }
+
+    /// Calls `fight` with every pair of units less than `distance` apart, returning how many pairs
+    /// were checked to find them.
+    ///
+    /// Only units in the same cell, and in half of the neighboring cells (the other half compare
+    /// with this one), are checked.
+    ///
+    /// # Panics
+    ///
+    /// If `distance` is more than a cell, as units in range could then be further than a
+    /// neighboring cell apart.
+    pub fn handle_melee(&self, distance: f32, mut fight: impl FnMut(UnitId, UnitId)) -> usize {
+        assert!(distance <= self.cell_size, "distance must be within a cell");
+        let mut checks = 0;
+        for cy in 0..self.cells {
+            for cx in 0..self.cells {
+                for unit in self.cell_units((cx, cy)) {
+                    let [x, y] = self.units[unit.0].position;
+
+                    // The rest of this cell's list; earlier units already compared with this one.
+                    let rest = self.list(self.units[unit.0].next);
+                    let neighbors = [(-1, -1), (0, -1), (1, -1), (-1, 0)]
+                        .into_iter()
+                        .filter_map(|(dx, dy)| {
+                            let nx = cx.checked_add_signed(dx).filter(|&nx| nx < self.cells)?;
+                            let ny = cy.checked_add_signed(dy)?;
+                            Some((nx, ny))
+                        })
+                        .flat_map(|cell| self.cell_units(cell));
+                    for other in rest.chain(neighbors) {
+                        checks += 1;
+                        let [ox, oy] = self.units[other.0].position;
+                        let (dx, dy) = (x - ox, y - oy);
+                        if dx * dx + dy * dy < distance * distance {
+                            fight(unit, other);
+                        }
+                    }
+                }
+            }
+        }
+        checks
+    }
+
+    /// Returns every unit inside `area`, looking through each cell it overlaps.
+    pub fn query(&self, area: Rect) -> impl Iterator<Item = (UnitId, [f32; 2])> + '_ {
+        let (min_x, min_y) = self.cell([area.x, area.y]);
+        let (max_x, max_y) = self.cell([area.x + area.width, area.y + area.height]);
+        (min_y..=max_y)
+            .flat_map(move |cy| (min_x..=max_x).map(move |cx| (cx, cy)))
+            .flat_map(|cell| self.cell_units(cell))
+            .map(|unit| (unit, self.units[unit.0].position))
+            .filter(move |&(_, position)| area.contains(position))
+    }
+
+    /// Returns the cell a position is in.
+    fn cell(&self, [x, y]: [f32; 2]) -> (usize, usize) {
+        let cell = |v: f32| ((v / self.cell_size).max(0.0) as usize).min(self.cells - 1);
+        (cell(x), cell(y))
+    }
+
+    /// Returns the units in a cell.
+    fn cell_units(&self, (cx, cy): (usize, usize)) -> impl Iterator<Item = UnitId> + '_ {
+        self.list(self.heads[cy * self.cells + cx])
+    }
+
+    /// Returns the units in a list, starting from `first`.
+    fn list(&self, first: Option<UnitId>) -> impl Iterator<Item = UnitId> + '_ {
+        std::iter::successors(first, |unit| self.units[unit.0].next)
+    }
+
+    /// Adds a unit to the front of the list of the cell it's in.
+    fn link(&mut self, unit: UnitId) {
+        let (cx, cy) = self.cell(self.units[unit.0].position);
+        let head = &mut self.heads[cy * self.cells + cx];
+        let next = head.replace(unit);
+        self.units[unit.0].prev = None;
+        self.units[unit.0].next = next;
+        if let Some(next) = next {
+            self.units[next.0].prev = Some(unit);
+        }
+    }
+
+    /// Removes a unit from the list of the cell it was in.
+    fn unlink(&mut self, unit: UnitId, (cx, cy): (usize, usize)) {
+        let Unit { prev, next, .. } = self.units[unit.0];
+        match prev {
+            Some(prev) => self.units[prev.0].next = next,
+            None => self.heads[cy * self.cells + cx] = next,
+        }
+        if let Some(next) = next {
+            self.units[next.0].prev = prev;
+        }
+    }
+}
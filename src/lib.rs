@@ -0,0 +1,23 @@
+//! Reusable building blocks shared by the examples (see `examples/`).
+//!
+//! Each example is meant to be read on its own, so only code that is useful to several examples (or
+//! to users of this crate) lives here.
+
+pub mod ai;
+pub mod arena;
+pub mod bytecode;
+pub mod component;
+pub mod double_buffer;
+pub mod ecs;
+pub mod event_bus;
+pub mod event_queue;
+pub mod game_loop;
+pub mod grid;
+pub mod particle;
+pub mod pool;
+pub mod prototype;
+pub mod quadtree;
+pub mod schedule;
+pub mod soa;
+pub mod symbol;
+pub mod transform;
@@ -0,0 +1,583 @@
+//! Particles, stored two ways: as an array of structs, and as a struct of arrays.
+//!
+//! Updating a particle only reads and writes its position, velocity and lifetime, but each
+//! [`Particle`] also carries what is needed to draw it. In a [`ParticleSystem`] (an array of
+//! structs), the update loop pulls all of that through the cache anyway; in a
+//! [`SoaParticleSystem`] (a struct of arrays), each field is its own array, and the update only
+//! touches the arrays it needs (declared with [`soa!`](crate::soa)). `cargo bench --bench
+//! particles` measures the difference.
+//!
+//! ```
+//! use game_patterns_rs::particle::{Particle, ParticleSystem, SoaParticleSystem};
+//!
+//! let particle = Particle {
+//!     position: [0.0, 0.0],
+//!     velocity: [1.0, 2.0],
+//!     lifetime: 1.0,
+//!     ..Particle::default()
+//! };
+//! let mut aos = ParticleSystem::new();
+//! let mut soa = SoaParticleSystem::new();
+//! let handle = aos.spawn(particle).unwrap();
+//! soa.spawn(particle);
+//!
+//! aos.update(0.5);
+//! soa.update(0.5);
+//! assert_eq!(aos.get(handle).unwrap().position, [0.5, 1.0]);
+//! assert_eq!(soa.get(0).position, [0.5, 1.0]);
+//!
+//! aos.update(0.5);
+//! soa.update(0.5);
+//! assert!(aos.is_empty() && soa.is_empty());
+//! assert_eq!(aos.get(handle), None);
+//! ```
+//!
+//! An [`Emitter`] spawns particles over time, rather than one [`ParticleSystem::spawn`] at a time.
+
+use std::ops::Range;
+
+/// A particle: what its update needs, and what drawing it needs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Particle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+
+    /// Seconds left before the particle disappears.
+    pub lifetime: f32,
+
+    /// Only read when drawing, never when updating.
+    pub color: [u8; 4],
+    pub size: f32,
+    pub texture: u32,
+    pub rotation: f32,
+}
+
+/// A pool of particles stored as an array of structs; active particles are kept at the front.
+///
+/// Keeping them packed means particles move whenever another is deactivated, so callers refer to
+/// particles by [`ParticleHandle`] rather than by index; a handle goes stale (and finds nothing)
+/// once its particle is deactivated.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    active_len: usize,
+
+    /// For each particle, the slot its handle refers to; for each slot, where its particle is.
+    owners: Vec<usize>,
+    slots: Vec<Slot>,
+}
+
+/// Refers to a particle in a [`ParticleSystem`], wherever it is moved to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParticleHandle {
+    slot: usize,
+    generation: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Slot {
+    index: usize,
+
+    /// Incremented each time the slot's particle is deactivated, so old handles go stale.
+    generation: u32,
+}
+
+impl ParticleSystem {
+    pub const MAX_PARTICLES: usize = 100_000;
+
+    pub fn new() -> Self {
+        Self {
+            particles: vec![Particle::default(); Self::MAX_PARTICLES],
+            active_len: 0,
+            owners: (0..Self::MAX_PARTICLES).collect(),
+            slots: (0..Self::MAX_PARTICLES)
+                .map(|index| Slot {
+                    index,
+                    generation: 0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the number of active particles.
+    pub fn len(&self) -> usize {
+        self.active_len
+    }
+
+    /// Returns whether no particles are active.
+    pub fn is_empty(&self) -> bool {
+        self.active_len == 0
+    }
+
+    /// Returns the active particles.
+    pub fn active(&self) -> &[Particle] {
+        &self.particles[..self.active_len]
+    }
+
+    /// Activates a particle, returning `None` if every particle is already active.
+    pub fn spawn(&mut self, particle: Particle) -> Option<ParticleHandle> {
+        if self.active_len == Self::MAX_PARTICLES {
+            return None;
+        }
+        let index = self.active_len;
+        self.particles[index] = particle;
+        self.active_len += 1;
+
+        let slot = self.owners[index];
+        Some(ParticleHandle {
+            slot,
+            generation: self.slots[slot].generation,
+        })
+    }
+
+    /// Returns the particle, if it is still active.
+    pub fn get(&self, handle: ParticleHandle) -> Option<&Particle> {
+        let index = self.index(handle)?;
+        Some(&self.particles[index])
+    }
+
+    /// Returns the particle, if it is still active.
+    pub fn get_mut(&mut self, handle: ParticleHandle) -> Option<&mut Particle> {
+        let index = self.index(handle)?;
+        Some(&mut self.particles[index])
+    }
+
+    /// Deactivates the particle, returning `false` if it already was.
+    pub fn despawn(&mut self, handle: ParticleHandle) -> bool {
+        let Some(index) = self.index(handle) else {
+            return false;
+        };
+        self.deactivate(index);
+        true
+    }
+
+    /// Moves every active particle, and deactivates those whose lifetime is over.
+    pub fn update(&mut self, dt: f32) {
+        let mut i = 0;
+        while i < self.active_len {
+            let particle = &mut self.particles[i];
+            particle.position[0] += particle.velocity[0] * dt;
+            particle.position[1] += particle.velocity[1] * dt;
+            particle.lifetime -= dt;
+            if particle.lifetime <= 0.0 {
+                // The last active particle takes its place, so check index `i` again.
+                self.deactivate(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn index(&self, handle: ParticleHandle) -> Option<usize> {
+        let slot = self.slots[handle.slot];
+        (slot.generation == handle.generation && slot.index < self.active_len).then_some(slot.index)
+    }
+
+    /// Deactivates the particle at `index`, moving the last active one into its place.
+    fn deactivate(&mut self, index: usize) {
+        self.active_len -= 1;
+        let last = self.active_len;
+        self.particles.swap(index, last);
+        self.owners.swap(index, last);
+
+        // The moved particle's slot follows it; the deactivated one's handles go stale.
+        self.slots[self.owners[index]].index = index;
+        let deactivated = &mut self.slots[self.owners[last]];
+        deactivated.index = last;
+        deactivated.generation += 1;
+    }
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+crate::soa! {
+    /// The fields of every particle, one array per field.
+    struct Particles for Particle {
+        position: [f32; 2],
+        velocity: [f32; 2],
+        lifetime: f32,
+        color: [u8; 4],
+        size: f32,
+        texture: u32,
+        rotation: f32,
+    }
+}
+
+/// The same pool of particles, stored as a struct of arrays: one array per field.
+pub struct SoaParticleSystem {
+    particles: Particles,
+}
+
+impl SoaParticleSystem {
+    pub fn new() -> Self {
+        Self {
+            particles: Particles::with_capacity(ParticleSystem::MAX_PARTICLES),
+        }
+    }
+
+    /// Returns the number of active particles.
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Returns whether no particles are active.
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Returns a copy of the active particle at `index`, gathered from every array.
+    pub fn get(&self, index: usize) -> Particle {
+        self.particles.get(index)
+    }
+
+    /// Activates a particle, returning `false` if every particle is already active.
+    pub fn spawn(&mut self, particle: Particle) -> bool {
+        if self.len() == ParticleSystem::MAX_PARTICLES {
+            return false;
+        }
+        self.particles.push(particle);
+        true
+    }
+
+    /// Moves every active particle, and deactivates those whose lifetime is over.
+    pub fn update(&mut self, dt: f32) {
+        let particles = &mut self.particles;
+        let expired = update_arrays(
+            &mut particles.position,
+            &particles.velocity,
+            &mut particles.lifetime,
+            dt,
+        );
+        if expired {
+            self.remove_expired();
+        }
+    }
+
+    /// Deactivates the particle at `index`, moving the last active one into its place.
+    pub fn deactivate(&mut self, index: usize) {
+        self.particles.swap_remove(index);
+    }
+
+    fn remove_expired(&mut self) {
+        let particles = &mut self.particles;
+        let mut i = 0;
+        while i < particles.len() {
+            if particles.lifetime[i] <= 0.0 {
+                particles.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Moves particles and counts down their lifetimes, returning whether any expired.
+fn update_arrays(
+    positions: &mut [[f32; 2]],
+    velocities: &[[f32; 2]],
+    lifetimes: &mut [f32],
+    dt: f32,
+) -> bool {
+    // Each loop reads only the arrays it needs, front to back.
+    for (position, velocity) in positions.iter_mut().zip(velocities) {
+        position[0] += velocity[0] * dt;
+        position[1] += velocity[1] * dt;
+    }
+    let mut expired = false;
+    for lifetime in lifetimes {
+        *lifetime -= dt;
+        expired |= *lifetime <= 0.0;
+    }
+    expired
+}
+
+#[cfg(feature = "parallel")]
+impl SoaParticleSystem {
+    /// Like [`SoaParticleSystem::update`], but splits the particles into a chunk per available
+    /// thread, and updates each chunk on its own thread.
+    ///
+    /// Each array is split at the same places, so a thread's chunks are whole particles, and the
+    /// threads never touch the same memory. Starting threads isn't free, so this only pays off
+    /// for many particles; `cargo bench --bench parallel --features parallel` shows where.
+    ///
+    /// ```
+    /// use game_patterns_rs::particle::{Particle, SoaParticleSystem};
+    ///
+    /// let mut particles = SoaParticleSystem::new();
+    /// for i in 0..1000 {
+    ///     particles.spawn(Particle {
+    ///         velocity: [1.0, 0.0],
+    ///         lifetime: (i % 2) as f32,
+    ///         ..Particle::default()
+    ///     });
+    /// }
+    /// particles.par_update_on(0.5, 4);
+    /// assert_eq!(particles.len(), 500);
+    /// assert_eq!(particles.get(0).position, [0.5, 0.0]);
+    /// ```
+    pub fn par_update(&mut self, dt: f32) {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        self.par_update_on(dt, threads);
+    }
+
+    /// Like [`SoaParticleSystem::par_update`], on `threads` threads (or one, if zero).
+    pub fn par_update_on(&mut self, dt: f32, threads: usize) {
+        let particles = &mut self.particles;
+        let chunk = particles.len().div_ceil(threads.max(1)).max(1);
+        let expired = std::thread::scope(|scope| {
+            let updates: Vec<_> = particles
+                .position
+                .chunks_mut(chunk)
+                .zip(particles.velocity.chunks(chunk))
+                .zip(particles.lifetime.chunks_mut(chunk))
+                .map(|((positions, velocities), lifetimes)| {
+                    scope.spawn(move || update_arrays(positions, velocities, lifetimes, dt))
+                })
+                .collect();
+            updates
+                .into_iter()
+                .fold(false, |expired, update| update.join().unwrap() | expired)
+        });
+        if expired {
+            self.remove_expired();
+        }
+    }
+}
+
+/// How many floats [`SoaParticleSystem::update_simd`] updates at once; enough for the compiler to
+/// unroll across several vector registers.
+#[cfg(feature = "simd")]
+const LANES: usize = 16;
+
+#[cfg(feature = "simd")]
+impl SoaParticleSystem {
+    /// Like [`SoaParticleSystem::update`], but explicitly works on [`LANES`] floats at a time.
+    ///
+    /// `std::simd` is not yet stable, so this splits each array into fixed-size chunks instead,
+    /// which the compiler turns into vector instructions. The scalar loop over a struct of arrays
+    /// is simple enough that the optimizer usually vectorizes it too, so expect the two to be
+    /// close, with the scalar loop sometimes ahead (see `cargo bench --bench particles --features
+    /// simd`); chunking makes vectorizing something the code asks for, rather than something the
+    /// optimizer may or may not notice in a more complicated update.
+    ///
+    /// ```
+    /// use game_patterns_rs::particle::{Particle, SoaParticleSystem};
+    ///
+    /// let mut scalar = SoaParticleSystem::new();
+    /// let mut simd = SoaParticleSystem::new();
+    /// for i in 0..20 {
+    ///     let particle = Particle {
+    ///         velocity: [1.0, i as f32],
+    ///         lifetime: i as f32 / 10.0,
+    ///         ..Particle::default()
+    ///     };
+    ///     scalar.spawn(particle);
+    ///     simd.spawn(particle);
+    /// }
+    ///
+    /// scalar.update(0.5);
+    /// simd.update_simd(0.5);
+    /// assert_eq!(simd.len(), 14);
+    /// for i in 0..simd.len() {
+    ///     assert_eq!(scalar.get(i), simd.get(i));
+    /// }
+    /// ```
+    pub fn update_simd(&mut self, dt: f32) {
+        let particles = &mut self.particles;
+
+        // Positions and velocities are pairs, so lay them out flat: x, y, x, y, ...
+        let positions = particles.position.as_flattened_mut();
+        let velocities = particles.velocity.as_flattened();
+        let mut position_chunks = positions.chunks_exact_mut(LANES);
+        let mut velocity_chunks = velocities.chunks_exact(LANES);
+        for (position, velocity) in (&mut position_chunks).zip(&mut velocity_chunks) {
+            // Load each chunk into registers, work on it there, and store the result, as SIMD
+            // code would; the optimizer needn't then worry about the two arrays overlapping.
+            let mut lanes: [f32; LANES] = position.try_into().unwrap();
+            let velocity: [f32; LANES] = velocity.try_into().unwrap();
+            for lane in 0..LANES {
+                lanes[lane] += velocity[lane] * dt;
+            }
+            position.copy_from_slice(&lanes);
+        }
+        for (position, velocity) in position_chunks
+            .into_remainder()
+            .iter_mut()
+            .zip(velocity_chunks.remainder())
+        {
+            *position += velocity * dt;
+        }
+
+        // Rather than branching per particle, track whether any particle expired in each lane.
+        let mut expired = [false; LANES];
+        let mut lifetime_chunks = particles.lifetime.chunks_exact_mut(LANES);
+        for lifetime in &mut lifetime_chunks {
+            let mut lanes: [f32; LANES] = (*lifetime).try_into().unwrap();
+            for lane in 0..LANES {
+                lanes[lane] -= dt;
+                expired[lane] |= lanes[lane] <= 0.0;
+            }
+            lifetime.copy_from_slice(&lanes);
+        }
+        for lifetime in lifetime_chunks.into_remainder() {
+            *lifetime -= dt;
+            expired[0] |= *lifetime <= 0.0;
+        }
+
+        if expired.contains(&true) {
+            self.remove_expired();
+        }
+    }
+}
+
+impl Default for SoaParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spawns particles over time: a steady stream, bursts, or both.
+///
+/// Each particle starts at the emitter's position, heading in a direction within `spread` of its
+/// velocity, and lives for a random time within its lifetime range. Randomness comes from a seeded
+/// generator, so the same emitter always spawns the same particles.
+///
+/// ```
+/// use game_patterns_rs::particle::{Emitter, ParticleSystem};
+///
+/// let mut particles = ParticleSystem::new();
+/// let mut fountain = Emitter::new([0.0, 0.0])
+///     .with_rate(10.0)
+///     .with_burst(5)
+///     .with_velocity([0.0, 5.0], 0.5)
+///     .with_lifetime(1.0..2.0);
+///
+/// // The burst, and a tenth of a second's worth of the stream.
+/// assert_eq!(fountain.update(0.1, &mut particles), 6);
+/// assert_eq!(fountain.update(0.05, &mut particles), 0);
+/// assert_eq!(fountain.update(0.05, &mut particles), 1);
+/// particles.update(0.2);
+/// assert_eq!(particles.len(), 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    position: [f32; 2],
+    rate: f32,
+    burst: u32,
+    velocity: [f32; 2],
+    spread: f32,
+    lifetime: Range<f32>,
+
+    /// Particles owed by the stream, but not yet spawned; always less than one.
+    owed: f32,
+    bursts: u32,
+    rng: Rng,
+}
+
+impl Emitter {
+    /// Creates an emitter at `position` that spawns nothing until configured.
+    pub fn new(position: [f32; 2]) -> Self {
+        Self {
+            position,
+            rate: 0.0,
+            burst: 0,
+            velocity: [0.0, 0.0],
+            spread: 0.0,
+            lifetime: 1.0..1.0,
+            owed: 0.0,
+            bursts: 0,
+            rng: Rng(0x9E37_79B9_7F4A_7C15),
+        }
+    }
+
+    /// Spawns `rate` particles per second.
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Spawns `count` particles at once on the next update, and on every [`Emitter::burst`].
+    pub fn with_burst(mut self, count: u32) -> Self {
+        self.burst = count;
+        self.bursts = 1;
+        self
+    }
+
+    /// Starts particles at `velocity`, rotated by up to `spread / 2` radians either way.
+    pub fn with_velocity(mut self, velocity: [f32; 2], spread: f32) -> Self {
+        self.velocity = velocity;
+        self.spread = spread;
+        self
+    }
+
+    /// Gives particles a lifetime (in seconds) within `lifetime`.
+    pub fn with_lifetime(mut self, lifetime: Range<f32>) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Seeds the generator that picks each particle's direction and lifetime.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        // Xorshift never leaves zero, so avoid it.
+        self.rng = Rng(seed.max(1));
+        self
+    }
+
+    /// Moves the emitter; particles already spawned stay where they are.
+    pub fn set_position(&mut self, position: [f32; 2]) {
+        self.position = position;
+    }
+
+    /// Spawns another burst on the next update.
+    pub fn burst(&mut self) {
+        self.bursts += 1;
+    }
+
+    /// Returns the particles due after `dt` seconds, for spawning into any particle system.
+    pub fn emit(&mut self, dt: f32) -> impl Iterator<Item = Particle> + '_ {
+        self.owed += self.rate * dt;
+        let streamed = self.owed as u32;
+        self.owed -= streamed as f32;
+        let count = streamed + self.burst * std::mem::take(&mut self.bursts);
+        (0..count).map(|_| self.particle())
+    }
+
+    /// Spawns the particles due after `dt` seconds, returning how many were spawned.
+    ///
+    /// Particles that don't fit, because every particle is already active, are dropped.
+    pub fn update(&mut self, dt: f32, particles: &mut ParticleSystem) -> usize {
+        self.emit(dt)
+            .filter_map(|particle| particles.spawn(particle))
+            .count()
+    }
+
+    fn particle(&mut self) -> Particle {
+        let angle = (self.rng.next_f32() - 0.5) * self.spread;
+        let (sin, cos) = angle.sin_cos();
+        let [x, y] = self.velocity;
+        let lifetime = &self.lifetime;
+        Particle {
+            position: self.position,
+            velocity: [x * cos - y * sin, x * sin + y * cos],
+            lifetime: lifetime.start + (lifetime.end - lifetime.start) * self.rng.next_f32(),
+            ..Particle::default()
+        }
+    }
+}
+
+/// A xorshift generator: not random enough for anything but effects, but small and dependency-free.
+#[derive(Clone, Debug)]
+struct Rng(u64);
+
+impl Rng {
+    /// Returns a number in `0.0..1.0`.
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
@@ -0,0 +1,409 @@
+//! A fixed-size pool of objects, reused rather than allocated and freed one at a time.
+//!
+//! Every object is created up front. [`Pool::create`] hands out one that isn't in use (finding it
+//! in constant time, through a free list threaded through the unused objects) for the caller to
+//! re-initialize, and [`Pool::reclaim`] returns it to the pool. Nothing is allocated or freed in
+//! between, and whatever the object owns (i.e. a buffer) is kept for its next use:
+//!
+//! ```
+//! use game_patterns_rs::pool::Pool;
+//!
+//! let mut sounds: Pool<Vec<f32>> = Pool::new(2);
+//! let beep = sounds
+//!     .create(|samples| {
+//!         samples.clear();
+//!         samples.extend([0.0, 1.0, 0.0, -1.0]);
+//!     })
+//!     .unwrap();
+//! assert_eq!(sounds.get(beep).unwrap().len(), 4);
+//!
+//! assert!(sounds.reclaim(beep));
+//! assert_eq!(sounds.get(beep), None);
+//! ```
+//!
+//! Objects are referred to by [`Handle`]. Once an object is reclaimed, its old handles find nothing,
+//! even after the pool reuses it for a new object; holding on to a handle after reclaiming it is
+//! the classic bug with pools, and would otherwise mean silently using someone else's object:
+//!
+//! ```
+//! use game_patterns_rs::pool::Pool;
+//!
+//! let mut enemies: Pool<&str> = Pool::new(1);
+//! let goblin = enemies.create(|name| *name = "goblin").unwrap();
+//! enemies.reclaim(goblin);
+//! let troll = enemies.create(|name| *name = "troll").unwrap();
+//!
+//! assert_eq!(goblin.index(), troll.index());
+//! assert_eq!(enemies.get(goblin), None);
+//! assert!(!enemies.reclaim(goblin));
+//! assert_eq!(enemies.get(troll), Some(&"troll"));
+//! ```
+//!
+//! By default, a pool never grows, so creating an object can fail (see [`Exhaustion`] for other
+//! options, and [`Pool::stats`] for choosing a capacity):
+//!
+//! ```
+//! use game_patterns_rs::pool::{Pool, PoolFull};
+//!
+//! let mut pool: Pool<u32> = Pool::new(2);
+//! let first = pool.create(|n| *n = 1).unwrap();
+//! pool.create(|n| *n = 2).unwrap();
+//! assert!(pool.is_full());
+//! assert_eq!(pool.create(|n| *n = 3), Err(PoolFull));
+//!
+//! // Reclaiming an object makes room again, and the next object reuses its place.
+//! pool.reclaim(first);
+//! assert_eq!(pool.create(|n| *n = 3).unwrap().index(), first.index());
+//! assert_eq!(pool.iter().map(|(_, n)| *n).collect::<Vec<_>>(), [3, 2]);
+//! ```
+
+use std::{error::Error, fmt};
+
+/// A fixed number of objects of type `T`, each either in use or available to be created.
+pub struct Pool<T> {
+    objects: Vec<Object<T>>,
+    first_available: Option<usize>,
+    len: usize,
+    exhaustion: Exhaustion,
+    stats: PoolStats,
+
+    /// Makes objects, when the pool is created and whenever it grows.
+    make: Box<dyn FnMut() -> T>,
+}
+
+struct Object<T> {
+    value: T,
+    state: State,
+
+    /// Incremented each time the object is reclaimed, so old handles to it go stale.
+    generation: u32,
+
+    /// When the object was last created, counted in creations; the lowest is the oldest.
+    created: u64,
+}
+
+/// Refers to an object created by a [`Pool`], until that object is reclaimed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+impl Handle {
+    /// Returns where in the pool the object is; reused by later objects once it is reclaimed.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    InUse,
+
+    /// Links to the next available object, so finding one never needs a search.
+    Available {
+        next: Option<usize>,
+    },
+}
+
+/// What [`Pool::create`] does when every object is in use.
+///
+/// ```
+/// use game_patterns_rs::pool::{Exhaustion, Pool, PoolFull};
+///
+/// let mut pool: Pool<u8> = Pool::new(2).with_exhaustion(Exhaustion::Error);
+/// for n in 1..=2 {
+///     pool.create(|value| *value = n).unwrap();
+/// }
+/// assert_eq!(pool.create(|value| *value = 3), Err(PoolFull));
+///
+/// let mut pool: Pool<u8> = Pool::new(2).with_exhaustion(Exhaustion::Grow(4));
+/// for n in 1..=3 {
+///     pool.create(|value| *value = n).unwrap();
+/// }
+/// assert_eq!(pool.capacity(), 6);
+///
+/// let mut pool: Pool<u8> = Pool::new(2).with_exhaustion(Exhaustion::EvictOldest);
+/// let first = pool.create(|value| *value = 1).unwrap();
+/// for n in 2..=3 {
+///     pool.create(|value| *value = n).unwrap();
+/// }
+/// assert_eq!(pool.get(first), None);
+/// assert_eq!(pool.iter().map(|(_, n)| *n).collect::<Vec<_>>(), [3, 2]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Exhaustion {
+    /// Returns [`PoolFull`], creating nothing.
+    #[default]
+    Error,
+
+    /// Adds this many objects to the pool (which, unlike the others, allocates).
+    Grow(usize),
+
+    /// Reclaims the object created longest ago, and reuses it; its handles go stale.
+    ///
+    /// The book suggests this for particles: nobody notices an old spark vanish, but they do
+    /// notice a new one that never appears. Finding the oldest means looking at every object.
+    EvictOldest,
+}
+
+/// How a [`Pool`] has been used so far; useful for choosing its capacity.
+///
+/// ```
+/// use game_patterns_rs::pool::Pool;
+///
+/// let mut pool: Pool<u8> = Pool::new(2);
+/// let first = pool.create(|_| {}).unwrap();
+/// pool.create(|_| {}).unwrap();
+/// pool.reclaim(first);
+/// pool.create(|_| {}).unwrap();
+/// assert!(pool.create(|_| {}).is_err());
+///
+/// let stats = pool.stats();
+/// assert_eq!(stats.created, 3);
+/// assert_eq!(stats.high_water, 2);
+/// assert_eq!(stats.refused, 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoolStats {
+    /// Objects created, counting each reuse.
+    pub created: u64,
+
+    /// The most objects that have been in use at once.
+    pub high_water: usize,
+
+    /// Objects not created because the pool was full (see [`Exhaustion::Error`]).
+    pub refused: u64,
+
+    /// Objects reclaimed to make room for a new one (see [`Exhaustion::EvictOldest`]).
+    pub evicted: u64,
+
+    /// Objects added by growing the pool (see [`Exhaustion::Grow`]).
+    pub grown: usize,
+}
+
+/// Returned by [`Pool::create`] when every object is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolFull;
+
+impl fmt::Display for PoolFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("object pool is full")
+    }
+}
+
+impl Error for PoolFull {}
+
+impl<T: Default + 'static> Pool<T> {
+    /// Creates a pool of `capacity` objects, none of them in use, which returns an error when
+    /// every object is in use.
+    pub fn new(capacity: usize) -> Self {
+        Self::from_fn(capacity, T::default)
+    }
+}
+
+impl<T> Pool<T> {
+    /// Creates a pool of `capacity` objects made by `f`, none of them in use, which returns an
+    /// error when every object is in use.
+    pub fn from_fn(capacity: usize, f: impl FnMut() -> T + 'static) -> Self {
+        let mut pool = Self {
+            objects: Vec::new(),
+            first_available: None,
+            len: 0,
+            exhaustion: Exhaustion::default(),
+            stats: PoolStats::default(),
+            make: Box::new(f),
+        };
+        pool.grow(capacity);
+        pool
+    }
+
+    /// Does as `exhaustion` says when every object is in use.
+    ///
+    /// # Panics
+    ///
+    /// If asked to grow by zero objects.
+    pub fn with_exhaustion(mut self, exhaustion: Exhaustion) -> Self {
+        assert_ne!(exhaustion, Exhaustion::Grow(0), "must grow by at least one");
+        self.exhaustion = exhaustion;
+        self
+    }
+
+    /// Returns how the pool has been used so far.
+    pub fn stats(&self) -> &PoolStats {
+        &self.stats
+    }
+
+    /// Returns the number of objects in use.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no objects are in use.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of objects in the pool, in use or not.
+    pub fn capacity(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns whether every object is in use.
+    pub fn is_full(&self) -> bool {
+        self.first_available.is_none()
+    }
+
+    /// Takes an available object, re-initializes it with `init`, and returns a handle to it.
+    ///
+    /// The object is as it was when last reclaimed (or as the pool created it), so `init` should
+    /// reset everything the previous user might have changed. If every object is in use, does as
+    /// the pool's [`Exhaustion`] says.
+    pub fn create(&mut self, init: impl FnOnce(&mut T)) -> Result<Handle, PoolFull> {
+        if self.is_full() {
+            match self.exhaustion {
+                Exhaustion::Error => {
+                    self.stats.refused += 1;
+                    return Err(PoolFull);
+                }
+                Exhaustion::Grow(objects) => {
+                    self.grow(objects);
+                    self.stats.grown += objects;
+                }
+                Exhaustion::EvictOldest => {
+                    // A pool of no objects has nothing to evict.
+                    let Some(oldest) = self.oldest() else {
+                        self.stats.refused += 1;
+                        return Err(PoolFull);
+                    };
+                    self.reclaim_at(oldest);
+                    self.stats.evicted += 1;
+                }
+            }
+        }
+
+        let index = self.first_available.ok_or(PoolFull)?;
+        let object = &mut self.objects[index];
+        let State::Available { next } = object.state else {
+            unreachable!("the free list only links available objects");
+        };
+        self.first_available = next;
+        object.state = State::InUse;
+        object.created = self.stats.created;
+        init(&mut object.value);
+        self.len += 1;
+        self.stats.created += 1;
+        self.stats.high_water = self.stats.high_water.max(self.len);
+        Ok(Handle {
+            index,
+            generation: object.generation,
+        })
+    }
+
+    /// Returns the object to the pool, returning `false` if it was already reclaimed.
+    pub fn reclaim(&mut self, handle: Handle) -> bool {
+        if self.get(handle).is_none() {
+            return false;
+        }
+        self.reclaim_at(handle.index);
+        true
+    }
+
+    /// Returns the object, if it hasn't been reclaimed.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let object = self.objects.get(handle.index)?;
+        object.is_handled_by(handle).then_some(&object.value)
+    }
+
+    /// Returns the object, if it hasn't been reclaimed.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let object = self.objects.get_mut(handle.index)?;
+        object.is_handled_by(handle).then_some(&mut object.value)
+    }
+
+    /// Returns every object in use, with its handle.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| matches!(object.state, State::InUse))
+            .map(|(index, object)| (object.handle(index), &object.value))
+    }
+
+    /// Returns every object in use, with its handle.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        self.objects
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, object)| matches!(object.state, State::InUse))
+            .map(|(index, object)| (object.handle(index), &mut object.value))
+    }
+
+    /// Reclaims every object for which `f` returns `false`; returns how many were reclaimed.
+    pub fn retain(&mut self, mut f: impl FnMut(&mut T) -> bool) -> usize {
+        let mut reclaimed = 0;
+        for index in 0..self.objects.len() {
+            let object = &mut self.objects[index];
+            if matches!(object.state, State::InUse) && !f(&mut object.value) {
+                self.reclaim_at(index);
+                reclaimed += 1;
+            }
+        }
+        reclaimed
+    }
+
+    /// Adds `objects` available objects, at the front of the free list.
+    fn grow(&mut self, objects: usize) {
+        let start = self.objects.len();
+        for index in start..start + objects {
+            let next = if index + 1 < start + objects {
+                Some(index + 1)
+            } else {
+                self.first_available
+            };
+            self.objects.push(Object {
+                value: (self.make)(),
+                state: State::Available { next },
+                generation: 0,
+                created: 0,
+            });
+        }
+        if objects > 0 {
+            self.first_available = Some(start);
+        }
+    }
+
+    /// Returns the index of the object in use that was created longest ago.
+    fn oldest(&self) -> Option<usize> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| matches!(object.state, State::InUse))
+            .min_by_key(|(_, object)| object.created)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the in-use object at `index` to the free list.
+    fn reclaim_at(&mut self, index: usize) {
+        let object = &mut self.objects[index];
+        object.state = State::Available {
+            next: self.first_available,
+        };
+        object.generation += 1;
+        self.first_available = Some(index);
+        self.len -= 1;
+    }
+}
+
+impl<T> Object<T> {
+    fn handle(&self, index: usize) -> Handle {
+        Handle {
+            index,
+            generation: self.generation,
+        }
+    }
+
+    fn is_handled_by(&self, handle: Handle) -> bool {
+        matches!(self.state, State::InUse) && self.generation == handle.generation
+    }
+}
@@ -0,0 +1,188 @@
+//! Prototype inheritance for any kind of data (monsters, items, spells, levels).
+//!
+//! Each entry is keyed by name and may list prototypes, whose fields it inherits and overrides. A
+//! type opts in by implementing [`Merge`]:
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! use game_patterns_rs::prototype::{Merge, PrototypeRegistry};
+//!
+//! #[derive(Clone, Default)]
+//! struct Item {
+//!     prototype: Vec<String>,
+//!     damage: Option<u8>,
+//!     weight: Option<u8>,
+//! }
+//!
+//! impl Merge for Item {
+//!     fn prototypes(&self) -> &[String] {
+//!         &self.prototype
+//!     }
+//!
+//!     fn merge(&mut self, other: &Self) {
+//!         self.damage = other.damage.or(self.damage);
+//!         self.weight = other.weight.or(self.weight);
+//!     }
+//! }
+//!
+//! let registry = PrototypeRegistry::new(BTreeMap::from([
+//!     ("sword".to_string(), Item { damage: Some(5), weight: Some(3), ..Item::default() }),
+//!     ("flaming-sword".to_string(), Item {
+//!         prototype: vec!["sword".to_string()],
+//!         damage: Some(8),
+//!         ..Item::default()
+//!     }),
+//! ]));
+//!
+//! let sword = registry.resolve("flaming-sword").unwrap();
+//! assert_eq!((sword.damage, sword.weight), (Some(8), Some(3)));
+//! ```
+
+use std::{collections::BTreeMap, fmt};
+
+/// Data that can inherit from prototypes of the same type.
+pub trait Merge: Clone + Default {
+    /// Returns the keys of this entry's prototypes, in the order they are merged.
+    fn prototypes(&self) -> &[String];
+
+    /// Merges `other` into `self`, where any field `other` defines takes precedence.
+    fn merge(&mut self, other: &Self);
+}
+
+/// Entries keyed by name, which are resolved (merged with their prototypes) on request.
+pub struct PrototypeRegistry<T> {
+    entries: BTreeMap<String, T>,
+}
+
+impl<T: Merge> PrototypeRegistry<T> {
+    /// Creates a registry of the given (unresolved) entries.
+    pub fn new(entries: BTreeMap<String, T>) -> Self {
+        PrototypeRegistry { entries }
+    }
+
+    /// Returns the entry with the given key as written, without its prototypes merged.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.entries.get(key)
+    }
+
+    /// Returns every entry as written, in the order of their keys.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.entries
+            .iter()
+            .map(|(key, entry)| (key.as_str(), entry))
+    }
+
+    /// Returns the entry with the given key, merged on top of its prototypes (and theirs).
+    ///
+    /// Merging starts from `T::default()`, and each prototype is merged before the entries that
+    /// refer to it.
+    ///
+    /// # Errors
+    ///
+    /// If the key, or a prototype in its chain, doesn't exist or refers back to itself.
+    pub fn resolve(&self, key: &str) -> Result<T, ChainError> {
+        if !self.entries.contains_key(key) {
+            return Err(ChainError::Unknown {
+                key: key.to_string(),
+            });
+        }
+        let mut chain = Vec::new();
+        self.resolve_chain(key, &mut Vec::new(), &mut chain)?;
+
+        let mut resolved = T::default();
+        for entry in chain {
+            resolved.merge(entry);
+        }
+        Ok(resolved)
+    }
+
+    /// Resolves every entry, in the order of their keys.
+    ///
+    /// Each problem is reported once, by the entry it belongs to (for a cycle, the first key in it);
+    /// entries that only inherit a problem are left out, without reporting it again.
+    pub fn resolve_all(&self) -> (BTreeMap<String, T>, Vec<ChainError>) {
+        let mut resolved = BTreeMap::new();
+        let mut errors = Vec::new();
+        for key in self.entries.keys() {
+            match self.resolve(key) {
+                Ok(entry) => {
+                    resolved.insert(key.clone(), entry);
+                }
+                Err(error) => {
+                    let belongs_to_key = match &error {
+                        ChainError::UnknownPrototype { key: owner, .. } => owner == key,
+                        ChainError::Cycle { chain } => chain.iter().min() == Some(key),
+                        ChainError::Unknown { .. } => true,
+                    };
+                    if belongs_to_key {
+                        errors.push(error);
+                    }
+                }
+            }
+        }
+        (resolved, errors)
+    }
+
+    /// Appends the entries that make up `key` to `chain`, each after its own prototypes.
+    ///
+    /// `path` holds the keys currently being resolved, so that a prototype that (eventually) refers
+    /// back to itself is reported rather than looping.
+    fn resolve_chain<'a>(
+        &'a self,
+        key: &str,
+        path: &mut Vec<String>,
+        chain: &mut Vec<&'a T>,
+    ) -> Result<(), ChainError> {
+        if let Some(start) = path.iter().position(|visited| visited == key) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(key.to_string());
+            return Err(ChainError::Cycle { chain: cycle });
+        }
+
+        let entry = &self.entries[key];
+        path.push(key.to_string());
+        for prototype in entry.prototypes() {
+            if !self.entries.contains_key(prototype) {
+                return Err(ChainError::UnknownPrototype {
+                    key: key.to_string(),
+                    prototype: prototype.clone(),
+                });
+            }
+            self.resolve_chain(prototype, path, chain)?;
+        }
+        path.pop();
+
+        chain.push(entry);
+        Ok(())
+    }
+}
+
+/// Why an entry's chain of prototypes could not be resolved.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChainError {
+    /// There is no entry with the requested key.
+    Unknown { key: String },
+
+    /// An entry refers to a prototype that doesn't exist.
+    UnknownPrototype { key: String, prototype: String },
+
+    /// A prototype refers (directly or through other prototypes) back to itself.
+    Cycle { chain: Vec<String> },
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::Unknown { key } => write!(f, "[{key}] does not exist"),
+            ChainError::UnknownPrototype { key, prototype } => {
+                write!(f, "[{key}] refers to unknown prototype \"{prototype}\"")
+            }
+            ChainError::Cycle { chain } => {
+                write!(f, "Prototypes form a cycle: {}", chain.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
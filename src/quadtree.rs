@@ -0,0 +1,394 @@
+//! A spatial partition that adapts to where objects are, rather than dividing space evenly.
+//!
+//! A fixed grid (see [`crate::grid`]) is simple and fast while objects
+//! are spread out, but crowds all end up in one cell, and it goes back to comparing everything
+//! with everything. A [`QuadTree`] instead splits any area holding too many objects into four
+//! quarters, and those into quarters again, so crowded areas are divided finely and empty ones not
+//! at all:
+//!
+//! ```
+//! use game_patterns_rs::quadtree::{QuadTree, Rect};
+//!
+//! let mut units = QuadTree::new(Rect::new(0.0, 0.0, 100.0, 100.0));
+//! units.insert([10.0, 10.0], "knight").unwrap();
+//! units.insert([12.0, 11.0], "archer").unwrap();
+//! units.insert([90.0, 90.0], "dragon").unwrap();
+//!
+//! let mut nearby: Vec<_> = units
+//!     .query(Rect::around([11.0, 11.0], 5.0))
+//!     .map(|(_, unit)| *unit)
+//!     .collect();
+//! nearby.sort();
+//! assert_eq!(nearby, ["archer", "knight"]);
+//! ```
+//!
+//! An area splits once it holds more than a threshold of objects, and its quarters merge back once
+//! it holds no more than a second, lower threshold; the gap between them means an object moving
+//! back and forth over the line doesn't split and merge the tree every frame:
+//!
+//! ```
+//! use game_patterns_rs::quadtree::{QuadTree, Rect};
+//!
+//! let mut tree = QuadTree::new(Rect::new(0.0, 0.0, 64.0, 64.0)).with_thresholds(4, 2);
+//! for i in 0..5 {
+//!     tree.insert([i as f32, i as f32], i).unwrap();
+//! }
+//! // They are all in the top left, so it splits again and again to separate them.
+//! assert_eq!(tree.stats().splits, 4);
+//! assert_eq!(tree.depth(), 4);
+//!
+//! // Down to three objects: not enough to split, but not few enough to merge.
+//! tree.remove([4.0, 4.0], &4);
+//! tree.remove([3.0, 3.0], &3);
+//! assert_eq!(tree.stats().merges, 0);
+//!
+//! tree.remove([2.0, 2.0], &2);
+//! assert_eq!(tree.depth(), 0);
+//! assert_eq!(tree.len(), 2);
+//! ```
+
+use std::{error::Error, fmt, mem};
+
+/// An axis-aligned rectangle, including its top and left edges but not its bottom and right.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the square reaching `radius` from `center` in each direction.
+    pub fn around([x, y]: [f32; 2], radius: f32) -> Self {
+        Self::new(x - radius, y - radius, radius * 2.0, radius * 2.0)
+    }
+
+    /// Returns whether a point is inside the rectangle.
+    pub fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Returns whether the rectangles overlap.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Returns the quarter a point is in, as an index into [`Rect::quarters`].
+    fn quarter(&self, [x, y]: [f32; 2]) -> usize {
+        let right = x >= self.x + self.width / 2.0;
+        let bottom = y >= self.y + self.height / 2.0;
+        right as usize + 2 * bottom as usize
+    }
+
+    /// Returns the top left, top right, bottom left and bottom right quarters.
+    fn quarters(&self) -> [Rect; 4] {
+        let (width, height) = (self.width / 2.0, self.height / 2.0);
+        let (mid_x, mid_y) = (self.x + width, self.y + height);
+        [
+            Rect::new(self.x, self.y, width, height),
+            Rect::new(mid_x, self.y, self.width - width, height),
+            Rect::new(self.x, mid_y, width, self.height - height),
+            Rect::new(mid_x, mid_y, self.width - width, self.height - height),
+        ]
+    }
+}
+
+/// Values of type `T`, each at a point, divided into ever smaller areas where they are crowded.
+pub struct QuadTree<T> {
+    root: Node<T>,
+    len: usize,
+    split_above: usize,
+    merge_at: usize,
+    max_depth: usize,
+    stats: QuadTreeStats,
+}
+
+struct Node<T> {
+    bounds: Rect,
+
+    /// How many values are in this area, including in its quarters.
+    len: usize,
+    kind: Kind<T>,
+}
+
+enum Kind<T> {
+    Leaf(Vec<([f32; 2], T)>),
+    Branch(Box<[Node<T>; 4]>),
+}
+
+/// How often a [`QuadTree`] has changed shape; useful for choosing its thresholds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QuadTreeStats {
+    /// Times an area was split into quarters.
+    pub splits: u64,
+
+    /// Times an area's quarters were merged back into it.
+    pub merges: u64,
+}
+
+/// Returned when inserting a value at a point outside a [`QuadTree`]'s bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfBounds;
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("position is outside the quadtree")
+    }
+}
+
+impl Error for OutOfBounds {}
+
+impl<T> QuadTree<T> {
+    /// Creates an empty tree covering `bounds`, splitting areas of more than 8 values and merging
+    /// those of 4 or fewer, at most 8 times deep.
+    pub fn new(bounds: Rect) -> Self {
+        Self {
+            root: Node::leaf(bounds),
+            len: 0,
+            split_above: 8,
+            merge_at: 4,
+            max_depth: 8,
+            stats: QuadTreeStats::default(),
+        }
+    }
+
+    /// Splits areas holding more than `split_above` values, and merges them back once they hold
+    /// `merge_at` or fewer.
+    ///
+    /// # Panics
+    ///
+    /// If `merge_at` isn't less than `split_above`, as areas would merge as soon as they split.
+    pub fn with_thresholds(mut self, split_above: usize, merge_at: usize) -> Self {
+        assert!(
+            merge_at < split_above,
+            "merge threshold must be below the split threshold"
+        );
+        self.split_above = split_above;
+        self.merge_at = merge_at;
+        self
+    }
+
+    /// Stops splitting areas `max_depth` quarters deep, however many values they hold; otherwise,
+    /// enough values at one point would be split forever.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns the area the tree covers.
+    pub fn bounds(&self) -> Rect {
+        self.root.bounds
+    }
+
+    /// Adds a value at a point, splitting its area if that makes it too crowded.
+    pub fn insert(&mut self, position: [f32; 2], value: T) -> Result<(), OutOfBounds> {
+        if !self.root.bounds.contains(position) {
+            return Err(OutOfBounds);
+        }
+        let (split_above, max_depth) = (self.split_above, self.max_depth);
+        self.root
+            .insert(position, value, 0, split_above, max_depth, &mut self.stats);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes a value at a point, returning whether it was found, and merging areas that become
+    /// sparse enough.
+    pub fn remove(&mut self, position: [f32; 2], value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let removed = self
+            .root
+            .remove(position, value, self.merge_at, &mut self.stats);
+        self.len -= removed as usize;
+        removed
+    }
+
+    /// Returns every value at a point inside `area`, in no particular order.
+    ///
+    /// Only values in the areas overlapping `area` are looked at.
+    pub fn query(&self, area: Rect) -> impl Iterator<Item = ([f32; 2], &T)> + '_ {
+        let mut stack = vec![&self.root];
+        let mut values = [].iter();
+        std::iter::from_fn(move || loop {
+            if let Some((position, value)) = values.by_ref().find(|(p, _)| area.contains(*p)) {
+                return Some((*position, value));
+            }
+            let node = stack.pop()?;
+            match &node.kind {
+                Kind::Leaf(leaf) => values = leaf.iter(),
+                Kind::Branch(quarters) => stack.extend(
+                    quarters
+                        .iter()
+                        .filter(|quarter| quarter.len > 0 && quarter.bounds.intersects(&area)),
+                ),
+            }
+        })
+    }
+
+    /// Returns the number of values in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the tree holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns how many times the most finely divided area has been split.
+    pub fn depth(&self) -> usize {
+        self.root.depth()
+    }
+
+    /// Returns how often the tree has changed shape so far.
+    pub fn stats(&self) -> &QuadTreeStats {
+        &self.stats
+    }
+}
+
+impl<T> Node<T> {
+    fn leaf(bounds: Rect) -> Self {
+        Self {
+            bounds,
+            len: 0,
+            kind: Kind::Leaf(Vec::new()),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        position: [f32; 2],
+        value: T,
+        depth: usize,
+        split_above: usize,
+        max_depth: usize,
+        stats: &mut QuadTreeStats,
+    ) {
+        self.len += 1;
+        match &mut self.kind {
+            Kind::Branch(quarters) => quarters[self.bounds.quarter(position)].insert(
+                position,
+                value,
+                depth + 1,
+                split_above,
+                max_depth,
+                stats,
+            ),
+            Kind::Leaf(values) => {
+                values.push((position, value));
+                if self.len > split_above && depth < max_depth {
+                    self.split(depth, split_above, max_depth, stats);
+                }
+            }
+        }
+    }
+
+    /// Moves this leaf's values into four new quarters, splitting those in turn if still crowded.
+    fn split(
+        &mut self,
+        depth: usize,
+        split_above: usize,
+        max_depth: usize,
+        stats: &mut QuadTreeStats,
+    ) {
+        let quarters = self.bounds.quarters().map(Node::leaf);
+        let Kind::Leaf(values) = mem::replace(&mut self.kind, Kind::Branch(Box::new(quarters)))
+        else {
+            unreachable!("only leaves are split");
+        };
+        stats.splits += 1;
+
+        let Kind::Branch(quarters) = &mut self.kind else {
+            unreachable!();
+        };
+        for (position, value) in values {
+            let quarter = &mut quarters[self.bounds.quarter(position)];
+            quarter.len += 1;
+            let Kind::Leaf(values) = &mut quarter.kind else {
+                unreachable!("new quarters are leaves");
+            };
+            values.push((position, value));
+        }
+        if depth + 1 < max_depth {
+            for quarter in quarters.iter_mut() {
+                if quarter.len > split_above {
+                    quarter.split(depth + 1, split_above, max_depth, stats);
+                }
+            }
+        }
+    }
+
+    fn remove(
+        &mut self,
+        position: [f32; 2],
+        value: &T,
+        merge_at: usize,
+        stats: &mut QuadTreeStats,
+    ) -> bool
+    where
+        T: PartialEq,
+    {
+        let removed = match &mut self.kind {
+            Kind::Leaf(values) => {
+                match values
+                    .iter()
+                    .position(|(p, v)| *p == position && v == value)
+                {
+                    Some(index) => {
+                        values.swap_remove(index);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Kind::Branch(quarters) => {
+                quarters[self.bounds.quarter(position)].remove(position, value, merge_at, stats)
+            }
+        };
+        if !removed {
+            return false;
+        }
+        self.len -= 1;
+        if self.len <= merge_at && matches!(self.kind, Kind::Branch(_)) {
+            let mut values = Vec::with_capacity(self.len);
+            self.drain_into(&mut values);
+            self.kind = Kind::Leaf(values);
+            stats.merges += 1;
+        }
+        true
+    }
+
+    /// Moves every value in this area into `out`, leaving it an empty leaf.
+    fn drain_into(&mut self, out: &mut Vec<([f32; 2], T)>) {
+        match mem::replace(&mut self.kind, Kind::Leaf(Vec::new())) {
+            Kind::Leaf(values) => out.extend(values),
+            Kind::Branch(mut quarters) => {
+                for quarter in quarters.iter_mut() {
+                    quarter.drain_into(out);
+                }
+            }
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match &self.kind {
+            Kind::Leaf(_) => 0,
+            Kind::Branch(quarters) => 1 + quarters.iter().map(Node::depth).max().unwrap_or(0),
+        }
+    }
+}
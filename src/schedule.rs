@@ -0,0 +1,150 @@
+//! Runs systems (functions over the whole game world) each tick, in an order derived from their
+//! declared dependencies rather than from where they happen to be called.
+//!
+//! ```
+//! use game_patterns_rs::schedule::Schedule;
+//!
+//! let mut schedule = Schedule::builder()
+//!     .add("graphics", &["physics"], |log: &mut Vec<&str>| log.push("graphics"))
+//!     .add("input", &[], |log: &mut Vec<&str>| log.push("input"))
+//!     .add("physics", &["input"], |log: &mut Vec<&str>| log.push("physics"))
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut log = Vec::new();
+//! schedule.run(&mut log);
+//! assert_eq!(log, ["input", "physics", "graphics"]);
+//! ```
+
+use std::{error::Error, fmt};
+
+/// A function run over the world `W` once per tick.
+type System<'a, W> = Box<dyn FnMut(&mut W) + 'a>;
+
+/// Systems, sorted so that each runs after the systems it depends on.
+pub struct Schedule<'a, W> {
+    names: Vec<&'static str>,
+    systems: Vec<System<'a, W>>,
+}
+
+impl<'a, W> Schedule<'a, W> {
+    pub fn builder() -> ScheduleBuilder<'a, W> {
+        ScheduleBuilder {
+            systems: Vec::new(),
+        }
+    }
+
+    /// Returns the names of the systems, in the order they run.
+    pub fn order(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    /// Runs every system once (one tick), in order.
+    pub fn run(&mut self, world: &mut W) {
+        for system in &mut self.systems {
+            system(world);
+        }
+    }
+}
+
+/// Collects systems and their dependencies; see [`Schedule::builder`].
+pub struct ScheduleBuilder<'a, W> {
+    systems: Vec<(&'static str, &'static [&'static str], System<'a, W>)>,
+}
+
+impl<'a, W> ScheduleBuilder<'a, W> {
+    /// Adds a system, which runs after every system named in `after`.
+    ///
+    /// Systems that don't depend on each other run in the order they were added.
+    pub fn add(
+        mut self,
+        name: &'static str,
+        after: &'static [&'static str],
+        system: impl FnMut(&mut W) + 'a,
+    ) -> Self {
+        self.systems.push((name, after, Box::new(system)));
+        self
+    }
+
+    /// Sorts the systems by their dependencies.
+    ///
+    /// # Errors
+    ///
+    /// If two systems have the same name, a system depends on one that doesn't exist, or systems
+    /// depend on each other.
+    pub fn build(self) -> Result<Schedule<'a, W>, ScheduleError> {
+        for (index, (name, after, _)) in self.systems.iter().enumerate() {
+            if self.systems[..index]
+                .iter()
+                .any(|(other, ..)| other == name)
+            {
+                return Err(ScheduleError::Duplicate(name));
+            }
+            if let Some(missing) = after
+                .iter()
+                .find(|dependency| !self.systems.iter().any(|(other, ..)| other == *dependency))
+            {
+                return Err(ScheduleError::UnknownDependency {
+                    system: name,
+                    dependency: missing,
+                });
+            }
+        }
+
+        let mut pending: Vec<_> = self.systems.into_iter().map(Some).collect();
+        let mut schedule = Schedule {
+            names: Vec::new(),
+            systems: Vec::new(),
+        };
+        while schedule.names.len() < pending.len() {
+            let ready = pending.iter().position(|system| {
+                system.as_ref().is_some_and(|(_, after, _)| {
+                    after
+                        .iter()
+                        .all(|dependency| schedule.names.contains(dependency))
+                })
+            });
+            let Some(ready) = ready else {
+                return Err(ScheduleError::Cycle(
+                    pending.iter().flatten().map(|(name, ..)| *name).collect(),
+                ));
+            };
+            let (name, _, system) = pending[ready].take().unwrap();
+            schedule.names.push(name);
+            schedule.systems.push(system);
+        }
+        Ok(schedule)
+    }
+}
+
+/// Why systems could not be ordered.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// Two systems have the same name.
+    Duplicate(&'static str),
+
+    /// A system depends on a system that doesn't exist.
+    UnknownDependency {
+        system: &'static str,
+        dependency: &'static str,
+    },
+
+    /// The systems (directly or indirectly) depend on each other.
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Duplicate(name) => write!(f, "{name} is added more than once"),
+            ScheduleError::UnknownDependency { system, dependency } => {
+                write!(f, "{system} runs after {dependency}, which doesn't exist")
+            }
+            ScheduleError::Cycle(systems) => {
+                write!(f, "{} depend on each other", systems.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for ScheduleError {}
@@ -0,0 +1,129 @@
+//! A struct of arrays, for any struct: the [`soa!`](crate::soa) macro.
+
+/// Declares a struct of arrays for the struct `$item`: one `Vec` per field, with the same names.
+///
+/// Loops over a single field (the point of storing data this way) use the arrays directly; the
+/// generated methods keep every array the same length, and move whole items in and out:
+///
+/// ```
+/// use game_patterns_rs::soa;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Enemy {
+///     health: u32,
+///     name: String,
+/// }
+///
+/// soa! {
+///     /// Every enemy, one array per field.
+///     struct Enemies for Enemy {
+///         health: u32,
+///         name: String,
+///     }
+/// }
+///
+/// let mut enemies = Enemies::new();
+/// enemies.push(Enemy { health: 10, name: "goblin".into() });
+/// enemies.push(Enemy { health: 25, name: "orc".into() });
+///
+/// // Only touches the health array.
+/// for health in &mut enemies.health {
+///     *health -= 5;
+/// }
+///
+/// assert_eq!(enemies.get(1), Enemy { health: 20, name: "orc".into() });
+/// assert_eq!(enemies.swap_remove(0).name, "goblin");
+/// assert_eq!(enemies.len(), 1);
+/// ```
+///
+/// The arrays are fields of the generated struct, so (like any private field) they can only be
+/// used directly in the module that declares it.
+#[macro_export]
+macro_rules! soa {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident for $item:ident {
+            $first:ident : $first_ty:ty $(, $field:ident : $ty:ty)* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        $vis struct $name {
+            $first: ::std::vec::Vec<$first_ty>,
+            $($field: ::std::vec::Vec<$ty>,)*
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// Creates an empty struct of arrays.
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Creates an empty struct of arrays, with room for `capacity` items in each array.
+            pub fn with_capacity(capacity: usize) -> Self {
+                Self {
+                    $first: ::std::vec::Vec::with_capacity(capacity),
+                    $($field: ::std::vec::Vec::with_capacity(capacity),)*
+                }
+            }
+
+            /// Returns the number of items.
+            pub fn len(&self) -> usize {
+                self.$first.len()
+            }
+
+            /// Returns whether there are no items.
+            pub fn is_empty(&self) -> bool {
+                self.$first.is_empty()
+            }
+
+            /// Adds an item, splitting its fields between the arrays.
+            pub fn push(&mut self, item: $item) {
+                self.$first.push(item.$first);
+                $(self.$field.push(item.$field);)*
+            }
+
+            /// Returns a copy of the item at `index`, gathered from every array.
+            ///
+            /// Only callable if every field is `Clone`.
+            // The `for<'a>` defers checking the bounds until this is called.
+            pub fn get(&self, index: usize) -> $item
+            where
+                for<'a> $first_ty: Clone,
+                $(for<'a> $ty: Clone,)*
+            {
+                $item {
+                    $first: self.$first[index].clone(),
+                    $($field: self.$field[index].clone(),)*
+                }
+            }
+
+            /// Replaces the item at `index`.
+            pub fn set(&mut self, index: usize, item: $item) {
+                self.$first[index] = item.$first;
+                $(self.$field[index] = item.$field;)*
+            }
+
+            /// Removes and returns the item at `index`, moving the last item into its place.
+            pub fn swap_remove(&mut self, index: usize) -> $item {
+                $item {
+                    $first: self.$first.swap_remove(index),
+                    $($field: self.$field.swap_remove(index),)*
+                }
+            }
+
+            /// Swaps the items at `a` and `b`.
+            pub fn swap(&mut self, a: usize, b: usize) {
+                self.$first.swap(a, b);
+                $(self.$field.swap(a, b);)*
+            }
+
+            /// Removes every item.
+            pub fn clear(&mut self) {
+                self.$first.clear();
+                $(self.$field.clear();)*
+            }
+        }
+    };
+}
@@ -0,0 +1,88 @@
+//! A string interned as a small, copyable id; the flyweight pattern applied to names.
+//!
+//! Games compare names (of sounds, breeds, events) far more often than they create them. Interning
+//! a name once turns every later comparison and hash into an integer operation:
+//!
+//! ```
+//! use game_patterns_rs::symbol::Symbol;
+//!
+//! let a = Symbol::intern("goblin");
+//! let b = Symbol::intern("goblin");
+//! assert_eq!(a, b);
+//! assert_eq!(a.as_str(), "goblin");
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+/// An interned string, which is as cheap to copy, compare, and hash as a `u32`.
+///
+/// Every symbol comes from one global table, so the same name is always the same symbol, on any
+/// thread. Interned names are never freed, so intern a bounded set of names (asset names, event
+/// names), not arbitrary user input.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the symbol for the given name, interning it if it has not been seen before.
+    ///
+    /// # Panics
+    ///
+    /// If more than `u32::MAX` names are interned.
+    pub fn intern(name: &str) -> Symbol {
+        SymbolTable::global().lock().unwrap().intern(name)
+    }
+
+    /// Returns the name this symbol was interned from.
+    pub fn as_str(self) -> &'static str {
+        SymbolTable::global().lock().unwrap().names[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Symbol::intern(value)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Maps names to symbols, and back; there is only the one, behind [`Symbol::intern`], so every
+/// symbol can be resolved.
+#[derive(Default)]
+struct SymbolTable {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, Symbol>,
+}
+
+impl SymbolTable {
+    /// Returns the table; only ever locked for the length of a lookup, never while calling out.
+    fn global() -> &'static Mutex<SymbolTable> {
+        static GLOBAL: OnceLock<Mutex<SymbolTable>> = OnceLock::new();
+        GLOBAL.get_or_init(Default::default)
+    }
+
+    fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.ids.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(u32::try_from(self.names.len()).expect("Too many symbols"));
+        let name: &'static str = Box::leak(name.into());
+        self.names.push(name);
+        self.ids.insert(name, symbol);
+        symbol
+    }
+}
@@ -0,0 +1,169 @@
+//! Positions relative to a parent entity (a turret on a tank), for the [`ecs`](crate::ecs).
+//!
+//! An entity's [`Transform`] is relative to its parent, if it has one (see [`set_parent`]), so
+//! moving the tank moves the turret with it. Systems that need to know where things actually are
+//! (rendering, collision) read the [`GlobalTransform`] that [`propagate`] computes each frame:
+//!
+//! ```
+//! use game_patterns_rs::{
+//!     ecs::World,
+//!     transform::{self, GlobalTransform, Transform},
+//! };
+//!
+//! let mut world = World::new();
+//! let tank = world.spawn();
+//! world.insert(tank, Transform::at(10.0, 0.0));
+//! let turret = world.spawn();
+//! world.insert(turret, Transform::at(0.0, 1.0));
+//! transform::set_parent(&mut world, turret, tank);
+//!
+//! world.get_mut::<Transform>(tank).unwrap().x += 5.0;
+//! transform::propagate(&mut world);
+//!
+//! let global = world.get::<GlobalTransform>(turret).unwrap().0;
+//! assert_eq!((global.x, global.y), (15.0, 1.0));
+//! ```
+
+use crate::ecs::{Entity, World};
+
+/// Where an entity is, and which way it faces, relative to its parent (or the world, if none).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+}
+
+impl Transform {
+    /// Returns a transform at the given position, without rotation.
+    pub fn at(x: f32, y: f32) -> Self {
+        Transform {
+            x,
+            y,
+            rotation: 0.0,
+        }
+    }
+
+    /// Returns `local`, which is relative to this transform, relative to what this one is.
+    pub fn combine(self, local: Transform) -> Transform {
+        let (sin, cos) = self.rotation.sin_cos();
+        Transform {
+            x: self.x + local.x * cos - local.y * sin,
+            y: self.y + local.x * sin + local.y * cos,
+            rotation: self.rotation + local.rotation,
+        }
+    }
+}
+
+/// Where an entity is in the world, as of the last [`propagate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GlobalTransform(pub Transform);
+
+/// The entity that an entity's [`Transform`] is relative to; see [`set_parent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Parent {
+    pub fn get(self) -> Entity {
+        self.0
+    }
+}
+
+/// The entities whose [`Transform`] is relative to an entity's; see [`set_parent`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.0
+    }
+}
+
+/// Makes `child`'s transform relative to `parent`, detaching it from its previous parent, if any.
+///
+/// # Panics
+///
+/// If either entity is not alive, or `child` is `parent` or one of its ancestors.
+pub fn set_parent(world: &mut World, child: Entity, parent: Entity) {
+    assert!(world.is_alive(child), "{child:?} is not alive");
+    assert!(world.is_alive(parent), "{parent:?} is not alive");
+    let mut ancestor = Some(parent);
+    while let Some(entity) = ancestor {
+        assert_ne!(entity, child, "{child:?} cannot be its own ancestor");
+        ancestor = world.get::<Parent>(entity).map(|parent| parent.get());
+    }
+
+    remove_parent(world, child);
+    world.insert(child, Parent(parent));
+    let added = world.get_mut::<Children>(parent).map(|mut children| {
+        children.0.push(child);
+    });
+    if added.is_none() {
+        world.insert(parent, Children(vec![child]));
+    }
+}
+
+/// Makes `child`'s transform relative to the world again, returning its previous parent, if any.
+pub fn remove_parent(world: &mut World, child: Entity) -> Option<Entity> {
+    let parent = world.remove::<Parent>(child)?.get();
+    let empty = world
+        .get_mut::<Children>(parent)
+        .is_some_and(|mut children| {
+            children.0.retain(|&entity| entity != child);
+            children.0.is_empty()
+        });
+    if empty {
+        world.remove::<Children>(parent);
+    }
+    Some(parent)
+}
+
+/// Despawns an entity, its children, their children, and so on, returning `false` if the entity
+/// was not alive.
+pub fn despawn_recursive(world: &mut World, entity: Entity) -> bool {
+    if !world.is_alive(entity) {
+        return false;
+    }
+    remove_parent(world, entity);
+    let mut stack = vec![entity];
+    while let Some(entity) = stack.pop() {
+        if let Some(children) = world.remove::<Children>(entity) {
+            stack.extend(children.0);
+        }
+        world.despawn(entity);
+    }
+    true
+}
+
+/// Updates the [`GlobalTransform`] of every entity with a [`Transform`], parents first.
+///
+/// Entities whose parent has no transform are skipped (along with their children), and keep
+/// their previous global transform, if any. Global transforms are written straight to their
+/// storage, so [observers](World::observe) aren't told when one is first attached.
+pub fn propagate(world: &mut World) {
+    world.register::<Transform>();
+    world.register::<Parent>();
+    world.register::<Children>();
+    world.register::<GlobalTransform>();
+
+    let transforms = world.storage::<Transform>();
+    let parents = world.storage::<Parent>();
+    let children = world.storage::<Children>();
+    let mut globals = world.storage_mut::<GlobalTransform>();
+
+    let mut stack: Vec<(Entity, Transform)> = transforms
+        .iter()
+        .filter(|(entity, _)| !parents.contains(*entity))
+        .map(|(entity, transform)| (entity, *transform))
+        .collect();
+    while let Some((entity, global)) = stack.pop() {
+        globals.insert(entity, GlobalTransform(global));
+        for &child in children.get(entity).map_or(&[][..], Children::as_slice) {
+            if let Some(local) = transforms.get(child) {
+                stack.push((child, global.combine(*local)));
+            }
+        }
+    }
+}